@@ -0,0 +1,184 @@
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use time::OffsetDateTime;
+
+use policy_engine::LiveRules;
+use winewarden_core::types::{ControlRecord, ControlResult, SessionAction};
+
+use crate::signals::{process_tree, signal_tree, WineWardenSignal};
+
+/// How long a game gets to exit after wineserver asks it to before the
+/// remaining processes are killed.
+pub const KILL_GRACE: Duration = Duration::from_secs(5);
+const EXIT_POLL: Duration = Duration::from_millis(100);
+
+/// Lets another thread pause, resume or kill a session, or replace its
+/// rules, while `Monitor::run` is watching it. Clones share state; actions
+/// taken end up in the report.
+#[derive(Clone, Default)]
+pub struct SessionControl {
+    state: Arc<Mutex<ControlState>>,
+}
+
+#[derive(Default)]
+struct ControlState {
+    target: Option<Target>,
+    paused: bool,
+    records: Vec<ControlRecord>,
+    pending_rules: Option<LiveRules>,
+}
+
+#[derive(Clone)]
+struct Target {
+    root_pid: u32,
+    prefix_root: PathBuf,
+}
+
+impl SessionControl {
+    pub fn is_paused(&self) -> bool {
+        self.lock().paused
+    }
+
+    /// Applies `action` to the session's process tree and records the
+    /// outcome. Fails only if the session has no running process.
+    pub fn apply(&self, action: SessionAction) -> Result<ControlRecord> {
+        let target = self
+            .lock()
+            .target
+            .clone()
+            .ok_or_else(|| anyhow!("session has no running process"))?;
+        let processes = process_tree(target.root_pid).len();
+        let result = match action {
+            SessionAction::Pause => self.set_paused(&target, true),
+            SessionAction::Resume => self.set_paused(&target, false),
+            SessionAction::Kill => self.kill(&target),
+        };
+        let record = ControlRecord {
+            timestamp: OffsetDateTime::now_utc(),
+            action,
+            processes,
+            result,
+        };
+        self.lock().records.push(record.clone());
+        Ok(record)
+    }
+
+    /// Hands new rules to the session; the monitor picks them up before
+    /// evaluating its next event.
+    pub fn update_rules(&self, rules: LiveRules) {
+        self.lock().pending_rules = Some(rules);
+    }
+
+    /// The rules handed over since the last call, if any.
+    pub fn take_rules(&self) -> Option<LiveRules> {
+        self.lock().pending_rules.take()
+    }
+
+    pub(crate) fn attach(&self, root_pid: u32, prefix_root: PathBuf) {
+        self.lock().target = Some(Target {
+            root_pid,
+            prefix_root,
+        });
+    }
+
+    /// Called once the game has exited; returns what was done to it.
+    pub(crate) fn detach(&self) -> Vec<ControlRecord> {
+        let mut state = self.lock();
+        state.target = None;
+        state.paused = false;
+        std::mem::take(&mut state.records)
+    }
+
+    fn set_paused(&self, target: &Target, paused: bool) -> ControlResult {
+        let signal = if paused {
+            WineWardenSignal::Stop
+        } else {
+            WineWardenSignal::Continue
+        };
+        match signal_tree(target.root_pid, signal) {
+            Ok(_) => {
+                self.lock().paused = paused;
+                ControlResult::Applied
+            }
+            Err(err) => ControlResult::Failed(format!("{err:#}")),
+        }
+    }
+
+    /// Resumes the tree so it can handle the request, asks wineserver to
+    /// shut the prefix down, then SIGKILLs whatever is left after the grace
+    /// period.
+    fn kill(&self, target: &Target) -> ControlResult {
+        if let Err(err) = signal_tree(target.root_pid, WineWardenSignal::Continue) {
+            return ControlResult::Failed(format!("{err:#}"));
+        }
+        self.lock().paused = false;
+
+        let shutdown = Command::new("wineserver")
+            .arg("-k")
+            .env("WINEPREFIX", &target.prefix_root)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if shutdown && wait_for_exit(target.root_pid, KILL_GRACE) {
+            return ControlResult::ExitedGracefully;
+        }
+
+        match signal_tree(target.root_pid, WineWardenSignal::Kill) {
+            Ok(_) => ControlResult::ForceKilled,
+            Err(err) => ControlResult::Failed(format!("{err:#}")),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ControlState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn wait_for_exit(root_pid: u32, grace: Duration) -> bool {
+    let deadline = Instant::now() + grace;
+    while Instant::now() < deadline {
+        if process_tree(root_pid).is_empty() {
+            return true;
+        }
+        sleep(EXIT_POLL);
+    }
+    process_tree(root_pid).is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_resume_and_kill_process_tree() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let control = SessionControl::default();
+        assert!(control.apply(SessionAction::Pause).is_err());
+        control.attach(child.id(), PathBuf::from("/nonexistent-prefix"));
+
+        let paused = control.apply(SessionAction::Pause).unwrap();
+        assert_eq!(paused.result, ControlResult::Applied);
+        assert_eq!(paused.processes, 1);
+        assert!(control.is_paused());
+
+        control.apply(SessionAction::Resume).unwrap();
+        assert!(!control.is_paused());
+
+        let killed = control.apply(SessionAction::Kill).unwrap();
+        assert_ne!(killed.result, ControlResult::Applied);
+        assert!(child.wait().is_ok());
+
+        let records = control.detach();
+        assert_eq!(records.len(), 3);
+        assert!(control.apply(SessionAction::Resume).is_err());
+    }
+}
@@ -1,12 +1,18 @@
+use std::fs::{self, File};
+use std::io::Read;
 use std::path::Path;
 use std::sync::mpsc::{channel, Receiver};
 
 use anyhow::{Context, Result};
+use notify::event::ModifyKind;
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use time::OffsetDateTime;
 
 use winewarden_core::types::{AccessAttempt, AccessKind, AccessTarget};
 
+/// Bytes read back from a written file for the entropy check.
+const SAMPLE_BYTES: u64 = 4096;
+
 pub struct FsWatcher {
     _watcher: RecommendedWatcher,
     receiver: Receiver<notify::Result<Event>>,
@@ -37,6 +43,24 @@ impl FsWatcher {
     }
 }
 
+/// The start of a file the game wrote, if it is still a regular file.
+/// Anything else, such as a FIFO, could block the read.
+pub fn sample_written(attempt: &AccessAttempt) -> Option<Vec<u8>> {
+    let AccessTarget::Path(path) = &attempt.target else {
+        return None;
+    };
+    if attempt.kind != AccessKind::Write || !fs::metadata(path).ok()?.is_file() {
+        return None;
+    }
+    let mut sample = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(SAMPLE_BYTES)
+        .read_to_end(&mut sample)
+        .ok()?;
+    Some(sample)
+}
+
 fn convert_event(event: Event) -> Vec<AccessAttempt> {
     let kind = map_kind(&event.kind);
     let timestamp = OffsetDateTime::now_utc();
@@ -45,7 +69,7 @@ fn convert_event(event: Event) -> Vec<AccessAttempt> {
         .into_iter()
         .map(|path| AccessAttempt {
             timestamp,
-            kind: kind.clone(),
+            kind,
             target: AccessTarget::Path(path),
             note: Some(format!("fs:{:?}", event.kind)),
         })
@@ -55,8 +79,9 @@ fn convert_event(event: Event) -> Vec<AccessAttempt> {
 fn map_kind(kind: &EventKind) -> AccessKind {
     match kind {
         EventKind::Create(_) => AccessKind::Write,
+        EventKind::Modify(ModifyKind::Name(_)) => AccessKind::Rename,
         EventKind::Modify(_) => AccessKind::Write,
-        EventKind::Remove(_) => AccessKind::Write,
+        EventKind::Remove(_) => AccessKind::Delete,
         EventKind::Access(_) => AccessKind::Read,
         _ => AccessKind::Read,
     }
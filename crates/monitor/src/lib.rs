@@ -5,22 +5,27 @@ use std::os::fd::{AsFd, AsRawFd, OwnedFd};
 use std::os::unix::process::CommandExt;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::thread::sleep;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread::{self, sleep};
 use std::time::Duration;
 
 use anyhow::{Context, Result};
-use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
 use nix::sys::socket::{socketpair, AddressFamily, SockFlag, SockType};
 use nix::unistd::close;
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 use uuid::Uuid;
 
-use policy_engine::{PolicyContext, PolicyEngine};
+pub use control::{SessionControl, KILL_GRACE};
+use policy_engine::{PolicyContext, PolicyEngine, TrustScore, MASS_MODIFICATION_RULE};
 use reporting::{trust_signal_for_tier, ReportEvent, SessionReport};
+use winewarden_core::store::{EventStore, ExecutableIdentity};
 use winewarden_core::trust::TrustTier;
-use winewarden_core::types::{AccessAttempt, LiveMonitorConfig, RunMetadata};
 
+use winewarden_core::types::{AccessAttempt, LiveMonitorConfig, RunMetadata, SessionAction};
+
+pub mod control;
 pub mod fs_watch;
 pub mod memory;
 pub mod mount_ns;
@@ -70,8 +75,58 @@ impl EventSource for JsonlEventSource {
     }
 }
 
+/// Receives a session's events as they are evaluated, while the game runs.
+/// All methods default to doing nothing.
+pub trait EventSink {
+    fn session_started(&self, _metadata: &RunMetadata) {}
+    fn event(&self, _session_id: Uuid, _event: &ReportEvent) {}
+    /// Called when the running trust score changes, checked once per
+    /// watcher tick and at the end of the session.
+    fn trust_score(&self, _session_id: Uuid, _score: &TrustScore) {}
+}
+
+pub struct NoopEventSink;
+
+impl EventSink for NoopEventSink {}
+
+/// Writes each event to the event store as it is evaluated. A failing
+/// store is reported once and otherwise ignored, so it never costs the
+/// session or its trust bookkeeping.
+pub struct StoreSink {
+    pub store: EventStore,
+    pub identity: ExecutableIdentity,
+    failed: AtomicBool,
+}
+
+impl StoreSink {
+    pub fn new(store: EventStore, identity: ExecutableIdentity) -> Self {
+        Self {
+            store,
+            identity,
+            failed: AtomicBool::new(false),
+        }
+    }
+}
+
+impl EventSink for StoreSink {
+    fn event(&self, session_id: Uuid, event: &ReportEvent) {
+        let result = self
+            .store
+            .record(session_id, &self.identity, &event.attempt, &event.decision);
+        if let Err(err) = result {
+            if !self.failed.swap(true, Ordering::Relaxed) {
+                eprintln!("Warning: failed to record events for {session_id}: {err:#}");
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunRequest {
+    /// Chosen by the caller so it can track the session before it starts;
+    /// a fresh id is generated when unset.
+    #[serde(default)]
+    pub session_id: Option<Uuid>,
     pub executable: PathBuf,
     pub args: Vec<String>,
     pub prefix_root: PathBuf,
@@ -83,15 +138,48 @@ pub struct RunRequest {
 
 pub struct Monitor {
     policy: PolicyEngine,
+    sinks: Vec<Box<dyn EventSink>>,
+    control: SessionControl,
+}
+
+/// Collects a session's events and forwards each one to the sinks.
+struct SessionRecorder<'a> {
+    monitor: &'a Monitor,
+    session_id: Uuid,
+    trust_tier: TrustTier,
+    events: Vec<ReportEvent>,
+    last_score: Option<u32>,
+    /// Events have arrived since the score was last checked.
+    unscored: bool,
 }
 
 impl Monitor {
     pub fn new(policy: PolicyEngine) -> Self {
-        Self { policy }
+        Self {
+            policy,
+            sinks: Vec::new(),
+            control: SessionControl::default(),
+        }
+    }
+
+    /// Adds a sink; every sink sees every event, in the order added.
+    pub fn with_sink(mut self, sink: Box<dyn EventSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Shares `control` with the caller so it can act on the running game.
+    pub fn with_control(mut self, control: SessionControl) -> Self {
+        self.control = control;
+        self
+    }
+
+    pub fn policy(&self) -> &PolicyEngine {
+        &self.policy
     }
 
     pub fn run(&self, request: RunRequest) -> Result<SessionReport> {
-        let session_id = Uuid::new_v4();
+        let session_id = request.session_id.unwrap_or_else(Uuid::new_v4);
         let started_at = OffsetDateTime::now_utc();
         let mut metadata = RunMetadata {
             session_id,
@@ -101,6 +189,9 @@ impl Monitor {
             ended_at: None,
             trust_tier: request.trust_tier,
         };
+        for sink in &self.sinks {
+            sink.session_started(&metadata);
+        }
 
         let mut child_process = None;
         let mut seccomp_fd: Option<OwnedFd> = None;
@@ -139,7 +230,14 @@ impl Monitor {
         let data_dir = request.prefix_root.join(".winewarden");
         let mut handler_ctx = seccomp_handler::HandlerContext::new(data_dir)?;
 
-        let mut evaluated = Vec::new();
+        let mut evaluated = SessionRecorder {
+            monitor: self,
+            session_id,
+            trust_tier: request.trust_tier,
+            events: Vec::new(),
+            last_score: None,
+            unscored: false,
+        };
         if let Some(mut child) = child_process {
             let live_config = request.live_monitor.clone().unwrap_or_default();
             let mut fs_watcher = if live_config.fs {
@@ -150,98 +248,102 @@ impl Monitor {
             let mut seen_pids = HashSet::new();
             let mut seen_net = HashSet::new();
             seen_pids.insert(child.id());
+            self.control.attach(child.id(), request.prefix_root.clone());
 
-            while child.try_wait()?.is_none() {
-                // Handle Seccomp Notifications
+            let poll_interval = if live_config.enabled() {
+                live_config.poll_interval_ms
+            } else {
+                100
+            };
+            let stop = AtomicBool::new(false);
+            let (notified_tx, notified) = mpsc::channel();
+            let watched = thread::scope(|scope| {
+                // Seccomp notifications are answered on their own thread;
+                // this one collects their events and runs the watchers.
                 if let Some(fd) = &seccomp_fd {
-                    let mut poll_fds = [PollFd::new(fd.as_fd(), PollFlags::POLLIN)];
-
-                    let timeout_ms = if live_config.enabled() {
-                        live_config.poll_interval_ms as i32
-                    } else {
-                        100
-                    };
-
-                    // PollTimeout::try_from is available for i32
-                    let timeout = PollTimeout::try_from(timeout_ms).unwrap_or(PollTimeout::NONE);
-
-                    match poll(&mut poll_fds, timeout) {
-                        Ok(_) => {
-                            if poll_fds[0]
-                                .revents()
-                                .unwrap_or(PollFlags::empty())
-                                .contains(PollFlags::POLLIN)
-                            {
-                                match seccomp_handler::handle_notification(
-                                    fd.as_raw_fd(),
-                                    &self.policy,
-                                    &policy_context,
-                                    &mut handler_ctx,
-                                ) {
-                                    Ok(Some((attempt, decision))) => {
-                                        evaluated.push(ReportEvent { attempt, decision });
-                                    }
-                                    Ok(None) => {} // Notification handled (e.g. unknown syscall or ignored)
-                                    Err(e) => eprintln!("Seccomp handler error: {}", e),
+                    let (policy, context, handler_ctx, stop) =
+                        (&self.policy, &policy_context, &mut handler_ctx, &stop);
+                    scope.spawn(move || {
+                        seccomp_handler::serve_notifications(
+                            fd.as_fd(),
+                            policy,
+                            context,
+                            handler_ctx,
+                            stop,
+                            notified_tx,
+                        )
+                    });
+                }
+
+                let watched = (|| -> Result<()> {
+                    while child.try_wait()?.is_none() {
+                        self.apply_pending_rules();
+                        sleep(Duration::from_millis(poll_interval));
+                        for (attempt, decision) in notified.try_iter() {
+                            evaluated.push(ReportEvent { attempt, decision });
+                        }
+
+                        if live_config.fs {
+                            if let Some(watcher) = fs_watcher.as_mut() {
+                                for event in watcher.drain() {
+                                    let sample = fs_watch::sample_written(&event);
+                                    let decision = self.policy.evaluate_with_sample(
+                                        &event,
+                                        &policy_context,
+                                        sample.as_deref(),
+                                    );
+                                    evaluated.push(ReportEvent {
+                                        attempt: event,
+                                        decision,
+                                    });
                                 }
                             }
                         }
-                        Err(e) => {
-                            if e != nix::errno::Errno::EINTR {
-                                eprintln!("Poll error: {}", e);
+                        if live_config.proc {
+                            for event in
+                                proc_watch::collect_process_events(child.id(), &mut seen_pids)
+                            {
+                                let decision = self.policy.evaluate(&event, &policy_context);
+                                evaluated.push(ReportEvent {
+                                    attempt: event,
+                                    decision,
+                                });
                             }
                         }
-                    }
-                } else {
-                    // Fallback to sleep if no seccomp
-                    if live_config.enabled() {
-                        sleep(Duration::from_millis(live_config.poll_interval_ms));
-                    } else {
-                        // Wait for child if no monitoring and no seccomp?
-                        // Just wait briefly to avoid busy loop
-                        sleep(Duration::from_millis(100));
-                    }
-                }
-
-                if live_config.fs {
-                    if let Some(watcher) = fs_watcher.as_mut() {
-                        for event in watcher.drain() {
-                            let decision = self.policy.evaluate(&event, &policy_context);
-                            evaluated.push(ReportEvent {
-                                attempt: event,
-                                decision,
-                            });
+                        if live_config.net {
+                            for event in
+                                net_watch::collect_network_events(child.id(), &mut seen_net)
+                            {
+                                let decision = self.policy.evaluate(&event, &policy_context);
+                                evaluated.push(ReportEvent {
+                                    attempt: event,
+                                    decision,
+                                });
+                            }
                         }
+                        evaluated.score();
                     }
-                }
-                if live_config.proc {
-                    for event in proc_watch::collect_process_events(child.id(), &mut seen_pids) {
-                        let decision = self.policy.evaluate(&event, &policy_context);
-                        evaluated.push(ReportEvent {
-                            attempt: event,
-                            decision,
-                        });
-                    }
-                }
-                if live_config.net {
-                    for event in net_watch::collect_network_events(child.id(), &mut seen_net) {
-                        let decision = self.policy.evaluate(&event, &policy_context);
-                        evaluated.push(ReportEvent {
-                            attempt: event,
-                            decision,
-                        });
-                    }
-                }
+                    Ok(())
+                })();
+                stop.store(true, Ordering::Relaxed);
+                watched
+            });
+            // Events answered after the last pass of the loop
+            for (attempt, decision) in notified.try_iter() {
+                evaluated.push(ReportEvent { attempt, decision });
             }
+            watched?;
 
             // seccomp_fd drops here
         }
+        let control = self.control.detach();
 
         let mut source: Box<dyn EventSource> = match &request.event_log {
             Some(path) => Box::new(JsonlEventSource::from_path(path)?),
             None => Box::new(NoopEventSource),
         };
 
+        self.apply_pending_rules();
         while let Some(event) = source.next_event()? {
             let decision = self.policy.evaluate(&event, &policy_context);
             evaluated.push(ReportEvent {
@@ -250,9 +352,18 @@ impl Monitor {
             });
         }
 
+        evaluated.score();
         metadata.ended_at = Some(OffsetDateTime::now_utc());
         let trust_signal = trust_signal_for_tier(request.trust_tier);
-        Ok(SessionReport::new(metadata, trust_signal, evaluated))
+        let mut report = SessionReport::new(metadata, trust_signal, evaluated.events);
+        report.control = control;
+        Ok(report)
+    }
+
+    fn apply_pending_rules(&self) {
+        if let Some(rules) = self.control.take_rules() {
+            self.policy.update_rules(rules);
+        }
     }
 
     fn spawn_process(
@@ -277,18 +388,25 @@ impl Monitor {
         // Apply Landlock sandbox
         // We clone the path/tier because the closure needs to own them or move them
         let prefix = prefix.to_path_buf();
+        let context = PolicyContext {
+            prefix_root: prefix.clone(),
+            trust_tier: tier,
+        };
+        let devices =
+            sandbox::device_grants(&self.policy.device_grants(&context, Path::new("/dev")));
+        let capture_dns = self.policy.matches_domains();
         unsafe {
             cmd.pre_exec(move || {
                 // 1. Landlock
-                sandbox::apply_sandbox(&prefix, tier)
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+                sandbox::apply_sandbox(&prefix, tier, &devices)
+                    .map_err(|e| io::Error::other(e.to_string()))?;
 
                 // 2. Seccomp (Install filter and send FD)
-                let notify_fd = syscalls::install_seccomp_filter()
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+                let notify_fd = syscalls::install_seccomp_filter(capture_dns)
+                    .map_err(|e| io::Error::other(e.to_string()))?;
 
                 syscalls::send_fd(tx.as_raw_fd(), notify_fd)
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+                    .map_err(|e| io::Error::other(e.to_string()))?;
 
                 // Close the notify FD in the child (parent has it now via socket, or will have it)
                 let _ = close(notify_fd);
@@ -321,3 +439,42 @@ impl Monitor {
         Ok((child, Some(rx)))
     }
 }
+
+impl SessionRecorder<'_> {
+    /// Pauses the game when configured to. Replayed event logs have no
+    /// process to pause, so a failure here is ignored
+    fn freeze(&self) {
+        let control = &self.monitor.control;
+        if self.monitor.policy.freeze_on_mass_modification() && !control.is_paused() {
+            let _ = control.apply(SessionAction::Pause);
+        }
+    }
+
+    fn push(&mut self, event: ReportEvent) {
+        let sinks = &self.monitor.sinks;
+        for sink in sinks {
+            sink.event(self.session_id, &event);
+        }
+        if event.decision.rule.as_deref() == Some(MASS_MODIFICATION_RULE) {
+            self.freeze();
+        }
+        self.events.push(event);
+        self.unscored = true;
+    }
+
+    /// Rescores the session if events arrived since the last call and
+    /// tells the sinks when the score moved. Scoring walks the whole
+    /// behavior profile, so it runs per tick rather than per event.
+    fn score(&mut self) {
+        if !std::mem::take(&mut self.unscored) {
+            return;
+        }
+        let score = self.monitor.policy.calculate_trust_score(self.trust_tier);
+        if self.last_score != Some(score.score) {
+            self.last_score = Some(score.score);
+            for sink in &self.monitor.sinks {
+                sink.trust_score(self.session_id, &score);
+            }
+        }
+    }
+}
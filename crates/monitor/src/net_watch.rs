@@ -3,7 +3,9 @@ use std::fs;
 
 use time::OffsetDateTime;
 
-use winewarden_core::types::{AccessAttempt, AccessKind, AccessTarget, NetworkTarget};
+use winewarden_core::types::{
+    AccessAttempt, AccessKind, AccessTarget, NetworkDirection, NetworkTarget,
+};
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct NetKey {
@@ -49,6 +51,8 @@ fn collect_from_table(pid: u32, protocol: &str, seen: &mut HashSet<NetKey>) -> V
                         host,
                         port,
                         protocol: protocol.to_string(),
+                        direction: NetworkDirection::Connect,
+                        names: Vec::new(),
                     }),
                     note: Some("connection observed".to_string()),
                 });
@@ -29,18 +29,44 @@ pub fn collect_process_events(root_pid: u32, seen: &mut HashSet<u32>) -> Vec<Acc
     new_events
 }
 
-fn read_children(pid: u32) -> Vec<u32> {
-    let path = format!("/proc/{pid}/task/{pid}/children");
-    let Ok(contents) = fs::read_to_string(path) else {
+/// Children of every thread of `pid`; a child belongs to the thread that
+/// forked it, so Wine's worker threads have children of their own.
+pub(crate) fn read_children(pid: u32) -> Vec<u32> {
+    let Ok(tasks) = fs::read_dir(format!("/proc/{pid}/task")) else {
         return Vec::new();
     };
-    contents
-        .split_whitespace()
-        .filter_map(|value| value.parse::<u32>().ok())
-        .collect()
+    let mut children: Vec<u32> = tasks
+        .flatten()
+        .filter_map(|task| fs::read_to_string(task.path().join("children")).ok())
+        .flat_map(|contents| {
+            contents
+                .split_whitespace()
+                .filter_map(|value| value.parse::<u32>().ok())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    children.sort_unstable();
+    children.dedup();
+    children
 }
 
 fn read_exe(pid: u32) -> Option<PathBuf> {
     let path = format!("/proc/{pid}/exe");
     fs::read_link(path).ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_children_of_other_threads_are_found() {
+        // Tests run off the main thread, so this child belongs to a worker
+        let mut child = Command::new("sleep").arg("5").spawn().unwrap();
+        let found = read_children(std::process::id());
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(found.contains(&child.id()));
+    }
+}
@@ -1,28 +1,60 @@
-use std::fs::File;
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use landlock::{
-    Access, AccessFs, BitFlags, PathBeneath, Ruleset, RulesetAttr, RulesetCreated,
+    Access, AccessFs, BitFlags, PathBeneath, PathFd, Ruleset, RulesetAttr, RulesetCreated,
     RulesetCreatedAttr, RulesetError, ABI,
 };
 
 use crate::mount_ns::MountNamespaceBuilder;
 use winewarden_core::trust::TrustTier;
+use winewarden_core::types::AccessKind;
+
+/// A path Landlock opens up, and what it allows there.
+pub type Grant = (PathBuf, BitFlags<AccessFs>);
 
 /// Applies a complete sandbox (Landlock + Mount Namespace) to the current process.
 /// This MUST be called before executing the untrusted code (e.g. in pre_exec).
-pub fn apply_sandbox(prefix_root: &Path, tier: TrustTier) -> Result<()> {
+/// `devices` come from `device_grants`; build them before forking, as
+/// walking /dev and evaluating the rules allocate.
+pub fn apply_sandbox(prefix_root: &Path, tier: TrustTier, devices: &[Grant]) -> Result<()> {
     // Step 1: Set up mount namespace for path virtualization
     // This creates bind mounts that redirect sensitive paths to virtual locations
     setup_mount_namespace(prefix_root)?;
 
     // Step 2: Apply Landlock sandbox for additional restrictions
-    apply_landlock_sandbox(prefix_root, tier)?;
+    apply_landlock_sandbox(prefix_root, tier, devices)?;
 
     Ok(())
 }
 
+/// Landlock grants for the device nodes the rules allow and the kinds of
+/// access allowed on each, plus listing the directories they sit in.
+/// Landlock holds on to the nodes present now, so a controller plugged in
+/// after launch stays blocked until the game is restarted.
+pub fn device_grants(devices: &[(PathBuf, Vec<AccessKind>)]) -> Vec<Grant> {
+    let parents: BTreeSet<&Path> = devices
+        .iter()
+        .filter_map(|(node, _)| node.parent())
+        .collect();
+    let mut grants: Vec<Grant> = parents
+        .into_iter()
+        .map(|parent| (parent.to_path_buf(), AccessFs::ReadDir.into()))
+        .collect();
+    for (node, kinds) in devices {
+        let access = kinds
+            .iter()
+            .fold(BitFlags::empty(), |access, kind| match kind {
+                AccessKind::Read => access | AccessFs::ReadFile,
+                AccessKind::Write => access | AccessFs::WriteFile,
+                _ => access,
+            });
+        grants.push((node.clone(), access));
+    }
+    grants
+}
+
 /// Sets up the mount namespace for path virtualization.
 fn setup_mount_namespace(_prefix_root: &Path) -> Result<()> {
     // Create mount namespace with default mappings
@@ -36,7 +68,7 @@ fn setup_mount_namespace(_prefix_root: &Path) -> Result<()> {
 }
 
 /// Applies Landlock sandbox for filesystem access control.
-fn apply_landlock_sandbox(prefix_root: &Path, tier: TrustTier) -> Result<()> {
+fn apply_landlock_sandbox(prefix_root: &Path, tier: TrustTier, devices: &[Grant]) -> Result<()> {
     // Define access rights
     let read_dirs = AccessFs::Execute | AccessFs::ReadFile | AccessFs::ReadDir;
     let read_write_dirs = read_dirs
@@ -91,9 +123,9 @@ fn apply_landlock_sandbox(prefix_root: &Path, tier: TrustTier) -> Result<()> {
             add_rule(&mut ruleset, path, AccessFs::ReadFile | AccessFs::WriteFile)?;
         }
     }
-    // GPU access
-    if Path::new("/dev/dri").exists() {
-        add_rule(&mut ruleset, Path::new("/dev/dri"), read_dirs)?;
+    // Gamepads, GPU, audio, cameras: whatever the rules allow this tier
+    for (path, access) in devices {
+        add_rule(&mut ruleset, path, *access)?;
     }
     // Shared Memory / TMP (Read-Write)
     // /dev/shm is crucial for performance
@@ -150,9 +182,10 @@ fn apply_landlock_sandbox(prefix_root: &Path, tier: TrustTier) -> Result<()> {
 }
 
 fn add_rule(ruleset: &mut RulesetCreated, path: &Path, access: BitFlags<AccessFs>) -> Result<()> {
-    // Landlock requires an open file descriptor.
+    // Landlock requires an open file descriptor. An O_PATH one does not
+    // open device nodes themselves.
 
-    let file = match File::open(path) {
+    let file = match PathFd::new(path) {
         Ok(f) => f,
 
         Err(_) => return Ok(()), // If we can't open it, we can't allow it. Skip.
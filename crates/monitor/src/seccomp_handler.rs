@@ -1,13 +1,23 @@
 use anyhow::{Context, Result};
 use byteorder::{BigEndian, ByteOrder, NativeEndian};
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
 use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+
+use nix::libc;
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use nix::sys::socket::{getsockopt, sockopt, SockType};
 use time::OffsetDateTime;
 
 use crate::memory;
 use crate::path_redirect::{CopyOnWrite, PathMapper};
+use net_compat::DnsCapture;
 use policy_engine::{DecisionAction, PolicyContext, PolicyDecision, PolicyEngine};
-use winewarden_core::types::{AccessAttempt, AccessKind, AccessTarget, NetworkTarget};
+use winewarden_core::types::{
+    AccessAttempt, AccessKind, AccessTarget, NetworkDirection, NetworkTarget,
+};
 
 // -- Linux Seccomp Userspace Notification ABI --
 
@@ -45,6 +55,7 @@ nix::ioctl_readwrite!(seccomp_notif_send, b'!', 1, SeccompNotifResp);
 
 // Syscall numbers (x86_64)
 const SYS_CONNECT: i32 = 42;
+const SYS_RECVFROM: i32 = 45;
 const SYS_BIND: i32 = 49;
 
 // Filesystem syscalls
@@ -61,6 +72,12 @@ const SYS_FACCESSAT: i32 = 269;
 const SYS_FACCESSAT2: i32 = 439;
 const SYS_MKDIR: i32 = 83;
 const SYS_MKDIRAT: i32 = 258;
+const SYS_RENAME: i32 = 82;
+const SYS_RMDIR: i32 = 84;
+const SYS_UNLINK: i32 = 87;
+const SYS_UNLINKAT: i32 = 263;
+const SYS_RENAMEAT: i32 = 264;
+const SYS_RENAMEAT2: i32 = 316;
 
 // Address Families
 const AF_INET: u16 = 2;
@@ -69,18 +86,81 @@ const AF_INET6: u16 = 10;
 /// Maximum path length to read from process memory
 const MAX_PATH_LEN: usize = 4096;
 
+/// dirfd meaning "relative to the working directory"
+const AT_FDCWD: i32 = -100;
+
+const DNS_PORT: u16 = 53;
+/// Large enough for any UDP DNS answer with EDNS
+const MAX_DNS_PACKET: usize = 4096;
+
 /// Context for handling seccomp notifications
 pub struct HandlerContext {
     /// Path mapper for redirect/virtualize operations
     pub mapper: PathMapper,
     /// Base data directory for creating virtual paths
     pub data_dir: std::path::PathBuf,
+    /// Addresses the game's DNS answers named, for domain rules
+    pub dns: DnsCapture,
 }
 
 impl HandlerContext {
     pub fn new(data_dir: std::path::PathBuf) -> Result<Self> {
         let mapper = PathMapper::from_env_or_default(&data_dir)?;
-        Ok(Self { mapper, data_dir })
+        Ok(Self {
+            mapper,
+            data_dir,
+            dns: DnsCapture::new(),
+        })
+    }
+}
+
+/// How often `serve_notifications` checks whether it should stop.
+const STOP_CHECK_MS: u16 = 100;
+
+/// Answers notifications until `stop` is set or the filter has no tasks
+/// left, sending each evaluated attempt to `events`. Runs on its own
+/// thread, so the game's syscalls are answered while the watchers are busy.
+pub fn serve_notifications(
+    seccomp_fd: BorrowedFd<'_>,
+    policy: &PolicyEngine,
+    context: &PolicyContext,
+    handler_ctx: &mut HandlerContext,
+    stop: &AtomicBool,
+    events: Sender<(AccessAttempt, PolicyDecision)>,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        let mut poll_fds = [PollFd::new(seccomp_fd, PollFlags::POLLIN)];
+        match poll(&mut poll_fds, PollTimeout::from(STOP_CHECK_MS)) {
+            Ok(_) => {
+                let revents = poll_fds[0].revents().unwrap_or(PollFlags::empty());
+                if !revents.contains(PollFlags::POLLIN) {
+                    // POLLHUP once every task using the filter has exited;
+                    // no notification can follow
+                    if revents
+                        .intersects(PollFlags::POLLHUP | PollFlags::POLLERR | PollFlags::POLLNVAL)
+                    {
+                        return;
+                    }
+                    continue;
+                }
+                match handle_notification(seccomp_fd.as_raw_fd(), policy, context, handler_ctx) {
+                    Ok(attempts) => {
+                        // Empty for unknown or ignored syscalls. The receiver
+                        // only goes away once the session is over
+                        for event in attempts {
+                            let _ = events.send(event);
+                        }
+                    }
+                    Err(e) => eprintln!("Seccomp handler error: {}", e),
+                }
+            }
+            Err(e) => {
+                if e != nix::errno::Errno::EINTR {
+                    eprintln!("Poll error: {}", e);
+                    return;
+                }
+            }
+        }
     }
 }
 
@@ -89,7 +169,7 @@ pub fn handle_notification(
     policy: &PolicyEngine,
     context: &PolicyContext,
     handler_ctx: &mut HandlerContext,
-) -> Result<Option<(AccessAttempt, PolicyDecision)>> {
+) -> Result<Vec<(AccessAttempt, PolicyDecision)>> {
     // 1. Receive Notification
     let mut req = SeccompNotif::default();
     unsafe {
@@ -100,12 +180,22 @@ pub fn handle_notification(
     // 2. Analyze Syscall
     let syscall = req.data.nr;
     let mut decision_action = DecisionAction::Allow;
-    let mut event_data = None;
+    let mut event_data = Vec::new();
     let mut path_redirect: Option<std::path::PathBuf> = None;
 
     // Handle network syscalls
     if syscall == SYS_CONNECT || syscall == SYS_BIND {
-        event_data = handle_network_syscall(&req, policy, context, &mut decision_action)?;
+        event_data = handle_network_syscall(
+            &req,
+            policy,
+            context,
+            &handler_ctx.dns,
+            &mut decision_action,
+        )?
+        .into_iter()
+        .collect();
+    } else if syscall == SYS_RECVFROM {
+        capture_dns_answer(&req, &mut handler_ctx.dns);
     }
     // Handle filesystem syscalls
     else if is_filesystem_syscall(syscall) {
@@ -130,9 +220,9 @@ pub fn handle_notification(
         flags: 0,
     };
 
-    // Set error code based on decision
+    // Set error code based on decision; the kernel expects a negated errno
     if matches!(decision_action, DecisionAction::Deny) {
-        resp.error = 1; // EPERM
+        resp.error = -libc::EPERM;
     }
 
     // Handle path redirection if needed
@@ -176,17 +266,82 @@ fn is_filesystem_syscall(syscall: i32) -> bool {
             | SYS_FACCESSAT2
             | SYS_MKDIR
             | SYS_MKDIRAT
+            | SYS_RENAME
+            | SYS_RMDIR
+            | SYS_UNLINK
+            | SYS_UNLINKAT
+            | SYS_RENAMEAT
+            | SYS_RENAMEAT2
     )
 }
 
+/// What a filesystem syscall does to its path. Opens count as writes when
+/// they ask for write access, create or truncate; renames are judged by
+/// both paths, see `read_rename_destination`.
+fn access_kind(req: &SeccompNotif) -> AccessKind {
+    let args = &req.data.args;
+    let open_flags = match req.data.nr {
+        SYS_OPEN => args[1],
+        SYS_OPENAT => args[2],
+        // Flags that cannot be read are taken for a write
+        SYS_OPENAT2 => match open_how_flags(req) {
+            Some(flags) => flags,
+            None => return AccessKind::Write,
+        },
+        SYS_MKDIR | SYS_MKDIRAT => return AccessKind::Write,
+        SYS_UNLINK | SYS_UNLINKAT | SYS_RMDIR => return AccessKind::Delete,
+        SYS_RENAME | SYS_RENAMEAT | SYS_RENAMEAT2 => return AccessKind::Rename,
+        _ => return AccessKind::Read,
+    };
+    let open_flags = open_flags as libc::c_int;
+    let writes = open_flags & libc::O_ACCMODE != libc::O_RDONLY
+        || open_flags & (libc::O_CREAT | libc::O_TRUNC) != 0;
+    if writes {
+        AccessKind::Write
+    } else {
+        AccessKind::Read
+    }
+}
+
+/// `flags`, the first field of the `struct open_how` that openat2(dirfd,
+/// pathname, how, size) points to.
+fn open_how_flags(req: &SeccompNotif) -> Option<u64> {
+    let (how, size) = (req.data.args[2], req.data.args[3]);
+    if size < 8 {
+        return None;
+    }
+    match memory::read_remote_memory(req.pid as i32, how, 8) {
+        Ok(bytes) => Some(NativeEndian::read_u64(&bytes)),
+        Err(e) => {
+            eprintln!("Failed to read open_how from process {}: {}", req.pid, e);
+            None
+        }
+    }
+}
+
+/// Ranks decisions for syscalls that touch two paths; the stricter applies.
+fn strictness(action: &DecisionAction) -> u8 {
+    match action {
+        DecisionAction::Allow => 0,
+        DecisionAction::Redirect(_) | DecisionAction::Virtualize(_) => 1,
+        DecisionAction::Deny => 2,
+    }
+}
+
 fn handle_network_syscall(
     req: &SeccompNotif,
     policy: &PolicyEngine,
     context: &PolicyContext,
+    dns: &DnsCapture,
     decision_action: &mut DecisionAction,
 ) -> Result<Option<(AccessAttempt, PolicyDecision)>> {
-    // connect(fd, addr, addrlen)
+    // connect(fd, addr, addrlen) and bind(fd, addr, addrlen)
     // args[0] = fd, args[1] = addr (ptr), args[2] = addrlen
+    let direction = if req.data.nr == SYS_BIND {
+        NetworkDirection::Bind
+    } else {
+        NetworkDirection::Connect
+    };
     let remote_addr_ptr = req.data.args[1];
     let addrlen = req.data.args[2] as usize;
 
@@ -198,7 +353,14 @@ fn handle_network_syscall(
     match memory::read_remote_memory(req.pid as i32, remote_addr_ptr, addrlen) {
         Ok(bytes) => {
             // 4. Parse IP/Port
-            if let Some(target) = parse_sockaddr(&bytes) {
+            if let Some(mut target) = parse_sockaddr(&bytes) {
+                target.direction = direction;
+                if direction == NetworkDirection::Connect {
+                    target.names = resolved_names(dns, &target.host);
+                }
+                if let Some(protocol) = socket_protocol(req.pid, req.data.args[0] as RawFd) {
+                    target.protocol = protocol.to_string();
+                }
                 // 5. Evaluate Policy
                 let attempt = AccessAttempt {
                     timestamp: OffsetDateTime::now_utc(),
@@ -211,11 +373,9 @@ fn handle_network_syscall(
 
                 let result = Some((attempt.clone(), policy_decision.clone()));
 
-                match policy_decision.action {
-                    DecisionAction::Deny => {
-                        *decision_action = DecisionAction::Deny;
-                    }
-                    _ => {} // Allow, Redirect (Treat as Allow for net for now), etc.
+                // Allow, Redirect (treated as Allow for net for now), etc. pass through
+                if let DecisionAction::Deny = policy_decision.action {
+                    *decision_action = DecisionAction::Deny;
                 }
 
                 return Ok(result);
@@ -237,7 +397,7 @@ fn handle_filesystem_syscall(
     handler_ctx: &mut HandlerContext,
     decision_action: &mut DecisionAction,
     path_redirect: &mut Option<std::path::PathBuf>,
-) -> Result<Option<(AccessAttempt, PolicyDecision)>> {
+) -> Result<Vec<(AccessAttempt, PolicyDecision)>> {
     // Read the path argument from process memory
     let path_result = read_path_argument(req, syscall);
 
@@ -245,26 +405,54 @@ fn handle_filesystem_syscall(
         Some(Ok(path)) => path,
         Some(Err(e)) => {
             eprintln!("Failed to read path from process {}: {}", req.pid, e);
-            return Ok(None);
+            return Ok(Vec::new());
         }
         None => {
             // Unknown syscall or no path to read
-            return Ok(None);
+            return Ok(Vec::new());
         }
     };
 
-    let path = std::path::PathBuf::from(&path_str);
+    // A rename changes its destination as much as its source, so both are
+    // evaluated and the stricter decision applies
+    let mut paths = vec![std::path::PathBuf::from(&path_str)];
+    match read_rename_destination(req, syscall) {
+        Some(Ok(destination)) => paths.push(std::path::PathBuf::from(destination)),
+        Some(Err(e)) => eprintln!(
+            "Failed to read rename destination from process {}: {}",
+            req.pid, e
+        ),
+        None => {}
+    }
 
     // Evaluate policy
-    let attempt = AccessAttempt {
-        timestamp: OffsetDateTime::now_utc(),
-        kind: AccessKind::Read, // Will be refined based on syscall
-        target: AccessTarget::Path(path.clone()),
-        note: Some(format!("Syscall: {}", syscall)),
+    let kind = access_kind(req);
+    let result: Vec<(AccessAttempt, PolicyDecision)> = paths
+        .into_iter()
+        .map(|path| {
+            let attempt = AccessAttempt {
+                timestamp: OffsetDateTime::now_utc(),
+                kind,
+                target: AccessTarget::Path(path),
+                note: Some(format!("Syscall: {}", syscall)),
+            };
+            let decision = policy.evaluate(&attempt, context);
+            (attempt, decision)
+        })
+        .collect();
+
+    // On a tie the source path decides
+    let Some((attempt, policy_decision)) = result
+        .iter()
+        .rev()
+        .max_by_key(|(_, decision)| strictness(&decision.action))
+    else {
+        return Ok(result);
     };
-
-    let policy_decision = policy.evaluate(&attempt, context);
-    let result = Some((attempt.clone(), policy_decision.clone()));
+    let AccessTarget::Path(path) = &attempt.target else {
+        return Ok(result);
+    };
+    let path = path.clone();
 
     // Handle the decision
     match &policy_decision.action {
@@ -307,21 +495,22 @@ fn read_path_argument(req: &SeccompNotif, syscall: i32) -> Option<Result<String>
     match syscall {
         // open(pathname, flags, mode)
         // args[0] = pathname (const char *)
-        SYS_OPEN | SYS_ACCESS | SYS_STAT | SYS_LSTAT | SYS_MKDIR => {
+        SYS_OPEN | SYS_ACCESS | SYS_STAT | SYS_LSTAT | SYS_MKDIR | SYS_RENAME | SYS_RMDIR
+        | SYS_UNLINK => {
             let path_ptr = req.data.args[0];
             Some(read_null_terminated_string(pid, path_ptr, MAX_PATH_LEN))
         }
 
-        // openat(dirfd, pathname, flags, mode)
+        // openat(dirfd, pathname, flags, mode), openat2(dirfd, pathname, how, size)
         // args[0] = dirfd, args[1] = pathname
-        SYS_OPENAT | SYS_MKDIRAT | SYS_FACCESSAT => {
+        SYS_OPENAT | SYS_OPENAT2 | SYS_MKDIRAT | SYS_FACCESSAT | SYS_UNLINKAT | SYS_RENAMEAT
+        | SYS_RENAMEAT2 => {
             let dirfd = req.data.args[0] as i32;
             let path_ptr = req.data.args[1];
 
             // If dirfd is AT_FDCWD (-100), path is relative to cwd
             // Otherwise we'd need to resolve the fd to a path (complex)
             // For now, just handle absolute paths and AT_FDCWD
-            const AT_FDCWD: i32 = -100;
             if dirfd == AT_FDCWD {
                 Some(read_null_terminated_string(pid, path_ptr, MAX_PATH_LEN))
             } else {
@@ -340,7 +529,6 @@ fn read_path_argument(req: &SeccompNotif, syscall: i32) -> Option<Result<String>
             let dirfd = req.data.args[0] as i32;
             let path_ptr = req.data.args[1];
 
-            const AT_FDCWD: i32 = -100;
             if dirfd == AT_FDCWD {
                 Some(read_null_terminated_string(pid, path_ptr, MAX_PATH_LEN))
             } else {
@@ -352,19 +540,11 @@ fn read_path_argument(req: &SeccompNotif, syscall: i32) -> Option<Result<String>
             }
         }
 
-        // openat2(dirfd, pathname, open_how, size)
-        // More complex structure, skip for now
-        SYS_OPENAT2 => {
-            eprintln!("Warning: openat2 not yet supported");
-            None
-        }
-
         // faccessat2(dirfd, pathname, mode, flags)
         SYS_FACCESSAT2 => {
             let dirfd = req.data.args[0] as i32;
             let path_ptr = req.data.args[1];
 
-            const AT_FDCWD: i32 = -100;
             if dirfd == AT_FDCWD {
                 Some(read_null_terminated_string(pid, path_ptr, MAX_PATH_LEN))
             } else {
@@ -380,6 +560,41 @@ fn read_path_argument(req: &SeccompNotif, syscall: i32) -> Option<Result<String>
     }
 }
 
+/// Reads the new path of a rename
+fn read_rename_destination(req: &SeccompNotif, syscall: i32) -> Option<Result<String>> {
+    let pid = req.pid as i32;
+
+    match syscall {
+        // rename(oldpath, newpath)
+        SYS_RENAME => Some(read_null_terminated_string(
+            pid,
+            req.data.args[1],
+            MAX_PATH_LEN,
+        )),
+
+        // renameat(olddirfd, oldpath, newdirfd, newpath), renameat2 adds flags
+        // args[2] = newdirfd, args[3] = newpath
+        SYS_RENAMEAT | SYS_RENAMEAT2 => {
+            let dirfd = req.data.args[2] as i32;
+            if dirfd == AT_FDCWD {
+                Some(read_null_terminated_string(
+                    pid,
+                    req.data.args[3],
+                    MAX_PATH_LEN,
+                ))
+            } else {
+                eprintln!(
+                    "Warning: Relative path with dirfd {} not yet supported",
+                    dirfd
+                );
+                None
+            }
+        }
+
+        _ => None,
+    }
+}
+
 /// Reads a null-terminated string from remote process memory
 fn read_null_terminated_string(pid: i32, addr: u64, max_len: usize) -> Result<String> {
     // Read in chunks to find the null terminator
@@ -409,6 +624,87 @@ fn read_null_terminated_string(pid: i32, addr: u64, max_len: usize) -> Result<St
     String::from_utf8(result).context("Path is not valid UTF-8")
 }
 
+/// The names whose DNS answers gave `host`. Dual-stack sockets connect to
+/// IPv4 addresses in their IPv6-mapped form.
+fn resolved_names(dns: &DnsCapture, host: &str) -> Vec<String> {
+    let host = match host.parse::<IpAddr>() {
+        Ok(IpAddr::V6(address)) => address
+            .to_ipv4_mapped()
+            .map_or_else(|| host.to_string(), |address| address.to_string()),
+        _ => host.to_string(),
+    };
+    let mut names: Vec<String> = dns
+        .domains_for_ip(&host)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    names.sort();
+    names
+}
+
+/// Reads the DNS answer the process is about to receive, leaving it
+/// queued. Resolvers poll before receiving, so the answer is already
+/// waiting while the syscall is held; other datagrams are left alone.
+fn capture_dns_answer(req: &SeccompNotif, dns: &mut DnsCapture) {
+    let Some(socket) = borrow_fd(req.pid, req.data.args[0] as RawFd) else {
+        return;
+    };
+    if getsockopt(&socket, sockopt::SockType).ok() != Some(SockType::Datagram) {
+        return;
+    }
+    let mut packet = [0u8; MAX_DNS_PACKET];
+    let mut source: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let mut source_len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    let len = unsafe {
+        libc::recvfrom(
+            socket.as_raw_fd(),
+            packet.as_mut_ptr().cast(),
+            packet.len(),
+            libc::MSG_PEEK | libc::MSG_DONTWAIT,
+            (&mut source as *mut libc::sockaddr_storage).cast(),
+            &mut source_len,
+        )
+    };
+    if len <= 0 {
+        return;
+    }
+    let source = unsafe {
+        std::slice::from_raw_parts(
+            (&source as *const libc::sockaddr_storage).cast::<u8>(),
+            source_len as usize,
+        )
+    };
+    if parse_sockaddr(source).is_some_and(|source| source.port == DNS_PORT) {
+        dns.record_packet(&packet[..len as usize]);
+    }
+}
+
+/// A duplicate of the process's descriptor `fd`. The syscall is blocked
+/// until we answer, so the descriptor cannot change underneath us.
+fn borrow_fd(pid: u32, fd: RawFd) -> Option<OwnedFd> {
+    let pidfd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+    if pidfd < 0 {
+        return None;
+    }
+    let pidfd = unsafe { OwnedFd::from_raw_fd(pidfd as RawFd) };
+    let socket = unsafe { libc::syscall(libc::SYS_pidfd_getfd, pidfd.as_raw_fd(), fd, 0) };
+    if socket < 0 {
+        return None;
+    }
+    Some(unsafe { OwnedFd::from_raw_fd(socket as RawFd) })
+}
+
+/// Finds out whether the socket the process is connecting or binding is
+/// TCP or UDP by borrowing a duplicate of it.
+fn socket_protocol(pid: u32, fd: RawFd) -> Option<&'static str> {
+    let socket = borrow_fd(pid, fd)?;
+    match getsockopt(&socket, sockopt::SockType).ok()? {
+        SockType::Stream => Some("tcp"),
+        SockType::Datagram => Some("udp"),
+        _ => None,
+    }
+}
+
 fn parse_sockaddr(data: &[u8]) -> Option<NetworkTarget> {
     if data.len() < 2 {
         return None;
@@ -426,6 +722,8 @@ fn parse_sockaddr(data: &[u8]) -> Option<NetworkTarget> {
                 host: ip.to_string(),
                 port,
                 protocol: "tcp/udp".to_string(),
+                direction: NetworkDirection::Connect,
+                names: Vec::new(),
             })
         }
         AF_INET6 if data.len() >= 24 => {
@@ -443,8 +741,143 @@ fn parse_sockaddr(data: &[u8]) -> Option<NetworkTarget> {
                 host: ip.to_string(),
                 port,
                 protocol: "tcp/udp".to_string(),
+                direction: NetworkDirection::Connect,
+                names: Vec::new(),
             })
         }
         _ => None, // Unix sockets, etc.
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    /// A notification from this very process, so its memory can be read back
+    fn notification(nr: i32, args: [u64; 6]) -> SeccompNotif {
+        SeccompNotif {
+            pid: std::process::id(),
+            data: SeccompData {
+                nr,
+                args,
+                ..SeccompData::default()
+            },
+            ..SeccompNotif::default()
+        }
+    }
+
+    #[test]
+    fn test_openat2_flags_are_read_from_open_how() {
+        // struct open_how { u64 flags; u64 mode; u64 resolve; }
+        let read_only = [libc::O_RDONLY as u64, 0, 0];
+        let creates = [(libc::O_RDONLY | libc::O_CREAT) as u64, 0o644, 0];
+        let openat2 = |how: &[u64; 3], size: u64| {
+            let args = [AT_FDCWD as u64, 0, how.as_ptr() as u64, size, 0, 0];
+            access_kind(&notification(SYS_OPENAT2, args))
+        };
+        assert_eq!(openat2(&read_only, 24), AccessKind::Read);
+        assert_eq!(openat2(&creates, 24), AccessKind::Write);
+        assert_eq!(openat2(&read_only, 0), AccessKind::Write);
+    }
+
+    #[test]
+    fn test_connect_matches_domains_from_dns_answers() {
+        let mut config = winewarden_core::config::Config::default_config();
+        config.network = toml::from_str(
+            r#"
+            mode = "enforce"
+            defaults = { green = "allow", yellow = "deny", red = "deny" }
+
+            [[rules]]
+            label = "Steam"
+            action = "allow"
+            domains = ["steampowered.com"]
+            "#,
+        )
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let paths = winewarden_core::config::ConfigPaths::under(dir.path());
+        let policy = PolicyEngine::from_config(config, &paths).unwrap();
+        assert!(policy.matches_domains());
+        let context = PolicyContext {
+            prefix_root: dir.path().join("prefix"),
+            trust_tier: winewarden_core::trust::TrustTier::Yellow,
+        };
+
+        // An answer for cdn.steampowered.com: A 203.0.113.7
+        let mut answer = vec![0, 1, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0];
+        answer.extend_from_slice(b"\x03cdn\x0csteampowered\x03com\x00\x00\x01\x00\x01");
+        answer.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 203, 0, 113, 7]);
+        let mut dns = DnsCapture::new();
+        assert!(dns.record_packet(&answer));
+
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let connect = |address: [u8; 4]| {
+            // struct sockaddr_in { u16 family; u16 port; u8 addr[4]; u8 zero[8]; }
+            let mut sockaddr = [0u8; 16];
+            NativeEndian::write_u16(&mut sockaddr[0..2], AF_INET);
+            BigEndian::write_u16(&mut sockaddr[2..4], 443);
+            sockaddr[4..8].copy_from_slice(&address);
+            let args = [
+                socket.as_raw_fd() as u64,
+                sockaddr.as_ptr() as u64,
+                16,
+                0,
+                0,
+                0,
+            ];
+            let mut action = DecisionAction::Allow;
+            let (attempt, decision) = handle_network_syscall(
+                &notification(SYS_CONNECT, args),
+                &policy,
+                &context,
+                &dns,
+                &mut action,
+            )
+            .unwrap()
+            .expect("connect evaluated");
+            (attempt, decision, action)
+        };
+
+        let (attempt, decision, action) = connect([203, 0, 113, 7]);
+        let AccessTarget::Network(target) = &attempt.target else {
+            panic!("not a network attempt");
+        };
+        assert_eq!(target.names, ["cdn.steampowered.com"]);
+        assert_eq!(decision.rule.as_deref(), Some("Steam"));
+        assert!(matches!(action, DecisionAction::Allow));
+
+        let (attempt, decision, action) = connect([198, 51, 100, 1]);
+        let AccessTarget::Network(target) = &attempt.target else {
+            panic!("not a network attempt");
+        };
+        assert!(target.names.is_empty());
+        assert_ne!(decision.rule.as_deref(), Some("Steam"));
+        assert!(matches!(action, DecisionAction::Deny));
+    }
+
+    #[test]
+    fn test_rename_destination_is_read() {
+        let source = CString::new("/tmp/prefix/save.dat").unwrap();
+        let destination = CString::new("/home/user/.ssh/authorized_keys").unwrap();
+        let renameat2 = notification(
+            SYS_RENAMEAT2,
+            [
+                AT_FDCWD as u64,
+                source.as_ptr() as u64,
+                AT_FDCWD as u64,
+                destination.as_ptr() as u64,
+                0,
+                0,
+            ],
+        );
+        let read = read_rename_destination(&renameat2, SYS_RENAMEAT2)
+            .unwrap()
+            .unwrap();
+        assert_eq!(read, "/home/user/.ssh/authorized_keys");
+        assert_eq!(access_kind(&renameat2), AccessKind::Rename);
+
+        assert!(strictness(&DecisionAction::Deny) > strictness(&DecisionAction::Allow));
+    }
+}
@@ -1,5 +1,67 @@
+use std::fs;
+
+use anyhow::{Context, Result};
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+
+use crate::proc_watch::read_children;
+
 #[derive(Debug, Clone, Copy)]
 pub enum WineWardenSignal {
     Continue,
     Stop,
+    Kill,
+}
+
+impl WineWardenSignal {
+    fn as_signal(self) -> Signal {
+        match self {
+            WineWardenSignal::Continue => Signal::SIGCONT,
+            WineWardenSignal::Stop => Signal::SIGSTOP,
+            WineWardenSignal::Kill => Signal::SIGKILL,
+        }
+    }
+}
+
+/// Live processes rooted at `root_pid`, parents before children. Zombies
+/// are left out; they have already exited and only wait to be reaped.
+pub fn process_tree(root_pid: u32) -> Vec<u32> {
+    let mut tree = Vec::new();
+    let mut queue = vec![root_pid];
+    while let Some(pid) = queue.pop() {
+        if !is_alive(pid) || tree.contains(&pid) {
+            continue;
+        }
+        tree.push(pid);
+        queue.extend(read_children(pid));
+    }
+    tree
+}
+
+/// Sends `signal` to every process in the tree and returns how many were
+/// signalled. The root goes first, so a stopped root cannot spawn children
+/// the walk would miss.
+pub fn signal_tree(root_pid: u32, signal: WineWardenSignal) -> Result<usize> {
+    let mut signalled = 0;
+    for pid in process_tree(root_pid) {
+        match kill(Pid::from_raw(pid as i32), signal.as_signal()) {
+            Ok(()) => signalled += 1,
+            // Exited between the walk and the signal.
+            Err(nix::errno::Errno::ESRCH) => {}
+            Err(err) => return Err(err).with_context(|| format!("send {signal:?} to pid {pid}")),
+        }
+    }
+    Ok(signalled)
+}
+
+fn is_alive(pid: u32) -> bool {
+    let Ok(stat) = fs::read_to_string(format!("/proc/{pid}/stat")) else {
+        return false;
+    };
+    // The state follows the parenthesised command name, which may itself
+    // contain spaces or parentheses.
+    let state = stat
+        .rfind(')')
+        .and_then(|end| stat[end + 1..].split_whitespace().next());
+    !matches!(state, None | Some("Z") | Some("X"))
 }
@@ -5,15 +5,21 @@ use std::io::{IoSlice, IoSliceMut};
 use std::os::fd::{FromRawFd, OwnedFd};
 use std::os::unix::io::RawFd;
 
-/// Installs a Seccomp filter that notifies on network and filesystem syscalls.
+/// Installs a Seccomp filter that notifies on network and filesystem syscalls,
+/// and on `recvfrom` when `capture_dns` is set so DNS answers can be read.
 /// Returns the notification file descriptor.
-pub fn install_seccomp_filter() -> Result<RawFd> {
+pub fn install_seccomp_filter(capture_dns: bool) -> Result<RawFd> {
     // Default action: Allow everything. We only want to intercept specific calls.
     let mut ctx =
         ScmpFilterContext::new(ScmpAction::Allow).context("Failed to create Seccomp context")?;
 
     // Network syscalls - for monitoring and policy enforcement
-    let network_syscalls = ["connect", "bind"];
+    let mut network_syscalls = vec!["connect", "bind"];
+    if capture_dns {
+        // The resolver receives its answers this way; every other socket
+        // pays for the round trip, so only when domain rules need it
+        network_syscalls.push("recvfrom");
+    }
 
     // Filesystem syscalls - for redirect/virtualize functionality
     // These are intercepted to implement path rewriting and copy-on-write
@@ -30,6 +36,12 @@ pub fn install_seccomp_filter() -> Result<RawFd> {
         "faccessat2",
         "mkdir",
         "mkdirat",
+        "unlink",
+        "unlinkat",
+        "rmdir",
+        "rename",
+        "renameat",
+        "renameat2",
     ];
 
     for syscall_name in network_syscalls.iter().chain(fs_syscalls.iter()) {
@@ -73,14 +85,11 @@ pub fn recv_fd(socket: RawFd) -> Result<OwnedFd> {
         .context("Failed to receive FD from socket")?;
 
     for cmsg in msg.cmsgs()? {
-        match cmsg {
-            ControlMessageOwned::ScmRights(fds) => {
-                if let Some(&fd) = fds.first() {
-                    // Safety: We just received this FD from recvmsg, so we own it now.
-                    return Ok(unsafe { OwnedFd::from_raw_fd(fd) });
-                }
+        if let ControlMessageOwned::ScmRights(fds) = cmsg {
+            if let Some(&fd) = fds.first() {
+                // Safety: We just received this FD from recvmsg, so we own it now.
+                return Ok(unsafe { OwnedFd::from_raw_fd(fd) });
             }
-            _ => {}
         }
     }
 
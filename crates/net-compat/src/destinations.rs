@@ -117,7 +117,7 @@ impl DestinationTracker {
             .iter()
             .map(|(k, v)| (k, *v))
             .collect();
-        counts.sort_by(|a, b| b.1.cmp(&a.1));
+        counts.sort_by_key(|entry| std::cmp::Reverse(entry.1));
         counts.truncate(n);
         counts
     }
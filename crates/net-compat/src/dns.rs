@@ -3,9 +3,12 @@
 //! This module tracks DNS queries and their responses to provide
 //! domain-level awareness for the policy engine.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::net::IpAddr;
 use std::time::{Duration, Instant};
 
+use crate::dns_parser::{parse_packet, RecordData};
+
 /// Observed DNS query and its resolution
 #[derive(Debug, Clone)]
 pub struct DnsObservation {
@@ -118,6 +121,40 @@ impl DnsCapture {
         }
     }
 
+    /// Records the addresses in a DNS response packet under the name asked
+    /// for and every alias on the way to them. Returns false when `packet`
+    /// is not a successful response carrying addresses.
+    pub fn record_packet(&mut self, packet: &[u8]) -> bool {
+        let Ok(packet) = parse_packet(packet) else {
+            return false;
+        };
+        if !packet.header.is_response() || !packet.header.is_success() {
+            return false;
+        }
+        let resolved: Vec<String> = packet
+            .answers
+            .iter()
+            .filter_map(|record| match record.rdata {
+                RecordData::A(octets) => Some(IpAddr::from(octets).to_string()),
+                RecordData::AAAA(octets) => Some(IpAddr::from(octets).to_string()),
+                _ => None,
+            })
+            .collect();
+        if resolved.is_empty() {
+            return false;
+        }
+        let names: BTreeSet<&str> = packet
+            .questions
+            .iter()
+            .map(|question| question.name.as_str())
+            .chain(packet.answers.iter().map(|record| record.name.as_str()))
+            .collect();
+        for name in names {
+            self.record_response(name, resolved.clone());
+        }
+        true
+    }
+
     /// Looks up a domain's observation
     pub fn lookup(&self, domain: &str) -> Option<&DnsObservation> {
         self.queries.get(&normalize_domain(domain))
@@ -256,6 +293,29 @@ mod tests {
         assert!(domains.contains(&"cdn.example.com"));
     }
 
+    #[test]
+    fn test_record_packet_follows_aliases() {
+        // cdn.example.com CNAME edge.example.net, A 203.0.113.7
+        let mut packet = vec![
+            0x12, 0x34, 0x81, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00,
+        ];
+        packet.extend_from_slice(b"\x03cdn\x07example\x03com\x00\x00\x01\x00\x01");
+        packet.extend_from_slice(&[0xc0, 0x0c, 0x00, 0x05, 0x00, 0x01, 0, 0, 0, 60, 0x00, 0x12]);
+        packet.extend_from_slice(b"\x04edge\x07example\x03net\x00");
+        packet.extend_from_slice(&[0xc0, 0x2d, 0x00, 0x01, 0x00, 0x01, 0, 0, 0, 60, 0x00, 0x04]);
+        packet.extend_from_slice(&[203, 0, 113, 7]);
+
+        let mut capture = DnsCapture::new();
+        assert!(capture.record_packet(&packet));
+        let mut domains = capture.domains_for_ip("203.0.113.7");
+        domains.sort();
+        assert_eq!(domains, vec!["cdn.example.com", "edge.example.net"]);
+
+        // A query carries no answers
+        packet[2] = 0x01;
+        assert!(!DnsCapture::new().record_packet(&packet));
+    }
+
     #[test]
     fn test_capacity_limit() {
         let mut capture = DnsCapture::with_capacity(3);
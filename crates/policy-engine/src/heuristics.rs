@@ -0,0 +1,349 @@
+//! Ransomware-style write heuristics.
+//!
+//! Writes, renames and deletes seen by the seccomp handler and the
+//! filesystem watcher are kept for a sliding window and checked for bursts
+//! across many directories, the user's files rewritten with output that
+//! looks encrypted, and mass deletion of the user's files. Both sources
+//! report most changes, so files are counted rather than events. A heuristic that fires turns the attempt that set it
+//! off into a systemic-risk denial, then starts counting again.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+
+use winewarden_core::config::{ConfigPaths, WriteHeuristicsConfig};
+use winewarden_core::paths::PathPattern;
+use winewarden_core::types::{AccessAttempt, AccessKind, AccessTarget};
+
+use crate::decision::{DecisionAction, PolicyDecision};
+
+/// Rule name on decisions made by these heuristics.
+pub const MASS_MODIFICATION_RULE: &str = "Mass modification";
+
+/// Shorter samples cannot show high entropy, so they are not judged.
+const MIN_SAMPLE_BYTES: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WritePattern {
+    WriteBurst,
+    EncryptedOutput,
+    MassDeletion,
+}
+
+/// A heuristic that fired
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MassModification {
+    pub pattern: WritePattern,
+    pub detail: String,
+}
+
+impl MassModification {
+    pub fn decision(&self) -> PolicyDecision {
+        PolicyDecision {
+            action: DecisionAction::Deny,
+            reason: format!("Mass modification: {}", self.detail),
+            zone_label: None,
+            systemic_risk: true,
+            rule: Some(MASS_MODIFICATION_RULE.to_string()),
+        }
+    }
+}
+
+/// Recent file changes for one session
+#[derive(Debug, Clone)]
+pub struct WriteAnalyzer {
+    config: WriteHeuristicsConfig,
+    protected: Vec<PathPattern>,
+    writes: Window,
+    encrypted: Window,
+    deletes: Window,
+}
+
+/// Paths changed within the window, oldest first, with how often each
+/// file and directory appears so the counts stay cheap on every write
+#[derive(Debug, Clone, Default)]
+struct Window {
+    changes: VecDeque<(OffsetDateTime, PathBuf)>,
+    files: HashMap<PathBuf, usize>,
+    directories: HashMap<PathBuf, usize>,
+}
+
+impl Window {
+    fn push(&mut self, at: OffsetDateTime, path: &Path, span: Duration) {
+        *self.files.entry(path.to_path_buf()).or_default() += 1;
+        if let Some(parent) = path.parent() {
+            *self.directories.entry(parent.to_path_buf()).or_default() += 1;
+        }
+        self.changes.push_back((at, path.to_path_buf()));
+        while self
+            .changes
+            .front()
+            .is_some_and(|(oldest, _)| at - *oldest > span)
+        {
+            if let Some((_, oldest)) = self.changes.pop_front() {
+                forget(&mut self.files, &oldest);
+                if let Some(parent) = oldest.parent() {
+                    forget(&mut self.directories, parent);
+                }
+            }
+        }
+    }
+
+    fn files(&self) -> usize {
+        self.files.len()
+    }
+
+    fn directories(&self) -> usize {
+        self.directories.len()
+    }
+}
+
+fn forget(counts: &mut HashMap<PathBuf, usize>, path: &Path) {
+    if let Some(count) = counts.get_mut(path) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(path);
+        }
+    }
+}
+
+impl WriteAnalyzer {
+    pub fn from_config(config: &WriteHeuristicsConfig, paths: &ConfigPaths) -> Result<Self> {
+        let protected = config
+            .protected_paths
+            .iter()
+            .map(|pattern| PathPattern::compile(pattern, paths))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            config: config.clone(),
+            protected,
+            writes: Window::default(),
+            encrypted: Window::default(),
+            deletes: Window::default(),
+        })
+    }
+
+    /// Forgets every change seen so far
+    pub fn reset(&mut self) {
+        self.writes = Window::default();
+        self.encrypted = Window::default();
+        self.deletes = Window::default();
+    }
+
+    /// Counts a change to a file. `sample` is the start of what was
+    /// written, when the caller could read it back.
+    pub fn observe(
+        &mut self,
+        attempt: &AccessAttempt,
+        prefix_root: &Path,
+        sample: Option<&[u8]>,
+    ) -> Option<MassModification> {
+        if !self.config.enabled {
+            return None;
+        }
+        let AccessTarget::Path(path) = &attempt.target else {
+            return None;
+        };
+        let seconds = self.config.window_secs;
+        let span = Duration::seconds(i64::try_from(seconds).unwrap_or(i64::MAX));
+        let at = attempt.timestamp;
+
+        match attempt.kind {
+            AccessKind::Write | AccessKind::Rename => {
+                self.writes.push(at, path, span);
+                let (files, directories) = (self.writes.files(), self.writes.directories());
+                if files >= self.config.burst_writes as usize
+                    && directories >= self.config.burst_directories as usize
+                {
+                    self.writes = Window::default();
+                    return Some(MassModification {
+                        pattern: WritePattern::WriteBurst,
+                        detail: format!(
+                            "{files} files written or renamed across {directories} directories within {seconds}s"
+                        ),
+                    });
+                }
+
+                // Games write compressed and encrypted assets of their own
+                if !self.is_protected(path, prefix_root) {
+                    return None;
+                }
+                let entropy = sample
+                    .filter(|sample| sample.len() >= MIN_SAMPLE_BYTES)
+                    .map(entropy)?;
+                if entropy < self.config.entropy_threshold {
+                    return None;
+                }
+                self.encrypted.push(at, path, span);
+                let files = self.encrypted.files();
+                if files >= self.config.encrypted_files as usize {
+                    self.encrypted = Window::default();
+                    return Some(MassModification {
+                        pattern: WritePattern::EncryptedOutput,
+                        detail: format!(
+                            "{files} of the user's files written with content that looks encrypted within {seconds}s, last {} at {entropy:.2} bits per byte",
+                            path.display()
+                        ),
+                    });
+                }
+                None
+            }
+            AccessKind::Delete => {
+                if !self.is_protected(path, prefix_root) {
+                    return None;
+                }
+                self.deletes.push(at, path, span);
+                let files = self.deletes.files();
+                if files >= self.config.mass_deletes as usize {
+                    self.deletes = Window::default();
+                    return Some(MassModification {
+                        pattern: WritePattern::MassDeletion,
+                        detail: format!("{files} of the user's files deleted within {seconds}s"),
+                    });
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn is_protected(&self, path: &Path, prefix_root: &Path) -> bool {
+        self.protected
+            .iter()
+            .any(|pattern| pattern.matches(path, prefix_root))
+    }
+}
+
+/// Shannon entropy of `bytes` in bits per byte, from 0 to 8
+pub fn entropy(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for byte in bytes {
+        counts[*byte as usize] += 1;
+    }
+    let total = bytes.len() as f64;
+    counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths() -> ConfigPaths {
+        ConfigPaths::under(Path::new("/tmp"))
+    }
+
+    fn attempt(kind: AccessKind, path: String, seconds: i64) -> AccessAttempt {
+        AccessAttempt {
+            timestamp: OffsetDateTime::UNIX_EPOCH + Duration::seconds(seconds),
+            kind,
+            target: AccessTarget::Path(PathBuf::from(path)),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_write_burst_needs_many_directories() {
+        let config = WriteHeuristicsConfig {
+            burst_writes: 10,
+            burst_directories: 5,
+            ..WriteHeuristicsConfig::default()
+        };
+        let mut analyzer = WriteAnalyzer::from_config(&config, &paths()).unwrap();
+        let prefix = Path::new("/tmp/prefix");
+
+        // Many writes to one directory are a game saving, not a burst
+        for n in 0..20 {
+            let write = attempt(AccessKind::Write, format!("/tmp/prefix/cache/{n}"), 0);
+            assert_eq!(analyzer.observe(&write, prefix, None), None);
+        }
+        // One file reported again and again is still one file
+        let mut analyzer = WriteAnalyzer::from_config(&config, &paths()).unwrap();
+        for n in 0..20 {
+            let write = attempt(AccessKind::Write, format!("/tmp/prefix/d{}/f", n % 5), 0);
+            assert_eq!(analyzer.observe(&write, prefix, None), None);
+        }
+        // Writes spread too thin in time never fill the window
+        let mut analyzer = WriteAnalyzer::from_config(&config, &paths()).unwrap();
+        for n in 0..20 {
+            let write = attempt(AccessKind::Rename, format!("/tmp/prefix/d{n}/f"), n * 11);
+            assert_eq!(analyzer.observe(&write, prefix, None), None);
+        }
+
+        let found = (0..10)
+            .filter_map(|n| {
+                let write = attempt(
+                    AccessKind::Rename,
+                    format!("/tmp/prefix/d{n}/f.locked"),
+                    300,
+                );
+                analyzer.observe(&write, prefix, None)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].pattern, WritePattern::WriteBurst);
+        assert!(found[0].decision().systemic_risk);
+    }
+
+    #[test]
+    fn test_encrypted_output_and_mass_deletion() {
+        let config = WriteHeuristicsConfig {
+            encrypted_files: 3,
+            mass_deletes: 3,
+            ..WriteHeuristicsConfig::default()
+        };
+        let mut analyzer = WriteAnalyzer::from_config(&config, &paths()).unwrap();
+        let prefix = Path::new("/tmp/prefix");
+        let random: Vec<u8> = (0..4096u32)
+            .map(|n| (n.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+        let text = b"plain save data ".repeat(64);
+        assert!(entropy(&random) > 7.5);
+        assert!(entropy(&text) < 4.0);
+
+        let documents = "/tmp/prefix/drive_c/users/steamuser/Documents";
+
+        // A game's own packed assets are not the user's files
+        for n in 0..3 {
+            let write = attempt(AccessKind::Write, format!("/tmp/prefix/assets{n}.pak"), 0);
+            assert_eq!(analyzer.observe(&write, prefix, Some(&random)), None);
+        }
+        let mut results = Vec::new();
+        for n in 0..3 {
+            let write = attempt(AccessKind::Write, format!("{documents}/save{n}"), 0);
+            results.push(analyzer.observe(&write, prefix, Some(&text)));
+            results.push(analyzer.observe(&write, prefix, Some(&random)));
+        }
+        let found: Vec<_> = results.into_iter().flatten().collect();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].pattern, WritePattern::EncryptedOutput);
+
+        let outside = attempt(
+            AccessKind::Delete,
+            "/tmp/prefix/drive_c/windows/temp/a".to_string(),
+            0,
+        );
+        assert_eq!(analyzer.observe(&outside, prefix, None), None);
+        let found: Vec<_> = (0..3)
+            .filter_map(|n| {
+                let delete = attempt(AccessKind::Delete, format!("{documents}/photo{n}.png"), 1);
+                analyzer.observe(&delete, prefix, None)
+            })
+            .collect();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].pattern, WritePattern::MassDeletion);
+    }
+}
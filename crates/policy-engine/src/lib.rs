@@ -1,29 +1,75 @@
-use std::cell::RefCell;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
 
 use anyhow::Result;
 
-use winewarden_core::config::{Config, ConfigPaths};
+use winewarden_core::config::{Config, ConfigPaths, NetworkConfig, NetworkMode, ProcessConfig};
+use winewarden_core::devices::DevicePolicy;
+use winewarden_core::paths::ZoneSet;
+use winewarden_core::store::{BehaviorBaseline, RunSummary};
 use winewarden_core::trust::TrustTier;
-use winewarden_core::types::{AccessAttempt, AccessTarget};
+use winewarden_core::types::{AccessAttempt, AccessKind, AccessTarget};
 
 mod decision;
+pub mod heuristics;
 pub mod rules;
+pub mod simulate;
 pub mod trust;
 pub mod zones;
 
-pub use decision::{DecisionAction, PolicyDecision};
+pub use decision::{DecisionAction, DecisionTrace, PolicyDecision, TraceStep};
+pub use heuristics::{MassModification, WriteAnalyzer, WritePattern, MASS_MODIFICATION_RULE};
+pub use rules::policy::RuleSet;
+pub use simulate::{simulate, ChangeKind, DecisionChange, Simulation};
+pub use trust::baseline::{detect_anomalies, Anomaly, AnomalyKind};
+pub use trust::lifecycle::{TransitionKind, TrustLifecycle, TrustTransition};
 pub use trust::scoring::{calculate_trust_score, BehaviorProfile, TrustScore, TrustScoringConfig};
 
+const DNS_PORT: u16 = 53;
+
 /// Policy engine for evaluating access attempts and making security decisions.
+///
+/// The engine is `Sync`: the seccomp handler and the watchers evaluate
+/// through one shared engine from their own threads. Evaluations take a
+/// reference to the current compiled rules and never block each other on
+/// them; only the tracking data sits behind locks.
 #[derive(Debug)]
 pub struct PolicyEngine {
     config: Config,
-    sacred_zones: Vec<winewarden_core::paths::SacredZone>,
+    /// Sacred zones and process rules; replaceable while a session runs
+    rules: RwLock<Arc<LiveRules>>,
     /// Tracks process execution statistics
-    process_tracker: RefCell<rules::process::ProcessTracker>,
+    process_tracker: Mutex<rules::process::ProcessTracker>,
     /// Tracks behavior profile for trust scoring
-    behavior_profile: RefCell<BehaviorProfile>,
+    behavior_profile: Mutex<BehaviorProfile>,
+    /// Recent file changes, for the mass-modification heuristics
+    write_analyzer: Mutex<WriteAnalyzer>,
+    /// What the executable usually does, from its trust record
+    baseline: BehaviorBaseline,
+}
+
+/// The parts of the policy that can be swapped into a running engine
+#[derive(Debug, Clone)]
+pub struct LiveRules {
+    pub policy: RuleSet,
+    pub sacred_zones: ZoneSet,
+    pub devices: DevicePolicy,
+    pub process: ProcessConfig,
+    pub network: NetworkConfig,
+}
+
+impl LiveRules {
+    /// Compiles the ordered rules, so a bad pattern fails here rather than
+    /// during evaluation
+    pub fn from_config(config: &Config, paths: &ConfigPaths) -> Result<Self> {
+        Ok(Self {
+            policy: RuleSet::compile(&config.rules, paths)?,
+            sacred_zones: zones::sacred::load_sacred_zones(config, paths)?,
+            devices: DevicePolicy::compile(&config.devices)?,
+            process: config.process.clone(),
+            network: config.network.clone(),
+        })
+    }
 }
 
 /// Context for policy evaluation
@@ -36,50 +82,70 @@ pub struct PolicyContext {
 impl PolicyEngine {
     /// Creates a new PolicyEngine from configuration
     pub fn from_config(config: Config, paths: &ConfigPaths) -> Result<Self> {
-        let sacred_zones = zones::sacred::load_sacred_zones(&config, paths)?;
+        let rules = LiveRules::from_config(&config, paths)?;
+        let write_analyzer = WriteAnalyzer::from_config(&config.write_heuristics, paths)?;
         Ok(Self {
             config,
-            sacred_zones,
-            process_tracker: RefCell::new(rules::process::ProcessTracker::new()),
-            behavior_profile: RefCell::new(BehaviorProfile::new()),
+            rules: RwLock::new(Arc::new(rules)),
+            process_tracker: Mutex::new(rules::process::ProcessTracker::new()),
+            behavior_profile: Mutex::new(BehaviorProfile::new()),
+            write_analyzer: Mutex::new(write_analyzer),
+            baseline: BehaviorBaseline::default(),
         })
     }
 
+    /// Scores runs against the executable's stored behavior baseline
+    pub fn with_baseline(mut self, baseline: BehaviorBaseline) -> Self {
+        self.baseline = baseline;
+        self
+    }
+
+    /// Replaces the ordered rules, sacred zones, device classes, process
+    /// rules and network settings; later evaluations use the new rules,
+    /// tracking data is kept.
+    /// Evaluations already running finish with the rules they started with
+    pub fn update_rules(&self, rules: LiveRules) {
+        *self.rules.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(rules);
+    }
+
+    /// The rules evaluations currently use
+    pub fn rules(&self) -> Arc<LiveRules> {
+        Arc::clone(&self.rules.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
     /// Evaluates an access attempt against the policy
     pub fn evaluate(&self, attempt: &AccessAttempt, context: &PolicyContext) -> PolicyDecision {
+        self.evaluate_with_sample(attempt, context, None)
+    }
+
+    /// Like `evaluate`, for a write seen after the fact; `sample` is the
+    /// start of the written file, checked for content that looks encrypted
+    pub fn evaluate_with_sample(
+        &self,
+        attempt: &AccessAttempt,
+        context: &PolicyContext,
+        sample: Option<&[u8]>,
+    ) -> PolicyDecision {
         // Update behavior profile based on attempt
         self.update_behavior_profile(attempt);
 
-        let decision = match &attempt.target {
-            AccessTarget::Path(path) => {
-                rules::filesystem::evaluate_path(path, &context.prefix_root, &self.sacred_zones)
-            }
-            AccessTarget::Network(target) => {
-                // Track network destinations
-                self.behavior_profile
-                    .borrow_mut()
-                    .record_outbound_connection(&target.host);
-                rules::network::evaluate_network(&self.config, context.trust_tier)
-            }
-            AccessTarget::Device(name) => PolicyDecision {
-                action: DecisionAction::Deny,
-                reason: format!("Device access blocked: {name}"),
-                zone_label: Some("Sacred devices".to_string()),
-                systemic_risk: true,
-            },
-            AccessTarget::Socket(name) => PolicyDecision {
-                action: DecisionAction::Deny,
-                reason: format!("System socket access blocked: {name}"),
-                zone_label: Some("System sockets".to_string()),
-                systemic_risk: true,
-            },
-        };
+        let rules = self.rules();
+        let mut decision = rules.policy.evaluate(
+            attempt,
+            context,
+            &rules.sacred_zones,
+            &rules.devices,
+            &rules.network,
+        );
+
+        // A mass modification overrides whatever the rules said
+        if let Some(found) = self.writes().observe(attempt, &context.prefix_root, sample) {
+            decision = found.decision();
+        }
 
         // Track denied attempts
         if matches!(decision.action, DecisionAction::Deny) {
-            self.behavior_profile
-                .borrow_mut()
-                .record_denied_attempt(&decision.reason);
+            self.profile().record_denied_attempt(&decision.reason);
         }
 
         decision
@@ -91,79 +157,212 @@ impl PolicyEngine {
         process: &str,
         _context: &PolicyContext,
     ) -> PolicyDecision {
+        // Checking the limit and counting the spawn happen under one lock
         let decision = rules::process::evaluate_process_spawn(
             process,
-            &self.config.process,
-            &mut self.process_tracker.borrow_mut(),
+            &self.rules().process,
+            &mut self.tracker(),
         );
 
         // Update behavior profile
+        let mut profile = self.profile();
         if matches!(decision.action, DecisionAction::Allow) {
-            self.behavior_profile
-                .borrow_mut()
-                .record_child_process(process);
+            profile.record_child_process(process);
         } else {
-            self.behavior_profile
-                .borrow_mut()
-                .record_denied_attempt(&decision.reason);
+            profile.record_denied_attempt(&decision.reason);
         }
 
         decision
     }
 
-    /// Calculates current trust score based on observed behavior
+    /// Evaluates an access attempt and records every rule considered.
+    /// Tracking data is left alone, so a trace never counts as behavior
+    pub fn explain(&self, attempt: &AccessAttempt, context: &PolicyContext) -> DecisionTrace {
+        let rules = self.rules();
+        rules.policy.explain(
+            attempt,
+            context,
+            &rules.sacred_zones,
+            &rules.devices,
+            &rules.network,
+        )
+    }
+
+    /// Evaluates a process spawn and records every check considered,
+    /// against a copy of the process tracker
+    pub fn explain_process_spawn(&self, process: &str, _context: &PolicyContext) -> DecisionTrace {
+        let mut tracker = self.process_tracker();
+        rules::process::explain_process_spawn(process, &self.rules().process, &mut tracker)
+    }
+
+    /// The device nodes under `dev_root` the sandbox should open up, with
+    /// the kinds of access (read, write) the rules allow on each at the
+    /// context's tier. Decided like attempts on the nodes would be, without
+    /// counting as behavior. Nodes that appear later are not listed
+    pub fn device_grants(
+        &self,
+        context: &PolicyContext,
+        dev_root: &Path,
+    ) -> Vec<(PathBuf, Vec<AccessKind>)> {
+        let rules = self.rules();
+        let allowed = |node: &Path, kind| {
+            let attempt = AccessAttempt {
+                timestamp: time::OffsetDateTime::now_utc(),
+                kind,
+                target: AccessTarget::Path(node.to_path_buf()),
+                note: None,
+            };
+            let decision = rules.policy.evaluate(
+                &attempt,
+                context,
+                &rules.sacred_zones,
+                &rules.devices,
+                &rules.network,
+            );
+            matches!(decision.action, DecisionAction::Allow)
+        };
+        rules
+            .devices
+            .nodes(dev_root)
+            .into_iter()
+            .filter_map(|node| {
+                let kinds: Vec<AccessKind> = [AccessKind::Read, AccessKind::Write]
+                    .into_iter()
+                    .filter(|kind| allowed(&node, *kind))
+                    .collect();
+                (!kinds.is_empty()).then_some((node, kinds))
+            })
+            .collect()
+    }
+
+    /// Whether an enforced network rule names domains, which connects can
+    /// only meet through the DNS answers the monitor reads for them
+    pub fn matches_domains(&self) -> bool {
+        let network = &self.rules().network;
+        matches!(network.mode, NetworkMode::Enforce)
+            && network.rules.iter().any(|rule| !rule.domains.is_empty())
+    }
+
+    /// Whether the session should be paused when a mass-modification
+    /// heuristic fires
+    pub fn freeze_on_mass_modification(&self) -> bool {
+        self.config.write_heuristics.freeze_session
+    }
+
+    /// Calculates current trust score based on observed behavior and how
+    /// far it departs from the executable's baseline
     pub fn calculate_trust_score(&self, current_tier: TrustTier) -> TrustScore {
-        let config = TrustScoringConfig::default();
-        calculate_trust_score(current_tier, &self.behavior_profile.borrow(), &config)
+        let profile = self.profile();
+        let anomalies = detect_anomalies(
+            &profile.run_behavior(),
+            &self.baseline,
+            &self.config.trust.baseline,
+        );
+        calculate_trust_score(
+            current_tier,
+            &profile,
+            anomalies,
+            &self.config.trust.scoring,
+        )
     }
 
-    /// Returns the current behavior profile
-    pub fn behavior_profile(&self) -> std::cell::Ref<'_, BehaviorProfile> {
-        self.behavior_profile.borrow()
+    /// Scores the finished run and decides the tier the executable keeps
+    pub fn assess_run(
+        &self,
+        current_tier: TrustTier,
+        prior_clean_runs: u32,
+        systemic_risks: u32,
+    ) -> TrustTransition {
+        let score = self.calculate_trust_score(current_tier);
+        TrustLifecycle::from_config(&self.config.trust).assess(
+            current_tier,
+            prior_clean_runs,
+            &score,
+            systemic_risks,
+        )
     }
 
-    /// Returns process tracking statistics
-    pub fn process_tracker(&self) -> std::cell::Ref<'_, rules::process::ProcessTracker> {
-        self.process_tracker.borrow()
+    /// What the trust record learns from the finished run. Runs that
+    /// departed from the baseline only teach it a few new names
+    pub fn run_summary(&self, transition: &TrustTransition) -> RunSummary {
+        RunSummary {
+            score: transition.score,
+            clean: transition.clean,
+            anomalous: !transition.anomalies.is_empty(),
+            behavior: self.profile().run_behavior(),
+        }
+    }
+
+    /// Returns a snapshot of the current behavior profile
+    pub fn behavior_profile(&self) -> BehaviorProfile {
+        self.profile().clone()
+    }
+
+    /// Returns a snapshot of the process tracking statistics
+    pub fn process_tracker(&self) -> rules::process::ProcessTracker {
+        self.tracker().clone()
     }
 
     /// Updates behavior profile based on an access attempt
     fn update_behavior_profile(&self, attempt: &AccessAttempt) {
-        match attempt.kind {
-            winewarden_core::types::AccessKind::Write => {
-                if let AccessTarget::Path(path) = &attempt.target {
-                    if let Some(path_str) = path.to_str() {
-                        self.behavior_profile
-                            .borrow_mut()
-                            .record_file_modification(path_str);
-                    }
+        match &attempt.target {
+            AccessTarget::Path(path) if attempt.kind == AccessKind::Write => {
+                if let Some(path_str) = path.to_str() {
+                    self.profile().record_file_modification(path_str);
                 }
             }
+            // Children the monitor finds are reported as executes
+            AccessTarget::Path(path) if attempt.kind == AccessKind::Execute => {
+                let name = path.file_name().unwrap_or(path.as_os_str());
+                self.profile().record_child_process(&name.to_string_lossy());
+            }
+            AccessTarget::Network(target) if target.port == DNS_PORT => {
+                self.profile().record_dns_query()
+            }
+            // Track network destinations
+            AccessTarget::Network(target) => {
+                self.profile().record_outbound_connection(&target.host)
+            }
             _ => {}
         }
     }
 
     /// Resets all tracking data
     pub fn reset_tracking(&self) {
-        *self.process_tracker.borrow_mut() = rules::process::ProcessTracker::new();
-        *self.behavior_profile.borrow_mut() = BehaviorProfile::new();
+        *self.tracker() = rules::process::ProcessTracker::new();
+        *self.profile() = BehaviorProfile::new();
+        self.writes().reset();
+    }
+
+    /// A panic while a lock was held leaves plain counters behind, so a
+    /// poisoned lock is still used
+    fn profile(&self) -> MutexGuard<'_, BehaviorProfile> {
+        self.behavior_profile
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn writes(&self) -> MutexGuard<'_, WriteAnalyzer> {
+        self.write_analyzer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn tracker(&self) -> MutexGuard<'_, rules::process::ProcessTracker> {
+        self.process_tracker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use winewarden_core::types::AccessKind;
+    use std::fs;
 
     fn create_test_engine() -> PolicyEngine {
         let config = Config::default_config();
-        let paths = ConfigPaths {
-            config_path: PathBuf::from("/tmp/config.toml"),
-            data_dir: PathBuf::from("/tmp/data"),
-            report_dir: PathBuf::from("/tmp/reports"),
-            trust_db_path: PathBuf::from("/tmp/trust.json"),
-            snapshot_dir: PathBuf::from("/tmp/snapshots"),
-        };
+        let paths = ConfigPaths::under(Path::new("/tmp"));
         PolicyEngine::from_config(config, &paths).unwrap()
     }
 
@@ -203,6 +402,141 @@ mod tests {
         assert!(matches!(decision.action, DecisionAction::Deny));
     }
 
+    #[test]
+    fn test_update_rules_applies_to_later_evaluations() {
+        let engine = create_test_engine();
+        let context = PolicyContext {
+            prefix_root: PathBuf::from("/tmp/prefix"),
+            trust_tier: TrustTier::Yellow,
+        };
+        let connect = AccessAttempt {
+            timestamp: time::OffsetDateTime::now_utc(),
+            kind: AccessKind::Network,
+            target: AccessTarget::Network(winewarden_core::types::NetworkTarget {
+                host: "203.0.113.7".to_string(),
+                port: 443,
+                protocol: "tcp".to_string(),
+                direction: winewarden_core::types::NetworkDirection::Connect,
+                names: Vec::new(),
+            }),
+            note: None,
+        };
+        let decision = engine.evaluate_process_spawn("bash", &context);
+        assert!(matches!(decision.action, DecisionAction::Deny));
+        let decision = engine.evaluate(&connect, &context);
+        assert!(matches!(decision.action, DecisionAction::Allow));
+
+        let mut rules = (*engine.rules()).clone();
+        rules.process.allow_shell_execution = true;
+        rules.process.allowed_patterns.push("bash".to_string());
+        rules.network = toml::from_str(
+            r#"
+            mode = "enforce"
+            defaults = { green = "deny", yellow = "deny", red = "deny" }
+            "#,
+        )
+        .unwrap();
+        engine.update_rules(rules);
+
+        let decision = engine.evaluate_process_spawn("bash", &context);
+        assert!(matches!(decision.action, DecisionAction::Allow));
+        // The network settings are swapped in with the rest
+        let decision = engine.evaluate(&connect, &context);
+        assert!(matches!(decision.action, DecisionAction::Deny));
+        // The earlier denial is still tracked
+        assert_eq!(engine.process_tracker().denied.len(), 1);
+    }
+
+    #[test]
+    fn test_explain_lists_rules_up_to_the_decision() {
+        let engine = create_test_engine();
+        let context = PolicyContext {
+            prefix_root: PathBuf::from("/tmp/prefix"),
+            trust_tier: TrustTier::Red,
+        };
+        let attempt = AccessAttempt {
+            timestamp: time::OffsetDateTime::now_utc(),
+            kind: AccessKind::Write,
+            target: AccessTarget::Path(PathBuf::from("/etc/passwd")),
+            note: None,
+        };
+
+        let trace = engine.explain(&attempt, &context);
+        let steps: Vec<(&str, bool)> = trace
+            .steps
+            .iter()
+            .map(|step| (step.rule.as_str(), step.matched))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("Sacred zones", true),
+                ("Devices", true),
+                ("Sacred devices", false),
+                ("System sockets", false),
+                ("Network", false),
+                ("Within prefix", false),
+                ("Prefix boundary", true),
+            ]
+        );
+        assert_eq!(
+            trace.steps[0].detail,
+            "no sacred zone covers the path, passed on"
+        );
+        assert_eq!(
+            trace.steps[1].detail,
+            "no device class covers the target, passed on"
+        );
+        assert_eq!(trace.steps[2].detail, "kind is write, rule wants device");
+        assert!(trace.steps[5]
+            .detail
+            .starts_with("path matches none of ${PREFIX}"));
+        assert!(matches!(trace.decision.action, DecisionAction::Deny));
+        assert_eq!(trace.decision.rule.as_deref(), Some("Prefix boundary"));
+
+        let trace = engine.explain_process_spawn("bash", &context);
+        let last = trace.steps.last().unwrap();
+        assert_eq!(last.rule, "Shell execution");
+        assert!(last.matched);
+        // Explaining leaves the tracking data alone
+        assert!(engine.process_tracker().denied.is_empty());
+        assert_eq!(engine.behavior_profile().denied_attempts, 0);
+    }
+
+    #[test]
+    fn test_engine_is_shared_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<PolicyEngine>();
+
+        let engine = create_test_engine();
+        let context = PolicyContext {
+            prefix_root: PathBuf::from("/tmp/prefix"),
+            trust_tier: TrustTier::Yellow,
+        };
+        let write = AccessAttempt {
+            timestamp: time::OffsetDateTime::now_utc(),
+            kind: AccessKind::Write,
+            target: AccessTarget::Path(PathBuf::from("/tmp/prefix/drive_c/save.dat")),
+            note: None,
+        };
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..20 {
+                        engine.evaluate(&write, &context);
+                        engine.evaluate_process_spawn("wine64", &context);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(engine.behavior_profile().file_modifications, 80);
+        // The default limit of 50 children holds however spawns interleave
+        let tracker = engine.process_tracker();
+        assert_eq!(tracker.child_count, 50);
+        assert_eq!(tracker.allowed.len(), 50);
+    }
+
     #[test]
     fn test_trust_score() {
         let engine = create_test_engine();
@@ -224,4 +558,127 @@ mod tests {
         let new_score = engine.calculate_trust_score(TrustTier::Yellow);
         assert!(new_score.score <= score.score);
     }
+
+    #[test]
+    fn test_executes_count_as_child_processes() {
+        let engine = create_test_engine();
+        let context = PolicyContext {
+            prefix_root: PathBuf::from("/tmp/prefix"),
+            trust_tier: TrustTier::Yellow,
+        };
+        let attempt = AccessAttempt {
+            timestamp: time::OffsetDateTime::now_utc(),
+            kind: AccessKind::Execute,
+            target: AccessTarget::Path(PathBuf::from("/tmp/prefix/drive_c/launcher.exe")),
+            note: Some("child process pid 42".to_string()),
+        };
+        engine.evaluate(&attempt, &context);
+
+        let profile = engine.behavior_profile();
+        assert_eq!(profile.child_process_count, 1);
+        assert!(profile.child_processes.contains("launcher.exe"));
+    }
+
+    #[test]
+    fn test_device_grants_follow_the_rules() {
+        let dev = std::env::temp_dir().join(format!("ww-dev-{}", std::process::id()));
+        fs::create_dir_all(dev.join("input")).unwrap();
+        for node in ["event0", "event1", "mouse0"] {
+            fs::write(dev.join("input").join(node), b"").unwrap();
+        }
+        let events = format!("{}/input/event*", dev.display());
+        let mut config = Config::default_config();
+        config.devices.gamepad.paths = vec![events];
+        config.devices.gamepad.ids.clear();
+        config.devices.gamepad.tiers = vec![TrustTier::Green];
+        let read_only: Config = toml::from_str(&format!(
+            r#"
+            [[rules]]
+            label = "Read-only pad"
+            when = {{ kind = ["write"], path = ["{}/input/event1"] }}
+            then = "deny"
+            "#,
+            dev.display()
+        ))
+        .unwrap();
+        config.rules.splice(0..0, read_only.rules);
+        let engine =
+            PolicyEngine::from_config(config, &ConfigPaths::under(Path::new("/tmp"))).unwrap();
+        let context = |trust_tier| PolicyContext {
+            prefix_root: PathBuf::from("/tmp/prefix"),
+            trust_tier,
+        };
+
+        let grants = engine.device_grants(&context(TrustTier::Green), &dev);
+        assert_eq!(
+            grants,
+            vec![
+                (
+                    dev.join("input/event0"),
+                    vec![AccessKind::Read, AccessKind::Write]
+                ),
+                (dev.join("input/event1"), vec![AccessKind::Read]),
+            ]
+        );
+        assert!(engine
+            .device_grants(&context(TrustTier::Red), &dev)
+            .is_empty());
+        fs::remove_dir_all(&dev).unwrap();
+    }
+
+    #[test]
+    fn test_assess_run_counts_clean_runs() {
+        let engine = create_test_engine();
+        let transition = engine.assess_run(TrustTier::Yellow, 0, 0);
+        assert!(transition.clean);
+        assert_eq!(transition.clean_runs, 1);
+
+        let transition = engine.assess_run(TrustTier::Yellow, 2, 0);
+        assert_eq!(transition.kind, TransitionKind::Promoted);
+        assert_eq!(transition.new_tier, TrustTier::Green);
+    }
+
+    #[test]
+    fn test_run_scored_against_baseline() {
+        let baseline = BehaviorBaseline {
+            runs: 5,
+            destinations: ["steampowered.com".to_string()].into(),
+            ..BehaviorBaseline::default()
+        };
+        let learning = create_test_engine();
+        let learned = create_test_engine().with_baseline(baseline);
+        let context = PolicyContext {
+            prefix_root: PathBuf::from("/tmp/prefix"),
+            trust_tier: TrustTier::Yellow,
+        };
+        let connect = |host: String, port| AccessAttempt {
+            timestamp: time::OffsetDateTime::now_utc(),
+            kind: AccessKind::Network,
+            target: AccessTarget::Network(winewarden_core::types::NetworkTarget {
+                host,
+                port,
+                protocol: "tcp".to_string(),
+                direction: winewarden_core::types::NetworkDirection::Connect,
+                names: Vec::new(),
+            }),
+            note: None,
+        };
+        for engine in [&learning, &learned] {
+            engine.evaluate(&connect("1.1.1.1".to_string(), 53), &context);
+            for n in 0..40 {
+                engine.evaluate(&connect(format!("host{n}.example"), 443), &context);
+            }
+        }
+
+        let transition = learned.assess_run(TrustTier::Yellow, 0, 0);
+        assert_eq!(transition.anomalies.len(), 1);
+        assert_eq!(transition.anomalies[0].kind, AnomalyKind::NewDestinations);
+        let unscored = learning.calculate_trust_score(TrustTier::Yellow);
+        assert_eq!(transition.score + 15, unscored.score);
+
+        let run = learned.run_summary(&transition);
+        assert!(run.anomalous);
+        assert_eq!(run.behavior.destinations.len(), 40);
+        assert_eq!(run.behavior.dns_queries, 1);
+    }
 }
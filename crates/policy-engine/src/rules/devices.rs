@@ -0,0 +1,38 @@
+use std::path::Path;
+
+use winewarden_core::devices::DevicePolicy;
+use winewarden_core::trust::TrustTier;
+use winewarden_core::types::AccessTarget;
+
+use crate::decision::{DecisionAction, PolicyDecision};
+
+/// Decides an attempt on a device node by its class. Targets no class
+/// covers are left to the next rule.
+pub fn evaluate_device(
+    target: &AccessTarget,
+    devices: &DevicePolicy,
+    trust_tier: TrustTier,
+) -> Option<PolicyDecision> {
+    let node = match target {
+        AccessTarget::Device(device) => Path::new(device),
+        AccessTarget::Path(path) => path.as_path(),
+        _ => return None,
+    };
+    let class = devices.classify(node)?;
+    let allowed = devices.allows(class, trust_tier);
+    Some(PolicyDecision {
+        action: if allowed {
+            DecisionAction::Allow
+        } else {
+            DecisionAction::Deny
+        },
+        reason: if allowed {
+            format!("Device allowed: {class} at tier {trust_tier}")
+        } else {
+            format!("Device denied: {class} is not allowed at tier {trust_tier}")
+        },
+        zone_label: Some("Devices".to_string()),
+        systemic_risk: !allowed,
+        rule: None,
+    })
+}
@@ -1,65 +1,100 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use winewarden_core::paths::{PathAction, SacredZone};
+use winewarden_core::paths::{PathAction, ZoneSet};
+use winewarden_core::types::AccessKind;
 
 use crate::decision::{DecisionAction, PolicyDecision};
 use crate::zones::redirects::fallback_redirect;
 
-pub fn evaluate_path(path: &Path, prefix_root: &Path, zones: &[SacredZone]) -> PolicyDecision {
-    for zone in zones {
-        if zone.matches(path) {
-            return apply_zone_rule(zone);
-        }
-    }
-
-    if !path.starts_with(prefix_root) {
-        return PolicyDecision {
-            action: DecisionAction::Deny,
-            reason: "Access outside prefix blocked".to_string(),
-            zone_label: Some("Prefix boundary".to_string()),
-            systemic_risk: true,
-        };
-    }
-
-    PolicyDecision {
-        action: DecisionAction::Allow,
-        reason: "Access within prefix allowed".to_string(),
-        zone_label: None,
-        systemic_risk: false,
-    }
+/// The most specific sacred zone covering `path` decides, with its action
+/// for `kind`; `None` if no zone covers the path.
+pub fn evaluate_zones(path: &Path, kind: AccessKind, zones: &ZoneSet) -> Option<PolicyDecision> {
+    zones.find(path).map(|zone| {
+        decide(
+            &zone.label,
+            zone.action_for(kind),
+            zone.redirect_to.as_ref(),
+        )
+    })
 }
 
-fn apply_zone_rule(zone: &SacredZone) -> PolicyDecision {
-    match zone.action {
+pub fn decide(label: &str, action: PathAction, redirect_to: Option<&PathBuf>) -> PolicyDecision {
+    match action {
         PathAction::Allow => PolicyDecision {
             action: DecisionAction::Allow,
-            reason: format!("Access allowed: {}", zone.label),
-            zone_label: Some(zone.label.clone()),
+            reason: format!("Access allowed: {label}"),
+            zone_label: Some(label.to_string()),
             systemic_risk: false,
+            rule: None,
         },
         PathAction::Deny => PolicyDecision {
             action: DecisionAction::Deny,
-            reason: format!("Access denied: {}", zone.label),
-            zone_label: Some(zone.label.clone()),
+            reason: format!("Access denied: {label}"),
+            zone_label: Some(label.to_string()),
             systemic_risk: true,
+            rule: None,
         },
         PathAction::Redirect => {
-            let redirect_to = zone.redirect_to.clone().unwrap_or_else(fallback_redirect);
+            let redirect_to = redirect_to.cloned().unwrap_or_else(fallback_redirect);
             PolicyDecision {
                 action: DecisionAction::Redirect(redirect_to),
-                reason: format!("Access redirected: {}", zone.label),
-                zone_label: Some(zone.label.clone()),
+                reason: format!("Access redirected: {label}"),
+                zone_label: Some(label.to_string()),
                 systemic_risk: true,
+                rule: None,
             }
         }
         PathAction::Virtualize => {
-            let redirect_to = zone.redirect_to.clone().unwrap_or_else(fallback_redirect);
+            let redirect_to = redirect_to.cloned().unwrap_or_else(fallback_redirect);
             PolicyDecision {
                 action: DecisionAction::Virtualize(redirect_to),
-                reason: format!("Access virtualized: {}", zone.label),
-                zone_label: Some(zone.label.clone()),
+                reason: format!("Access virtualized: {label}"),
+                zone_label: Some(label.to_string()),
                 systemic_risk: true,
+                rule: None,
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winewarden_core::config::{ConfigPaths, SacredZoneConfig, ZoneActions};
+
+    #[test]
+    fn test_zone_action_follows_access_kind() {
+        let paths = ConfigPaths::under(Path::new("/tmp"));
+        let zones = ZoneSet::compile(
+            &[SacredZoneConfig {
+                label: "My Games".to_string(),
+                path: "/home/me/Documents/My Games".to_string(),
+                action: PathAction::Allow,
+                redirect_to: Some("${DATA_DIR}/virtual/games".to_string()),
+                actions: ZoneActions {
+                    write: Some(PathAction::Virtualize),
+                    delete: Some(PathAction::Deny),
+                    ..ZoneActions::default()
+                },
+                regex: false,
+            }],
+            &paths,
+        )
+        .unwrap();
+        let save = Path::new("/home/me/Documents/My Games/Skyrim/save1.ess");
+        let action = |kind| evaluate_zones(save, kind, &zones).unwrap().action;
+
+        assert!(matches!(action(AccessKind::Read), DecisionAction::Allow));
+        assert!(matches!(action(AccessKind::Execute), DecisionAction::Allow));
+        assert!(matches!(
+            action(AccessKind::Write),
+            DecisionAction::Virtualize(target) if target == Path::new("/tmp/data/virtual/games")
+        ));
+        // Renames fall back to the write action, deletes have their own.
+        assert!(matches!(
+            action(AccessKind::Rename),
+            DecisionAction::Virtualize(_)
+        ));
+        assert!(matches!(action(AccessKind::Delete), DecisionAction::Deny));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod devices;
 pub mod filesystem;
 pub mod network;
+pub mod policy;
 pub mod process;
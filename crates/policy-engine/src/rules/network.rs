@@ -1,15 +1,26 @@
-use winewarden_core::config::{Config, NetworkMode};
+use std::net::IpAddr;
+
+use winewarden_core::config::{
+    NetworkAction, NetworkConfig, NetworkMode, NetworkProtocol, NetworkRule,
+};
+use winewarden_core::network::domain_matches;
 use winewarden_core::trust::TrustTier;
+use winewarden_core::types::NetworkTarget;
 
 use crate::decision::{DecisionAction, PolicyDecision};
 
-pub fn evaluate_network(config: &Config, trust_tier: TrustTier) -> PolicyDecision {
-    let reason = match config.network.mode {
+pub fn evaluate_network(
+    config: &NetworkConfig,
+    target: &NetworkTarget,
+    trust_tier: TrustTier,
+) -> PolicyDecision {
+    let reason = match config.mode {
         NetworkMode::Observe => "Network observed (no interference)",
         NetworkMode::Permissive => "Network allowed (permissive mode)",
+        NetworkMode::Enforce => return enforce(config, target, trust_tier),
     };
 
-    let systemic_risk = matches!(trust_tier, TrustTier::Red) && config.network.block_on_malicious;
+    let systemic_risk = matches!(trust_tier, TrustTier::Red) && config.block_on_malicious;
     let action = if systemic_risk {
         DecisionAction::Deny
     } else {
@@ -27,5 +38,271 @@ pub fn evaluate_network(config: &Config, trust_tier: TrustTier) -> PolicyDecisio
         reason: final_reason,
         zone_label: Some("Network".to_string()),
         systemic_risk,
+        rule: None,
+    }
+}
+
+/// The first matching rule decides; otherwise the tier's default does.
+fn enforce(
+    config: &NetworkConfig,
+    target: &NetworkTarget,
+    trust_tier: TrustTier,
+) -> PolicyDecision {
+    let destination = format!("{}:{}", target.host, target.port);
+    let (action, reason, rule) = match config
+        .rules
+        .iter()
+        .enumerate()
+        .find(|(_, rule)| rule_matches(rule, target, trust_tier))
+    {
+        Some((index, rule)) => {
+            let name = if rule.label.is_empty() {
+                format!("network.rules[{index}]")
+            } else {
+                rule.label.clone()
+            };
+            let verb = verb(rule.action);
+            (
+                rule.action,
+                format!("Network {verb} by rule '{name}': {destination}"),
+                Some(name),
+            )
+        }
+        None => {
+            let action = config.defaults.for_tier(trust_tier);
+            let verb = verb(action);
+            let reason =
+                format!("Network {verb} by default for {trust_tier:?} tier: {destination}");
+            (action, reason, None)
+        }
+    };
+
+    let denied = action == NetworkAction::Deny;
+    PolicyDecision {
+        action: if denied {
+            DecisionAction::Deny
+        } else {
+            DecisionAction::Allow
+        },
+        reason,
+        zone_label: Some("Network".to_string()),
+        systemic_risk: denied && matches!(trust_tier, TrustTier::Red),
+        rule,
+    }
+}
+
+fn verb(action: NetworkAction) -> &'static str {
+    match action {
+        NetworkAction::Allow => "allowed",
+        NetworkAction::Deny => "denied",
+    }
+}
+
+fn rule_matches(rule: &NetworkRule, target: &NetworkTarget, trust_tier: TrustTier) -> bool {
+    if !rule.tiers.is_empty() && !rule.tiers.contains(&trust_tier) {
+        return false;
+    }
+    if rule
+        .direction
+        .is_some_and(|direction| direction != target.direction)
+    {
+        return false;
+    }
+    // A socket whose protocol could not be read may be either, so a rule
+    // naming one only applies to it when the rule denies
+    let protocol_known = ["tcp", "udp"]
+        .iter()
+        .any(|known| target.protocol.eq_ignore_ascii_case(known));
+    let protocol_matches = match rule.protocol {
+        NetworkProtocol::Any => true,
+        NetworkProtocol::Tcp | NetworkProtocol::Udp if !protocol_known => {
+            rule.action == NetworkAction::Deny
+        }
+        NetworkProtocol::Tcp => target.protocol.eq_ignore_ascii_case("tcp"),
+        NetworkProtocol::Udp => target.protocol.eq_ignore_ascii_case("udp"),
+    };
+    if !protocol_matches {
+        return false;
+    }
+    if !rule.ports.is_empty() && !rule.ports.iter().any(|range| range.contains(target.port)) {
+        return false;
+    }
+    if rule.domains.is_empty() && rule.cidrs.is_empty() {
+        return true;
+    }
+    // Connects seen at the syscall level carry an address, with the names
+    // the game's DNS answers gave for it; explained or replayed attempts
+    // may name the host directly
+    let names_match = |name: &str| {
+        rule.domains
+            .iter()
+            .any(|domain| domain_matches(name, domain))
+    };
+    match target.host.parse::<IpAddr>() {
+        Ok(address) => {
+            rule.cidrs.iter().any(|cidr| cidr.contains(address))
+                || target.names.iter().any(|name| names_match(name))
+        }
+        Err(_) => names_match(&target.host),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winewarden_core::types::NetworkDirection;
+
+    fn target(host: &str, port: u16, protocol: &str) -> NetworkTarget {
+        NetworkTarget {
+            host: host.to_string(),
+            port,
+            protocol: protocol.to_string(),
+            direction: NetworkDirection::Connect,
+            names: Vec::new(),
+        }
+    }
+
+    fn enforcing(rules: &str) -> NetworkConfig {
+        let mut config: NetworkConfig = toml::from_str(rules).unwrap();
+        config.mode = NetworkMode::Enforce;
+        config
+    }
+
+    #[test]
+    fn test_first_matching_rule_decides() {
+        let config = enforcing(
+            r#"
+            [[rules]]
+            label = "LAN"
+            action = "deny"
+            cidrs = ["192.168.0.0/16"]
+
+            [[rules]]
+            label = "Steam"
+            action = "allow"
+            domains = ["steampowered.com"]
+            cidrs = ["162.254.192.0/21"]
+            ports = [443, "27015-27050"]
+            protocol = "tcp"
+
+            [[rules]]
+            action = "deny"
+            label = ""
+            tiers = ["yellow"]
+            "#,
+        );
+
+        let lan = evaluate_network(
+            &config,
+            &target("192.168.1.5", 443, "tcp"),
+            TrustTier::Green,
+        );
+        assert!(matches!(lan.action, DecisionAction::Deny));
+        assert_eq!(lan.rule.as_deref(), Some("LAN"));
+
+        let steam = evaluate_network(
+            &config,
+            &target("162.254.193.7", 27020, "tcp"),
+            TrustTier::Yellow,
+        );
+        assert!(matches!(steam.action, DecisionAction::Allow));
+        assert_eq!(steam.rule.as_deref(), Some("Steam"));
+
+        let by_name = evaluate_network(
+            &config,
+            &target("cdn.steampowered.com", 443, "tcp"),
+            TrustTier::Yellow,
+        );
+        assert_eq!(by_name.rule.as_deref(), Some("Steam"));
+
+        // Wrong protocol falls through to the unlabeled yellow-only rule.
+        let udp = evaluate_network(
+            &config,
+            &target("162.254.193.7", 27020, "udp"),
+            TrustTier::Yellow,
+        );
+        assert!(matches!(udp.action, DecisionAction::Deny));
+        assert_eq!(udp.rule.as_deref(), Some("network.rules[2]"));
+
+        // Nothing matches for green, so the green default applies.
+        let other = evaluate_network(&config, &target("203.0.113.9", 80, "tcp"), TrustTier::Green);
+        assert!(matches!(other.action, DecisionAction::Allow));
+        assert_eq!(other.rule, None);
+        let red = evaluate_network(&config, &target("203.0.113.9", 80, "tcp"), TrustTier::Red);
+        assert!(matches!(red.action, DecisionAction::Deny));
+        assert!(red.systemic_risk);
+    }
+
+    #[test]
+    fn test_resolved_names_match_domains() {
+        let config = enforcing(
+            r#"
+            [[rules]]
+            label = "Steam"
+            action = "allow"
+            domains = ["steampowered.com"]
+            "#,
+        );
+        let mut resolved = target("203.0.113.7", 443, "tcp");
+        let unnamed = evaluate_network(&config, &resolved, TrustTier::Yellow);
+        assert_eq!(unnamed.rule, None);
+
+        resolved.names = vec!["cdn.steampowered.com".to_string()];
+        let named = evaluate_network(&config, &resolved, TrustTier::Yellow);
+        assert_eq!(named.rule.as_deref(), Some("Steam"));
+        assert!(matches!(named.action, DecisionAction::Allow));
+    }
+
+    #[test]
+    fn test_unknown_protocol_only_meets_deny_rules() {
+        let config = enforcing(
+            r#"
+            defaults = { green = "allow", yellow = "allow", red = "deny" }
+
+            [[rules]]
+            label = "Game servers over TCP"
+            action = "allow"
+            cidrs = ["203.0.113.0/24"]
+            protocol = "tcp"
+
+            [[rules]]
+            label = "No UDP floods"
+            action = "deny"
+            ports = ["1-65535"]
+            protocol = "udp"
+            "#,
+        );
+        let unknown = target("203.0.113.9", 7777, "tcp/udp");
+        let decision = evaluate_network(&config, &unknown, TrustTier::Green);
+        assert!(matches!(decision.action, DecisionAction::Deny));
+        assert_eq!(decision.rule.as_deref(), Some("No UDP floods"));
+
+        let tcp = evaluate_network(
+            &config,
+            &target("203.0.113.9", 7777, "tcp"),
+            TrustTier::Green,
+        );
+        assert_eq!(tcp.rule.as_deref(), Some("Game servers over TCP"));
+    }
+
+    #[test]
+    fn test_direction_filter() {
+        let config = enforcing(
+            r#"
+            defaults = { green = "deny", yellow = "deny", red = "deny" }
+
+            [[rules]]
+            label = "Local servers"
+            action = "allow"
+            direction = "bind"
+            "#,
+        );
+        let mut bind = target("0.0.0.0", 7777, "udp");
+        bind.direction = NetworkDirection::Bind;
+        let allowed = evaluate_network(&config, &bind, TrustTier::Yellow);
+        assert!(matches!(allowed.action, DecisionAction::Allow));
+
+        let connect = evaluate_network(&config, &target("0.0.0.0", 7777, "udp"), TrustTier::Yellow);
+        assert!(matches!(connect.action, DecisionAction::Deny));
     }
 }
@@ -0,0 +1,624 @@
+//! The ordered rule list from `[[rules]]`.
+//!
+//! Rules are compiled once, when the engine is built or its rules are
+//! replaced, and checked top to bottom for every access attempt. The
+//! built-in checks (sacred zones, device classes, the network settings) are
+//! rules too, so
+//! the default config spells out the whole policy in order.
+
+use std::cell::OnceCell;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::{Context, Result};
+
+use winewarden_core::config::{ConfigPaths, NetworkConfig, PolicyRule, RuleOutcome};
+use winewarden_core::devices::DevicePolicy;
+use winewarden_core::network::domain_matches;
+use winewarden_core::paths::{expand_path_template, PathAction, PathPattern, ZoneSet};
+use winewarden_core::store::{sha256_file, HashCache};
+use winewarden_core::trust::TrustTier;
+use winewarden_core::types::{AccessAttempt, AccessKind, AccessTarget};
+
+use crate::decision::{DecisionAction, DecisionTrace, PolicyDecision, TraceStep};
+use crate::rules::{devices, filesystem, network};
+use crate::PolicyContext;
+
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+    /// Where `sha256` conditions look up and keep file hashes. Opened once,
+    /// and only when a rule has such a condition; without it files are
+    /// hashed directly.
+    hash_cache: Option<Arc<Mutex<HashCache>>>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    name: String,
+    tiers: Vec<TrustTier>,
+    kinds: Vec<AccessKind>,
+    paths: Vec<PathPattern>,
+    sha256: Vec<String>,
+    hosts: Vec<String>,
+    outcome: RuleOutcome,
+    redirect_to: Option<PathBuf>,
+}
+
+/// What a rule can see of an attempt. The file hash is only computed if a
+/// rule asks for it, and then at most once.
+struct Subject<'a> {
+    attempt: &'a AccessAttempt,
+    context: &'a PolicyContext,
+    hash_cache: Option<&'a Mutex<HashCache>>,
+    sha256: OnceCell<Option<String>>,
+}
+
+impl RuleSet {
+    pub fn compile(rules: &[PolicyRule], paths: &ConfigPaths) -> Result<Self> {
+        let mut compiled = Vec::with_capacity(rules.len());
+        for (index, rule) in rules.iter().enumerate() {
+            let name = if rule.label.is_empty() {
+                format!("rules[{index}]")
+            } else {
+                rule.label.clone()
+            };
+            let patterns = rule
+                .when
+                .path
+                .iter()
+                .map(|pattern| PathPattern::compile(pattern, paths))
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("compile rule '{name}'"))?;
+            let redirect_to = match &rule.redirect_to {
+                Some(template) => Some(expand_path_template(template, paths)?),
+                None => None,
+            };
+            compiled.push(CompiledRule {
+                name,
+                tiers: rule.when.tier.clone(),
+                kinds: rule.when.kind.clone(),
+                paths: patterns,
+                sha256: rule
+                    .when
+                    .sha256
+                    .iter()
+                    .map(|digest| digest.to_ascii_lowercase())
+                    .collect(),
+                hosts: rule.when.host.clone(),
+                outcome: rule.then,
+                redirect_to,
+            });
+        }
+        let hash_cache = if compiled.iter().any(|rule| !rule.sha256.is_empty()) {
+            HashCache::open(&paths.hash_cache_path)
+                .ok()
+                .map(|cache| Arc::new(Mutex::new(cache)))
+        } else {
+            None
+        };
+        Ok(Self {
+            rules: compiled,
+            hash_cache,
+        })
+    }
+
+    /// The first matching rule decides. A `sacred_zones` or `devices` rule
+    /// that does not cover the target passes the attempt on to the next rule.
+    pub fn evaluate(
+        &self,
+        attempt: &AccessAttempt,
+        context: &PolicyContext,
+        zones: &ZoneSet,
+        devices: &DevicePolicy,
+        network_config: &NetworkConfig,
+    ) -> PolicyDecision {
+        self.decide(attempt, context, zones, devices, network_config, None)
+    }
+
+    /// Like `evaluate`, also recording every rule considered on the way.
+    pub fn explain(
+        &self,
+        attempt: &AccessAttempt,
+        context: &PolicyContext,
+        zones: &ZoneSet,
+        devices: &DevicePolicy,
+        network_config: &NetworkConfig,
+    ) -> DecisionTrace {
+        let mut steps = Vec::new();
+        let decision = self.decide(
+            attempt,
+            context,
+            zones,
+            devices,
+            network_config,
+            Some(&mut steps),
+        );
+        DecisionTrace { steps, decision }
+    }
+
+    fn decide(
+        &self,
+        attempt: &AccessAttempt,
+        context: &PolicyContext,
+        zones: &ZoneSet,
+        devices: &DevicePolicy,
+        network_config: &NetworkConfig,
+        mut trace: Option<&mut Vec<TraceStep>>,
+    ) -> PolicyDecision {
+        let subject = Subject {
+            attempt,
+            context,
+            hash_cache: self.hash_cache.as_deref(),
+            sha256: OnceCell::new(),
+        };
+        for rule in &self.rules {
+            if let Err(mismatch) = rule.check(&subject) {
+                if let Some(steps) = trace.as_deref_mut() {
+                    steps.push(rule.step(false, mismatch.describe(rule, &subject)));
+                }
+                continue;
+            }
+            let delegated = match rule.outcome {
+                RuleOutcome::Allow => Some(rule.decide(PathAction::Allow)),
+                RuleOutcome::Deny => Some(rule.decide(PathAction::Deny)),
+                RuleOutcome::Redirect => Some(rule.decide(PathAction::Redirect)),
+                RuleOutcome::Virtualize => Some(rule.decide(PathAction::Virtualize)),
+                RuleOutcome::SacredZones => match &attempt.target {
+                    AccessTarget::Path(path) => {
+                        filesystem::evaluate_zones(path, attempt.kind, zones)
+                    }
+                    _ => None,
+                },
+                RuleOutcome::Network => match &attempt.target {
+                    AccessTarget::Network(target) => Some(network::evaluate_network(
+                        network_config,
+                        target,
+                        context.trust_tier,
+                    )),
+                    _ => None,
+                },
+                RuleOutcome::Devices => {
+                    devices::evaluate_device(&attempt.target, devices, context.trust_tier)
+                }
+            };
+            if let Some(steps) = trace.as_deref_mut() {
+                steps.push(rule.step(
+                    true,
+                    delegation_detail(rule, attempt, zones, delegated.as_ref()),
+                ));
+            }
+            if let Some(mut decision) = delegated {
+                decision.rule = decision.rule.or_else(|| Some(rule.name.clone()));
+                return decision;
+            }
+        }
+
+        // A rule list replaces the built-in one wholesale, in configs and
+        // overlays alike, so whatever it leaves out is denied
+        PolicyDecision {
+            action: DecisionAction::Deny,
+            reason: "Access denied: no policy rule matched".to_string(),
+            zone_label: None,
+            systemic_risk: true,
+            rule: None,
+        }
+    }
+}
+
+/// The first condition of a rule that an attempt failed.
+#[derive(Debug, Clone, Copy)]
+enum Mismatch {
+    Tier,
+    Kind,
+    NotAPath,
+    Path,
+    NotNetwork,
+    Host,
+    Sha256,
+}
+
+impl Mismatch {
+    fn describe(self, rule: &CompiledRule, subject: &Subject<'_>) -> String {
+        let attempt = subject.attempt;
+        match self {
+            Mismatch::Tier => format!(
+                "tier is {}, rule wants {}",
+                subject.context.trust_tier,
+                join(rule.tiers.iter().map(|tier| tier.to_string()))
+            ),
+            Mismatch::Kind => format!(
+                "kind is {}, rule wants {}",
+                attempt.kind.as_str(),
+                join(rule.kinds.iter().map(|kind| kind.as_str().to_string()))
+            ),
+            Mismatch::NotAPath => "target is not a path".to_string(),
+            Mismatch::Path => format!(
+                "path matches none of {}",
+                join(
+                    rule.paths
+                        .iter()
+                        .map(|pattern| pattern.template().to_string())
+                )
+            ),
+            Mismatch::NotNetwork => "target is not a network destination".to_string(),
+            Mismatch::Host => format!("host matches none of {}", join(rule.hosts.iter().cloned())),
+            Mismatch::Sha256 => match subject.sha256() {
+                Some(digest) => format!("sha256 {digest} is not listed"),
+                None if attempt.kind != AccessKind::Execute => {
+                    "only executed files are hashed".to_string()
+                }
+                None => "target could not be hashed".to_string(),
+            },
+        }
+    }
+}
+
+fn join(values: impl Iterator<Item = String>) -> String {
+    values.collect::<Vec<_>>().join(", ")
+}
+
+/// What a matched rule made of the attempt, for the trace.
+fn delegation_detail(
+    rule: &CompiledRule,
+    attempt: &AccessAttempt,
+    zones: &ZoneSet,
+    decision: Option<&PolicyDecision>,
+) -> String {
+    match (rule.outcome, &attempt.target, decision) {
+        (RuleOutcome::SacredZones, AccessTarget::Path(path), Some(decision)) => {
+            match zones.find(path) {
+                Some(zone) => format!("{} (zone {})", decision.reason, zone.pattern),
+                None => decision.reason.clone(),
+            }
+        }
+        (RuleOutcome::SacredZones, AccessTarget::Path(_), None) => {
+            "no sacred zone covers the path, passed on".to_string()
+        }
+        (RuleOutcome::SacredZones, _, _) => "target is not a path, passed on".to_string(),
+        (RuleOutcome::Network, _, None) => {
+            "target is not a network destination, passed on".to_string()
+        }
+        (RuleOutcome::Devices, _, None) => {
+            "no device class covers the target, passed on".to_string()
+        }
+        (_, _, Some(decision)) => decision.reason.clone(),
+        (_, _, None) => "passed on".to_string(),
+    }
+}
+
+impl CompiledRule {
+    fn check(&self, subject: &Subject<'_>) -> Result<(), Mismatch> {
+        let attempt = subject.attempt;
+        if !self.tiers.is_empty() && !self.tiers.contains(&subject.context.trust_tier) {
+            return Err(Mismatch::Tier);
+        }
+        if !self.kinds.is_empty() && !self.kinds.contains(&attempt.kind) {
+            return Err(Mismatch::Kind);
+        }
+        if !self.paths.is_empty() {
+            let AccessTarget::Path(path) = &attempt.target else {
+                return Err(Mismatch::NotAPath);
+            };
+            let prefix_root = &subject.context.prefix_root;
+            if !self
+                .paths
+                .iter()
+                .any(|pattern| pattern.matches(path, prefix_root))
+            {
+                return Err(Mismatch::Path);
+            }
+        }
+        if !self.hosts.is_empty() {
+            let AccessTarget::Network(target) = &attempt.target else {
+                return Err(Mismatch::NotNetwork);
+            };
+            if !self
+                .hosts
+                .iter()
+                .any(|host| domain_matches(&target.host, host))
+            {
+                return Err(Mismatch::Host);
+            }
+        }
+        if !self.sha256.is_empty() {
+            match subject.sha256() {
+                Some(digest) if self.sha256.contains(digest) => {}
+                _ => return Err(Mismatch::Sha256),
+            }
+        }
+        Ok(())
+    }
+
+    fn decide(&self, action: PathAction) -> PolicyDecision {
+        let mut decision = filesystem::decide(&self.name, action, self.redirect_to.as_ref());
+        decision.rule = Some(self.name.clone());
+        decision
+    }
+
+    fn step(&self, matched: bool, detail: String) -> TraceStep {
+        TraceStep {
+            rule: self.name.clone(),
+            matched,
+            detail,
+        }
+    }
+}
+
+impl Subject<'_> {
+    /// Only executed files are hashed, through the hash cache, so reads and
+    /// writes never pay for one. Other and unreadable targets have no hash,
+    /// so `sha256` conditions fail on them.
+    fn sha256(&self) -> Option<&String> {
+        self.sha256
+            .get_or_init(|| match &self.attempt.target {
+                AccessTarget::Path(path) if self.attempt.kind == AccessKind::Execute => {
+                    match self.hash_cache {
+                        Some(cache) => cache
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .sha256(path),
+                        None => sha256_file(path),
+                    }
+                    .ok()
+                }
+                _ => None,
+            })
+            .as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use winewarden_core::config::Config;
+    use winewarden_core::types::{NetworkDirection, NetworkTarget};
+
+    /// Config paths under a directory of the test's own, so hash caches
+    /// are never shared.
+    fn paths(dir: &tempfile::TempDir) -> ConfigPaths {
+        ConfigPaths::under(dir.path())
+    }
+
+    fn context(trust_tier: TrustTier) -> PolicyContext {
+        PolicyContext {
+            prefix_root: PathBuf::from("/tmp/prefix"),
+            trust_tier,
+        }
+    }
+
+    fn attempt(kind: AccessKind, target: AccessTarget) -> AccessAttempt {
+        AccessAttempt {
+            timestamp: time::OffsetDateTime::now_utc(),
+            kind,
+            target,
+            note: None,
+        }
+    }
+
+    fn path(kind: AccessKind, path: &str) -> AccessAttempt {
+        attempt(kind, AccessTarget::Path(PathBuf::from(path)))
+    }
+
+    /// `extra` rules go in front of the default rule set.
+    fn evaluate(extra: &str, attempt: &AccessAttempt, tier: TrustTier) -> PolicyDecision {
+        let config = Config::default_config();
+        let mut rules: Vec<PolicyRule> = if extra.is_empty() {
+            Vec::new()
+        } else {
+            toml::from_str::<Config>(extra).unwrap().rules
+        };
+        rules.extend(config.rules.iter().cloned());
+        let dir = tempfile::tempdir().unwrap();
+        let paths = paths(&dir);
+        let zones = ZoneSet::compile(&config.sacred_zones, &paths).unwrap();
+        let devices = DevicePolicy::compile(&config.devices).unwrap();
+        RuleSet::compile(&rules, &paths).unwrap().evaluate(
+            attempt,
+            &context(tier),
+            &zones,
+            &devices,
+            &config.network,
+        )
+    }
+
+    #[test]
+    fn test_default_rules_keep_builtin_behaviour() {
+        let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
+
+        let inside = evaluate(
+            "",
+            &path(AccessKind::Write, "/tmp/prefix/drive_c/save.dat"),
+            TrustTier::Yellow,
+        );
+        assert!(matches!(inside.action, DecisionAction::Allow));
+        assert_eq!(inside.rule.as_deref(), Some("Within prefix"));
+
+        let documents = evaluate(
+            "",
+            &path(AccessKind::Read, &format!("{home}/Documents/notes.txt")),
+            TrustTier::Green,
+        );
+        assert!(matches!(documents.action, DecisionAction::Redirect(_)));
+        assert_eq!(documents.zone_label.as_deref(), Some("Home outside prefix"));
+        assert_eq!(documents.rule.as_deref(), Some("Sacred zones"));
+
+        let ssh = evaluate(
+            "",
+            &path(AccessKind::Read, &format!("{home}/.ssh/id_ed25519")),
+            TrustTier::Green,
+        );
+        assert!(matches!(ssh.action, DecisionAction::Deny));
+        assert_eq!(ssh.zone_label.as_deref(), Some("SSH keys"));
+
+        let outside = evaluate("", &path(AccessKind::Read, "/etc/shadow"), TrustTier::Green);
+        assert!(matches!(outside.action, DecisionAction::Deny));
+        assert!(outside.systemic_risk);
+        assert_eq!(outside.reason, "Access denied: Prefix boundary");
+
+        let camera = attempt(
+            AccessKind::Device,
+            AccessTarget::Device("/dev/video0".to_string()),
+        );
+        let trusted = evaluate("", &camera, TrustTier::Green);
+        assert!(matches!(trusted.action, DecisionAction::Allow));
+        assert_eq!(trusted.rule.as_deref(), Some("Devices"));
+        let untrusted = evaluate("", &camera, TrustTier::Yellow);
+        assert!(matches!(untrusted.action, DecisionAction::Deny));
+        assert!(untrusted.systemic_risk);
+        assert_eq!(
+            untrusted.reason,
+            "Device denied: camera is not allowed at tier yellow"
+        );
+
+        // Opening a GPU node is a path attempt; the device class decides it too
+        let gpu = evaluate(
+            "",
+            &path(AccessKind::Read, "/dev/dri/renderD128"),
+            TrustTier::Red,
+        );
+        assert!(matches!(gpu.action, DecisionAction::Allow));
+        assert_eq!(gpu.rule.as_deref(), Some("Devices"));
+
+        let device = evaluate(
+            "",
+            &attempt(
+                AccessKind::Device,
+                AccessTarget::Device("/dev/sda".to_string()),
+            ),
+            TrustTier::Green,
+        );
+        assert!(matches!(device.action, DecisionAction::Deny));
+        assert_eq!(device.zone_label.as_deref(), Some("Sacred devices"));
+
+        let network = evaluate(
+            "",
+            &attempt(
+                AccessKind::Network,
+                AccessTarget::Network(NetworkTarget {
+                    host: "example.com".to_string(),
+                    port: 443,
+                    protocol: "tcp".to_string(),
+                    direction: NetworkDirection::Connect,
+                    names: Vec::new(),
+                }),
+            ),
+            TrustTier::Yellow,
+        );
+        assert!(matches!(network.action, DecisionAction::Allow));
+        assert_eq!(network.rule.as_deref(), Some("Network"));
+    }
+
+    #[test]
+    fn test_custom_rule_list_fails_closed() {
+        let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
+        let config = Config::from_toml_str(
+            r#"
+            [[rules]]
+            label = "Saves"
+            when = { kind = ["write"], path = ["${PREFIX}/**/*.sav"] }
+            then = "allow"
+            "#,
+        )
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let paths = paths(&dir);
+        let zones = ZoneSet::compile(&config.sacred_zones, &paths).unwrap();
+        let devices = DevicePolicy::compile(&config.devices).unwrap();
+        let rules = RuleSet::compile(&config.rules, &paths).unwrap();
+        let evaluate = |attempt: &AccessAttempt| {
+            rules.evaluate(
+                attempt,
+                &context(TrustTier::Green),
+                &zones,
+                &devices,
+                &config.network,
+            )
+        };
+
+        let save = evaluate(&path(AccessKind::Write, "/tmp/prefix/drive_c/save1.sav"));
+        assert!(matches!(save.action, DecisionAction::Allow));
+
+        let ssh = evaluate(&path(AccessKind::Read, &format!("{home}/.ssh/id_ed25519")));
+        assert!(matches!(ssh.action, DecisionAction::Deny));
+        assert!(ssh.systemic_risk);
+        assert_eq!(ssh.rule, None);
+        assert_eq!(ssh.reason, "Access denied: no policy rule matched");
+    }
+
+    #[test]
+    fn test_conditions_on_tier_kind_and_path() {
+        let rules = r#"
+            [[rules]]
+            label = "Untrusted cannot write saves"
+            when = { tier = ["red"], kind = ["write"], path = ["${PREFIX}/**/*.sav"] }
+            then = "deny"
+        "#;
+        let save = path(AccessKind::Write, "/tmp/prefix/drive_c/users/save1.sav");
+
+        let red = evaluate(rules, &save, TrustTier::Red);
+        assert!(matches!(red.action, DecisionAction::Deny));
+        assert_eq!(red.rule.as_deref(), Some("Untrusted cannot write saves"));
+
+        let yellow = evaluate(rules, &save, TrustTier::Yellow);
+        assert_eq!(yellow.rule.as_deref(), Some("Within prefix"));
+
+        let read = evaluate(
+            rules,
+            &path(AccessKind::Read, "/tmp/prefix/drive_c/users/save1.sav"),
+            TrustTier::Red,
+        );
+        assert!(matches!(read.action, DecisionAction::Allow));
+    }
+
+    #[test]
+    fn test_sha256_condition() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = dir.path().join("tool.exe");
+        std::fs::File::create(&tool)
+            .unwrap()
+            .write_all(b"MZ trusted")
+            .unwrap();
+        let digest = winewarden_core::store::sha256_file(&tool).unwrap();
+
+        let mut config: Config = toml::from_str(&format!(
+            "[[rules]]\nlabel = \"Known tool\"\nwhen = {{ sha256 = [\"{}\"] }}\nthen = \"allow\"\n",
+            digest.to_uppercase()
+        ))
+        .unwrap();
+        config.rules.extend(Config::default_config().rules);
+        let paths = paths(&dir);
+        let zones = ZoneSet::compile(&config.sacred_zones, &paths).unwrap();
+        let devices = DevicePolicy::compile(&config.devices).unwrap();
+        // The cache is opened once, with the rules
+        let rules = RuleSet::compile(&config.rules, &paths).unwrap();
+        assert!(paths.hash_cache_path.exists());
+        let evaluate = |attempt: &AccessAttempt| {
+            rules.evaluate(
+                attempt,
+                &context(TrustTier::Yellow),
+                &zones,
+                &devices,
+                &config.network,
+            )
+        };
+
+        let run = path(AccessKind::Execute, &tool.to_string_lossy());
+        let decision = evaluate(&run);
+        assert!(matches!(decision.action, DecisionAction::Allow));
+        assert_eq!(decision.rule.as_deref(), Some("Known tool"));
+
+        // Reading the same file does not hash it
+        let read = path(AccessKind::Read, &tool.to_string_lossy());
+        let decision = evaluate(&read);
+        assert_eq!(decision.rule.as_deref(), Some("Prefix boundary"));
+
+        std::fs::File::create(&tool)
+            .unwrap()
+            .write_all(b"MZ changed build")
+            .unwrap();
+        let decision = evaluate(&run);
+        assert_eq!(decision.rule.as_deref(), Some("Prefix boundary"));
+    }
+}
@@ -9,7 +9,7 @@ use std::collections::HashMap;
 
 use winewarden_core::config::ProcessConfig;
 
-use crate::decision::{DecisionAction, PolicyDecision};
+use crate::decision::{DecisionAction, DecisionTrace, PolicyDecision, TraceStep};
 
 /// Tracks process execution statistics for a session
 #[derive(Debug, Clone, Default)]
@@ -52,8 +52,43 @@ pub fn evaluate_process_spawn(
     config: &ProcessConfig,
     tracker: &mut ProcessTracker,
 ) -> PolicyDecision {
+    decide(process, config, tracker, None)
+}
+
+/// Like `evaluate_process_spawn`, also recording each check on the way
+pub fn explain_process_spawn(
+    process: &str,
+    config: &ProcessConfig,
+    tracker: &mut ProcessTracker,
+) -> DecisionTrace {
+    let mut steps = Vec::new();
+    let decision = decide(process, config, tracker, Some(&mut steps));
+    DecisionTrace { steps, decision }
+}
+
+fn decide(
+    process: &str,
+    config: &ProcessConfig,
+    tracker: &mut ProcessTracker,
+    mut trace: Option<&mut Vec<TraceStep>>,
+) -> PolicyDecision {
+    let mut note = |rule: &str, matched: bool, detail: String| {
+        if let Some(steps) = trace.as_deref_mut() {
+            steps.push(TraceStep {
+                rule: rule.to_string(),
+                matched,
+                detail,
+            });
+        }
+    };
+
     // Check 1: Child process limit
+    let limit = format!(
+        "{} of {} children spawned",
+        tracker.child_count, config.max_child_processes
+    );
     if tracker.would_exceed_limit(config.max_child_processes) {
+        note("Child process limit", true, limit);
         return PolicyDecision {
             action: DecisionAction::Deny,
             reason: format!(
@@ -62,62 +97,117 @@ pub fn evaluate_process_spawn(
             ),
             zone_label: Some("Process Limits".to_string()),
             systemic_risk: true,
+            rule: None,
         };
     }
+    note("Child process limit", false, limit);
 
     // Check 2: Blocked patterns
     for pattern in &config.blocked_patterns {
         if matches_pattern(process, pattern) {
+            note("Blocked patterns", true, format!("matches '{}'", pattern));
             tracker.record_attempt(process, false);
             return PolicyDecision {
                 action: DecisionAction::Deny,
                 reason: format!("Process blocked by pattern '{}'", pattern),
                 zone_label: Some("Process Security".to_string()),
                 systemic_risk: true,
+                rule: None,
             };
         }
     }
+    note(
+        "Blocked patterns",
+        false,
+        format!(
+            "matches none of {} pattern(s)",
+            config.blocked_patterns.len()
+        ),
+    );
 
     // Check 3: Shell execution
-    if is_shell(process) && !config.allow_shell_execution {
+    let shell = is_shell(process);
+    if shell && !config.allow_shell_execution {
+        note(
+            "Shell execution",
+            true,
+            "a shell, and shells are not allowed".to_string(),
+        );
         tracker.record_attempt(process, false);
         return PolicyDecision {
             action: DecisionAction::Deny,
             reason: "Shell execution not allowed".to_string(),
             zone_label: Some("Process Security".to_string()),
             systemic_risk: true,
+            rule: None,
         };
     }
+    note(
+        "Shell execution",
+        false,
+        if shell {
+            "a shell, and shells are allowed"
+        } else {
+            "not a shell"
+        }
+        .to_string(),
+    );
 
     // Check 4: Script execution
-    if is_script(process) && !config.allow_script_execution {
+    let script = is_script(process);
+    if script && !config.allow_script_execution {
+        note(
+            "Script execution",
+            true,
+            "a script, and scripts are not allowed".to_string(),
+        );
         tracker.record_attempt(process, false);
         return PolicyDecision {
             action: DecisionAction::Deny,
             reason: "Script execution not allowed".to_string(),
             zone_label: Some("Process Security".to_string()),
             systemic_risk: true,
+            rule: None,
         };
     }
+    note(
+        "Script execution",
+        false,
+        if script {
+            "a script, and scripts are allowed"
+        } else {
+            "not a script"
+        }
+        .to_string(),
+    );
 
     // Check 5: Allowed patterns (if any are defined)
     if !config.allowed_patterns.is_empty() {
-        let mut matched = false;
-        for pattern in &config.allowed_patterns {
-            if matches_pattern(process, pattern) {
-                matched = true;
-                break;
+        let matched = config
+            .allowed_patterns
+            .iter()
+            .find(|pattern| matches_pattern(process, pattern));
+        match matched {
+            Some(pattern) => note("Allowed patterns", true, format!("matches '{}'", pattern)),
+            None => {
+                note(
+                    "Allowed patterns",
+                    false,
+                    format!(
+                        "matches none of {} pattern(s)",
+                        config.allowed_patterns.len()
+                    ),
+                );
+                tracker.record_attempt(process, false);
+                return PolicyDecision {
+                    action: DecisionAction::Deny,
+                    reason: format!("Process '{}' not in allowed patterns", process),
+                    zone_label: Some("Process Security".to_string()),
+                    systemic_risk: true,
+                    rule: None,
+                };
             }
         }
-        if !matched {
-            tracker.record_attempt(process, false);
-            return PolicyDecision {
-                action: DecisionAction::Deny,
-                reason: format!("Process '{}' not in allowed patterns", process),
-                zone_label: Some("Process Security".to_string()),
-                systemic_risk: true,
-            };
-        }
     }
 
     // All checks passed
@@ -127,6 +217,7 @@ pub fn evaluate_process_spawn(
         reason: format!("Process allowed: {}", process),
         zone_label: Some("Process".to_string()),
         systemic_risk: false,
+        rule: None,
     }
 }
 
@@ -0,0 +1,205 @@
+//! What-if comparison of two policies over the same recorded attempts.
+//!
+//! Each attempt goes through `PolicyEngine::evaluate` on both engines in
+//! recording order, as the monitor evaluates it, so checks that depend on
+//! earlier attempts (such as mass-modification detection) see the same
+//! history they would in a real run.
+
+use serde::{Deserialize, Serialize};
+
+use winewarden_core::types::AccessAttempt;
+
+use crate::decision::{DecisionAction, PolicyDecision};
+use crate::{PolicyContext, PolicyEngine};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    NewlyDenied,
+    NewlyAllowed,
+    /// Allowed under both, but redirected or virtualized elsewhere.
+    RedirectChanged,
+}
+
+impl ChangeKind {
+    /// Changes are reported grouped in this order.
+    pub const ALL: [ChangeKind; 3] = [
+        ChangeKind::NewlyDenied,
+        ChangeKind::NewlyAllowed,
+        ChangeKind::RedirectChanged,
+    ];
+
+    pub fn classify(before: &DecisionAction, after: &DecisionAction) -> Option<Self> {
+        match (before, after) {
+            (DecisionAction::Deny, DecisionAction::Deny) => None,
+            (_, DecisionAction::Deny) => Some(ChangeKind::NewlyDenied),
+            (DecisionAction::Deny, _) => Some(ChangeKind::NewlyAllowed),
+            (DecisionAction::Allow, DecisionAction::Allow) => None,
+            (DecisionAction::Redirect(old), DecisionAction::Redirect(new))
+            | (DecisionAction::Virtualize(old), DecisionAction::Virtualize(new))
+                if old == new =>
+            {
+                None
+            }
+            _ => Some(ChangeKind::RedirectChanged),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionChange {
+    pub kind: ChangeKind,
+    pub attempt: AccessAttempt,
+    pub before: PolicyDecision,
+    pub after: PolicyDecision,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Simulation {
+    pub evaluated: usize,
+    /// Attempts whose decision differs, in recording order.
+    pub changes: Vec<DecisionChange>,
+}
+
+impl Simulation {
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes
+            .iter()
+            .filter(|change| change.kind == kind)
+            .count()
+    }
+}
+
+/// Evaluates `attempts` under `baseline` and `candidate` and keeps the
+/// attempts whose decision changed. Rule names and reasons alone do not
+/// count as a change.
+pub fn simulate(
+    attempts: impl IntoIterator<Item = AccessAttempt>,
+    context: &PolicyContext,
+    baseline: &PolicyEngine,
+    candidate: &PolicyEngine,
+) -> Simulation {
+    let mut simulation = Simulation::default();
+    for attempt in attempts {
+        simulation.evaluated += 1;
+        let before = baseline.evaluate(&attempt, context);
+        let after = candidate.evaluate(&attempt, context);
+        if let Some(kind) = ChangeKind::classify(&before.action, &after.action) {
+            simulation.changes.push(DecisionChange {
+                kind,
+                attempt,
+                before,
+                after,
+            });
+        }
+    }
+    simulation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+    use winewarden_core::config::{Config, ConfigPaths};
+    use winewarden_core::trust::TrustTier;
+    use winewarden_core::types::{AccessKind, AccessTarget};
+
+    fn engine(extra: &str) -> PolicyEngine {
+        let mut config = Config::default_config();
+        if !extra.is_empty() {
+            let mut rules = toml::from_str::<Config>(extra).unwrap().rules;
+            rules.append(&mut config.rules);
+            config.rules = rules;
+        }
+        let paths = ConfigPaths::under(Path::new("/tmp"));
+        PolicyEngine::from_config(config, &paths).unwrap()
+    }
+
+    fn write(path: &str) -> AccessAttempt {
+        AccessAttempt {
+            timestamp: time::OffsetDateTime::now_utc(),
+            kind: AccessKind::Write,
+            target: AccessTarget::Path(PathBuf::from(path)),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_simulate_reports_changed_decisions() {
+        let candidate = engine(
+            r#"
+            [[rules]]
+            label = "No save edits"
+            when = { path = ["${PREFIX}/**/*.sav"] }
+            then = "deny"
+
+            [[rules]]
+            label = "Scratch space"
+            when = { path = ["/opt/scratch/**"] }
+            then = "redirect"
+            redirect_to = "/tmp/scratch"
+
+            [[rules]]
+            label = "Shared tools"
+            when = { path = ["/opt/tools/**"] }
+            then = "allow"
+            "#,
+        );
+        let context = PolicyContext {
+            prefix_root: PathBuf::from("/tmp/prefix"),
+            trust_tier: TrustTier::Yellow,
+        };
+        let attempts = vec![
+            write("/tmp/prefix/drive_c/save1.sav"),
+            write("/tmp/prefix/drive_c/options.ini"),
+            write("/opt/scratch/cache.bin"),
+            write("/opt/tools/log.txt"),
+        ];
+
+        let simulation = simulate(attempts, &context, &engine(""), &candidate);
+        assert_eq!(simulation.evaluated, 4);
+        let kinds: Vec<ChangeKind> = simulation
+            .changes
+            .iter()
+            .map(|change| change.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ChangeKind::NewlyDenied,
+                ChangeKind::NewlyAllowed,
+                ChangeKind::NewlyAllowed
+            ]
+        );
+        assert_eq!(
+            simulation.changes[0].after.rule.as_deref(),
+            Some("No save edits")
+        );
+        assert!(matches!(
+            &simulation.changes[1].after.action,
+            DecisionAction::Redirect(path) if path == &PathBuf::from("/tmp/scratch")
+        ));
+    }
+
+    #[test]
+    fn test_classify_redirect_targets() {
+        let a = DecisionAction::Redirect(PathBuf::from("/a"));
+        let b = DecisionAction::Redirect(PathBuf::from("/b"));
+        assert_eq!(ChangeKind::classify(&a, &a.clone()), None);
+        assert_eq!(
+            ChangeKind::classify(&a, &b),
+            Some(ChangeKind::RedirectChanged)
+        );
+        assert_eq!(
+            ChangeKind::classify(
+                &DecisionAction::Allow,
+                &DecisionAction::Virtualize(PathBuf::from("/a"))
+            ),
+            Some(ChangeKind::RedirectChanged)
+        );
+        assert_eq!(
+            ChangeKind::classify(&a, &DecisionAction::Deny),
+            Some(ChangeKind::NewlyDenied)
+        );
+    }
+}
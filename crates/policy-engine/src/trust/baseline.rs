@@ -0,0 +1,154 @@
+//! Compares a run with the executable's behavior baseline.
+//!
+//! Baselines are learned from clean runs and stored with the trust record.
+//! Nothing is flagged until the baseline has seen `min_runs` clean runs.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use winewarden_core::config::BaselineConfig;
+use winewarden_core::store::{BehaviorBaseline, RunBehavior};
+
+/// Names listed in an anomaly detail, at most.
+const EXAMPLES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    NewDestinations,
+    NewChildProcesses,
+    WriteVolume,
+    DnsVolume,
+}
+
+/// One way a run departed from its baseline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    pub detail: String,
+}
+
+/// Lists how `run` departs from `baseline`, in `AnomalyKind` order
+pub fn detect_anomalies(
+    run: &RunBehavior,
+    baseline: &BehaviorBaseline,
+    config: &BaselineConfig,
+) -> Vec<Anomaly> {
+    let mut anomalies = Vec::new();
+    if !config.enabled || baseline.runs < config.min_runs.max(1) {
+        return anomalies;
+    }
+
+    let mut unseen =
+        |kind, what: &str, seen: &BTreeSet<String>, known: &BTreeSet<String>, limit| {
+            let new: Vec<&str> = seen.difference(known).map(String::as_str).collect();
+            if limit > 0 && new.len() >= limit as usize {
+                anomalies.push(Anomaly {
+                    kind,
+                    detail: format!(
+                        "{} {what} never seen in {} clean runs, e.g. {}",
+                        new.len(),
+                        baseline.runs,
+                        new[..new.len().min(EXAMPLES)].join(", ")
+                    ),
+                });
+            }
+        };
+    unseen(
+        AnomalyKind::NewDestinations,
+        "hosts",
+        &run.destinations,
+        &baseline.destinations,
+        config.new_destinations,
+    );
+    unseen(
+        AnomalyKind::NewChildProcesses,
+        "child processes",
+        &run.child_processes,
+        &baseline.child_processes,
+        config.new_child_processes,
+    );
+
+    let mut volume = |kind, what: &str, count: u32, usual: f64, floor: u32| {
+        if count >= floor && f64::from(count) > usual * config.volume_factor {
+            anomalies.push(Anomaly {
+                kind,
+                detail: format!("{count} {what} against a usual {usual:.0}"),
+            });
+        }
+    };
+    volume(
+        AnomalyKind::WriteVolume,
+        "file writes",
+        run.file_writes,
+        baseline.file_writes,
+        config.min_file_writes,
+    );
+    volume(
+        AnomalyKind::DnsVolume,
+        "DNS queries",
+        run.dns_queries,
+        baseline.dns_queries,
+        config.min_dns_queries,
+    );
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn baseline() -> BehaviorBaseline {
+        BehaviorBaseline {
+            runs: 5,
+            destinations: ["steampowered.com", "steamcontent.com"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            file_writes: 40.0,
+            dns_queries: 4.0,
+            ..BehaviorBaseline::default()
+        }
+    }
+
+    #[test]
+    fn test_flags_departures_from_baseline() {
+        let run = RunBehavior {
+            destinations: (0..40).map(|n| format!("host{n:02}.example")).collect(),
+            file_writes: 400,
+            dns_queries: 6,
+            ..RunBehavior::default()
+        };
+        let anomalies = detect_anomalies(&run, &baseline(), &BaselineConfig::default());
+        let kinds: Vec<AnomalyKind> = anomalies.iter().map(|anomaly| anomaly.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![AnomalyKind::NewDestinations, AnomalyKind::WriteVolume]
+        );
+        assert_eq!(
+            anomalies[0].detail,
+            "40 hosts never seen in 5 clean runs, e.g. host00.example, host01.example, host02.example"
+        );
+        assert_eq!(anomalies[1].detail, "400 file writes against a usual 40");
+    }
+
+    #[test]
+    fn test_quiet_until_baseline_is_learned() {
+        let run = RunBehavior {
+            destinations: (0..40).map(|n| format!("host{n}.example")).collect(),
+            ..RunBehavior::default()
+        };
+        let learning = BehaviorBaseline {
+            runs: 2,
+            ..baseline()
+        };
+        let config = BaselineConfig::default();
+        assert!(detect_anomalies(&run, &learning, &config).is_empty());
+
+        let disabled = BaselineConfig {
+            enabled: false,
+            ..config
+        };
+        assert!(detect_anomalies(&run, &baseline(), &disabled).is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+//! Trust lifecycle: turns an end-of-run trust score into tier changes.
+//!
+//! Executables climb one tier after enough consecutive clean runs and drop
+//! straight to the recommended tier after a run that scores below their
+//! current one.
+
+use serde::{Deserialize, Serialize};
+use winewarden_core::config::TrustConfig;
+use winewarden_core::store::{TierChange, TrustChangeSource};
+use winewarden_core::trust::TrustTier;
+
+use super::baseline::Anomaly;
+use super::scoring::TrustScore;
+
+/// Promotion and demotion rules, taken from the `[trust]` config section.
+#[derive(Debug, Clone)]
+pub struct TrustLifecycle {
+    /// Whether clean runs may raise the tier at all
+    pub auto_promote: bool,
+    /// Consecutive clean runs needed for one promotion step
+    pub promotion_after_runs: u32,
+    /// Lowest score that still counts as a clean run
+    pub promotion_min_score: u32,
+}
+
+/// Direction of a lifecycle decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransitionKind {
+    Unchanged,
+    Promoted,
+    Demoted,
+}
+
+/// Outcome of one run, stored back into the trust store and the session report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustTransition {
+    pub kind: TransitionKind,
+    /// Tier the run was monitored at
+    pub previous_tier: TrustTier,
+    /// Tier the executable keeps for its next run
+    pub new_tier: TrustTier,
+    /// End-of-run trust score (0-100)
+    pub score: u32,
+    /// No systemic-risk decisions and a score at or above the threshold
+    pub clean: bool,
+    /// Clean-run streak after this run
+    pub clean_runs: u32,
+    pub reason: String,
+    /// Departures from the executable's behavior baseline
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl TrustLifecycle {
+    pub fn from_config(config: &TrustConfig) -> Self {
+        Self {
+            auto_promote: config.auto_promote,
+            promotion_after_runs: config.promotion_after_runs,
+            promotion_min_score: config.promotion_min_score,
+        }
+    }
+
+    /// Decides the tier after a run at `tier`. `prior_clean_runs` is the
+    /// streak stored before the run.
+    pub fn assess(
+        &self,
+        tier: TrustTier,
+        prior_clean_runs: u32,
+        score: &TrustScore,
+        systemic_risks: u32,
+    ) -> TrustTransition {
+        let clean = systemic_risks == 0 && score.score >= self.promotion_min_score;
+        let transition = |kind, new_tier, clean_runs, reason: String| TrustTransition {
+            kind,
+            previous_tier: tier,
+            new_tier,
+            score: score.score,
+            clean,
+            clean_runs,
+            reason,
+            anomalies: score.anomalies.clone(),
+        };
+
+        if score.recommended_tier.is_below(tier) {
+            return transition(
+                TransitionKind::Demoted,
+                score.recommended_tier,
+                0,
+                format!(
+                    "score {} recommends {} ({})",
+                    score.score, score.recommended_tier, score.assessment
+                ),
+            );
+        }
+        if !clean {
+            let reason = if systemic_risks > 0 {
+                format!("{systemic_risks} systemic-risk decisions reset the clean-run streak")
+            } else {
+                format!(
+                    "score {} is below {} and reset the clean-run streak",
+                    score.score, self.promotion_min_score
+                )
+            };
+            return transition(TransitionKind::Unchanged, tier, 0, reason);
+        }
+
+        let clean_runs = prior_clean_runs.saturating_add(1);
+        let needed = self.promotion_after_runs.max(1);
+        if self.auto_promote && tier != TrustTier::Green && clean_runs >= needed {
+            return transition(
+                TransitionKind::Promoted,
+                tier.promoted(),
+                0,
+                format!(
+                    "{clean_runs} clean runs with score at least {}",
+                    self.promotion_min_score
+                ),
+            );
+        }
+        transition(
+            TransitionKind::Unchanged,
+            tier,
+            clean_runs,
+            format!("clean run {clean_runs} of {needed}"),
+        )
+    }
+}
+
+impl TrustTransition {
+    pub fn changed(&self) -> bool {
+        self.kind != TransitionKind::Unchanged
+    }
+
+    /// History entry for the trust store; `None` when the tier is unchanged.
+    pub fn tier_change(&self) -> Option<TierChange> {
+        let source = match self.kind {
+            TransitionKind::Unchanged => return None,
+            TransitionKind::Promoted => TrustChangeSource::AutoPromotion,
+            TransitionKind::Demoted => TrustChangeSource::ScoreDemotion,
+        };
+        Some(TierChange::new(source, self.reason.clone()))
+    }
+
+    /// One line for the human session summary.
+    pub fn summary(&self) -> String {
+        match self.kind {
+            TransitionKind::Promoted => format!(
+                "Trust raised to {} after {}.",
+                self.new_tier.calm_label(),
+                self.reason
+            ),
+            TransitionKind::Demoted => format!(
+                "Trust lowered to {}: {}.",
+                self.new_tier.calm_label(),
+                self.reason
+            ),
+            TransitionKind::Unchanged => format!("Trust stays {}.", self.new_tier.calm_label()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lifecycle() -> TrustLifecycle {
+        TrustLifecycle::from_config(&TrustConfig::default())
+    }
+
+    #[test]
+    fn test_promotes_after_clean_streak() {
+        let lifecycle = lifecycle();
+        let score = TrustScore::new(80, vec![]);
+
+        let first = lifecycle.assess(TrustTier::Red, 0, &score, 0);
+        assert_eq!(first.kind, TransitionKind::Unchanged);
+        assert_eq!(first.clean_runs, 1);
+
+        let third = lifecycle.assess(TrustTier::Red, 2, &score, 0);
+        assert_eq!(third.kind, TransitionKind::Promoted);
+        assert_eq!(third.new_tier, TrustTier::Yellow);
+        assert_eq!(third.clean_runs, 0);
+        assert!(third.tier_change().is_some());
+    }
+
+    #[test]
+    fn test_systemic_risk_resets_streak() {
+        let transition = lifecycle().assess(TrustTier::Yellow, 2, &TrustScore::new(80, vec![]), 1);
+        assert_eq!(transition.kind, TransitionKind::Unchanged);
+        assert!(!transition.clean);
+        assert_eq!(transition.clean_runs, 0);
+    }
+
+    #[test]
+    fn test_no_promotion_when_disabled() {
+        let mut lifecycle = lifecycle();
+        lifecycle.auto_promote = false;
+        let transition = lifecycle.assess(TrustTier::Yellow, 10, &TrustScore::new(80, vec![]), 0);
+        assert_eq!(transition.kind, TransitionKind::Unchanged);
+        assert_eq!(transition.clean_runs, 11);
+    }
+
+    #[test]
+    fn test_demotes_to_recommended_tier() {
+        let transition = lifecycle().assess(TrustTier::Green, 2, &TrustScore::new(20, vec![]), 0);
+        assert_eq!(transition.kind, TransitionKind::Demoted);
+        assert_eq!(transition.new_tier, TrustTier::Red);
+        assert_eq!(transition.clean_runs, 0);
+        assert!(transition.tier_change().is_some());
+    }
+}
@@ -1,2 +1,4 @@
+pub mod baseline;
+pub mod lifecycle;
 pub mod scoring;
 pub mod tiers;
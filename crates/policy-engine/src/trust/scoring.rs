@@ -3,43 +3,15 @@
 //! Calculates trust scores based on observed behavior patterns during execution.
 //! Scores can trigger trust tier adjustments and policy enforcement changes.
 
+use std::collections::BTreeSet;
+use std::net::IpAddr;
+
+use winewarden_core::store::RunBehavior;
 use winewarden_core::trust::TrustTier;
 
-/// Trust scoring configuration
-#[derive(Debug, Clone)]
-pub struct TrustScoringConfig {
-    /// Threshold for considering behavior suspicious (0-100)
-    pub suspicion_threshold: u32,
-    /// Weight for network activity in scoring
-    pub network_activity_weight: f32,
-    /// Weight for file system activity in scoring
-    pub filesystem_activity_weight: f32,
-    /// Weight for process activity in scoring
-    pub process_activity_weight: f32,
-    /// Penalty for accessing sensitive paths
-    pub sensitive_path_penalty: i32,
-    /// Penalty for outbound network connections
-    pub outbound_connection_penalty: i32,
-    /// Penalty for spawning many child processes
-    pub child_process_penalty: i32,
-    /// Bonus for consistent behavior
-    pub consistency_bonus: i32,
-}
+pub use winewarden_core::config::TrustScoringConfig;
 
-impl Default for TrustScoringConfig {
-    fn default() -> Self {
-        Self {
-            suspicion_threshold: 50,
-            network_activity_weight: 0.4,
-            filesystem_activity_weight: 0.3,
-            process_activity_weight: 0.3,
-            sensitive_path_penalty: -10,
-            outbound_connection_penalty: -5,
-            child_process_penalty: -3,
-            consistency_bonus: 5,
-        }
-    }
-}
+use super::baseline::Anomaly;
 
 /// Observed behavior patterns for scoring
 #[derive(Debug, Clone, Default)]
@@ -48,10 +20,14 @@ pub struct BehaviorProfile {
     pub sensitive_path_attempts: u32,
     /// Number of unique outbound network destinations
     pub unique_destinations: u32,
+    /// Hosts connected to, addresses by network
+    pub destinations: BTreeSet<String>,
     /// Number of DNS queries made
     pub dns_query_count: u32,
     /// Number of child processes spawned
     pub child_process_count: u32,
+    /// Names of the child processes spawned
+    pub child_processes: BTreeSet<String>,
     /// Number of file modifications
     pub file_modifications: u32,
     /// Number of denied access attempts
@@ -74,16 +50,25 @@ impl BehaviorProfile {
 
     /// Records an outbound connection
     pub fn record_outbound_connection(&mut self, host: &str) {
-        self.unique_destinations += 1;
+        if !self.destinations.insert(destination_key(host)) {
+            return;
+        }
+        self.unique_destinations = self.destinations.len() as u32;
         if self.unique_destinations > 10 {
             self.suspicious_patterns
                 .push(format!("Many connections: {}", host));
         }
     }
 
+    /// Records a DNS query
+    pub fn record_dns_query(&mut self) {
+        self.dns_query_count += 1;
+    }
+
     /// Records a child process spawn
     pub fn record_child_process(&mut self, process: &str) {
         self.child_process_count += 1;
+        self.child_processes.insert(process.to_string());
         if self.child_process_count > 20 {
             self.suspicious_patterns
                 .push(format!("Process spawning: {}", process));
@@ -102,6 +87,16 @@ impl BehaviorProfile {
         self.file_modifications += 1;
     }
 
+    /// What this run did, for comparison with the executable's baseline
+    pub fn run_behavior(&self) -> RunBehavior {
+        RunBehavior {
+            destinations: self.destinations.clone(),
+            child_processes: self.child_processes.clone(),
+            file_writes: self.file_modifications,
+            dns_queries: self.dns_query_count,
+        }
+    }
+
     /// Returns true if profile shows suspicious activity
     pub fn is_suspicious(&self) -> bool {
         self.sensitive_path_attempts > 0
@@ -123,6 +118,8 @@ pub struct TrustScore {
     pub notes: Vec<String>,
     /// Whether score indicates suspicious behavior
     pub is_suspicious: bool,
+    /// Departures from the executable's behavior baseline
+    pub anomalies: Vec<Anomaly>,
 }
 
 impl TrustScore {
@@ -139,6 +136,7 @@ impl TrustScore {
             assessment,
             notes,
             is_suspicious,
+            anomalies: Vec::new(),
         }
     }
 
@@ -165,10 +163,12 @@ impl TrustScore {
     }
 }
 
-/// Calculates trust score based on behavior profile and current tier
+/// Calculates trust score based on behavior profile, departures from the
+/// executable's baseline and current tier
 pub fn calculate_trust_score(
     current_tier: TrustTier,
     profile: &BehaviorProfile,
+    anomalies: Vec<Anomaly>,
     config: &TrustScoringConfig,
 ) -> TrustScore {
     let mut score: i32 = 75; // Start at neutral (Yellow tier midpoint)
@@ -214,12 +214,24 @@ pub fn calculate_trust_score(
         notes.push(format!("Consistency bonus: +{}", config.consistency_bonus));
     }
 
+    // Apply baseline anomaly penalties
+    for anomaly in &anomalies {
+        score += config.anomaly_penalty;
+        notes.push(format!(
+            "Anomaly ({}): {}",
+            config.anomaly_penalty, anomaly.detail
+        ));
+    }
+
     // Add suspicious patterns to notes
     for pattern in &profile.suspicious_patterns {
         notes.push(format!("Suspicious: {}", pattern));
     }
 
-    TrustScore::new(score as u32, notes)
+    let mut trust_score = TrustScore::new(score.max(0) as u32, notes);
+    trust_score.is_suspicious = trust_score.score < config.suspicion_threshold;
+    trust_score.anomalies = anomalies;
+    trust_score
 }
 
 /// Calculates network activity score
@@ -269,85 +281,22 @@ fn calculate_process_score(profile: &BehaviorProfile, config: &TrustScoringConfi
     score
 }
 
-/// Tracks trust scores over time for trend analysis
-#[derive(Debug, Clone, Default)]
-pub struct TrustScoreHistory {
-    /// Score history entries
-    pub entries: Vec<TrustScore>,
-    /// Maximum history size
-    max_size: usize,
-}
-
-impl TrustScoreHistory {
-    pub fn new() -> Self {
-        Self {
-            entries: Vec::new(),
-            max_size: 100,
-        }
-    }
-
-    /// Records a new trust score
-    pub fn record(&mut self, score: TrustScore) {
-        if self.entries.len() >= self.max_size {
-            self.entries.remove(0);
-        }
-        self.entries.push(score);
-    }
-
-    /// Calculates average score from history
-    pub fn average_score(&self) -> Option<f32> {
-        if self.entries.is_empty() {
-            return None;
-        }
-        let sum: u32 = self.entries.iter().map(|e| e.score).sum();
-        Some(sum as f32 / self.entries.len() as f32)
-    }
-
-    /// Detects if trust is declining
-    pub fn is_declining(&self) -> bool {
-        if self.entries.len() < 3 {
-            return false;
-        }
-
-        let recent: u32 = self.entries.iter().rev().take(3).map(|e| e.score).sum();
-        let previous: u32 = self
-            .entries
-            .iter()
-            .rev()
-            .skip(3)
-            .take(3)
-            .map(|e| e.score)
-            .sum();
-
-        recent < previous
-    }
-
-    /// Returns trend direction
-    pub fn trend(&self) -> TrustTrend {
-        if self.entries.len() < 2 {
-            return TrustTrend::Stable;
+/// Addresses are kept by network, /24 for IPv4 and /48 for IPv6, so a game
+/// served from a CDN does not meet new hosts on every run
+fn destination_key(host: &str) -> String {
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(addr)) => {
+            let [a, b, c, _] = addr.octets();
+            format!("{a}.{b}.{c}.0/24")
         }
-
-        let first = self.entries.first().unwrap().score;
-        let last = self.entries.last().unwrap().score;
-        let diff = last as i32 - first as i32;
-
-        match diff {
-            d if d > 10 => TrustTrend::Improving,
-            d if d < -10 => TrustTrend::Declining,
-            _ => TrustTrend::Stable,
+        Ok(IpAddr::V6(addr)) => {
+            let [a, b, c, ..] = addr.segments();
+            format!("{a:x}:{b:x}:{c:x}::/48")
         }
+        Err(_) => host.to_string(),
     }
 }
 
-/// Trust score trend direction
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum TrustTrend {
-    Improving,
-    Stable,
-    Declining,
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -358,7 +307,7 @@ mod tests {
 
         // Clean profile should get good score
         let clean_profile = BehaviorProfile::new();
-        let score = calculate_trust_score(TrustTier::Yellow, &clean_profile, &config);
+        let score = calculate_trust_score(TrustTier::Yellow, &clean_profile, Vec::new(), &config);
         assert!(score.score >= 70);
         assert!(!score.is_suspicious);
 
@@ -366,7 +315,8 @@ mod tests {
         let mut suspicious_profile = BehaviorProfile::new();
         suspicious_profile.record_sensitive_path("/home/user/.ssh");
         suspicious_profile.record_outbound_connection("suspicious.com");
-        let score = calculate_trust_score(TrustTier::Yellow, &suspicious_profile, &config);
+        let score =
+            calculate_trust_score(TrustTier::Yellow, &suspicious_profile, Vec::new(), &config);
         // Score should have notes explaining the issues
         assert!(!score.notes.is_empty());
         // Profile should be marked as suspicious
@@ -395,18 +345,15 @@ mod tests {
     }
 
     #[test]
-    fn test_trust_history() {
-        let mut history = TrustScoreHistory::new();
-
-        // Add 6 entries to test declining detection
-        history.record(TrustScore::new(90, vec![]));
-        history.record(TrustScore::new(85, vec![]));
-        history.record(TrustScore::new(80, vec![]));
-        history.record(TrustScore::new(75, vec![]));
-        history.record(TrustScore::new(70, vec![]));
-        history.record(TrustScore::new(60, vec![]));
-
-        assert!(history.is_declining());
-        assert_eq!(history.trend(), TrustTrend::Declining);
+    fn test_addresses_are_kept_by_network() {
+        let mut profile = BehaviorProfile::new();
+        profile.record_outbound_connection("203.0.113.7");
+        profile.record_outbound_connection("203.0.113.90");
+        profile.record_outbound_connection("2001:db8:1:2::1");
+        profile.record_outbound_connection("2001:db8:1:ff::1");
+
+        assert_eq!(profile.unique_destinations, 2);
+        assert!(profile.destinations.contains("203.0.113.0/24"));
+        assert!(profile.destinations.contains("2001:db8:1::/48"));
     }
 }
@@ -1,5 +1,9 @@
-use std::path::PathBuf;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 use uuid::Uuid;
@@ -20,3 +24,51 @@ impl SnapshotMetadata {
         }
     }
 }
+
+/// Snapshot files in `snapshot_dir` with their metadata. Files that do not
+/// parse as snapshots are skipped.
+pub fn list_snapshots(snapshot_dir: &Path) -> Result<Vec<(PathBuf, SnapshotMetadata)>> {
+    let entries = match fs::read_dir(snapshot_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("read snapshot dir {}", snapshot_dir.display()))
+        }
+    };
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+        let Ok(contents) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if let Ok(metadata) = serde_json::from_str::<SnapshotMetadata>(&contents) {
+            snapshots.push((path, metadata));
+        }
+    }
+    Ok(snapshots)
+}
+
+/// Deletes all but the newest `keep` snapshots of each prefix and returns
+/// how many were removed.
+pub fn prune_snapshots(snapshot_dir: &Path, keep: usize) -> Result<usize> {
+    let mut by_prefix: HashMap<PathBuf, Vec<(PathBuf, OffsetDateTime)>> = HashMap::new();
+    for (path, metadata) in list_snapshots(snapshot_dir)? {
+        by_prefix
+            .entry(metadata.prefix_root)
+            .or_default()
+            .push((path, metadata.created_at));
+    }
+    let mut removed = 0;
+    for snapshots in by_prefix.values_mut() {
+        snapshots.sort_by_key(|(_, created_at)| Reverse(*created_at));
+        for (path, _) in snapshots.iter().skip(keep) {
+            fs::remove_file(path).with_context(|| format!("remove snapshot {}", path.display()))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
@@ -2,14 +2,14 @@ use serde::{Deserialize, Serialize};
 use time::Duration;
 use uuid::Uuid;
 
+use policy_engine::{DecisionAction, PolicyDecision, TrustTransition, MASS_MODIFICATION_RULE};
 use winewarden_core::trust::{TrustSignal, TrustTier};
-use winewarden_core::types::{AccessAttempt, RunMetadata};
-use policy_engine::{DecisionAction, PolicyDecision};
+use winewarden_core::types::{AccessAttempt, ControlRecord, RunMetadata, SessionAction};
 
 pub mod human;
 pub mod json;
-pub mod timeline;
 pub mod redact;
+pub mod timeline;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReportEvent {
@@ -34,6 +34,12 @@ pub struct SessionReport {
     pub trust_signal: TrustSignal,
     pub events: Vec<ReportEvent>,
     pub stats: ReportStats,
+    /// Set once the run's trust outcome has been written back to the store.
+    #[serde(default)]
+    pub trust_transition: Option<TrustTransition>,
+    /// Pause, resume and kill requests made while the game ran.
+    #[serde(default)]
+    pub control: Vec<ControlRecord>,
 }
 
 impl SessionReport {
@@ -46,6 +52,8 @@ impl SessionReport {
             trust_signal,
             events,
             stats,
+            trust_transition: None,
+            control: Vec::new(),
         }
     }
 
@@ -55,7 +63,10 @@ impl SessionReport {
     }
 
     pub fn human_summary(&self) -> String {
-        let duration = self.duration().map(|d| format_duration(d)).unwrap_or_else(|| "unknown".to_string());
+        let duration = self
+            .duration()
+            .map(format_duration)
+            .unwrap_or_else(|| "unknown".to_string());
         let dangerous = self.stats.denied + self.stats.redirected + self.stats.virtualized;
         let safe_line = if dangerous == 0 {
             "No dangerous access attempts succeeded.".to_string()
@@ -63,10 +74,42 @@ impl SessionReport {
             format!("{dangerous} dangerous access attempts were blocked or redirected.")
         };
 
-        format!(
+        let mut summary = format!(
             "You played for {duration}.\n{safe_line}\nYour system remains intact.\n{signal}",
             signal = self.trust_signal.message
-        )
+        );
+        if let Some(kill) = self
+            .control
+            .iter()
+            .rev()
+            .find(|record| record.action == SessionAction::Kill)
+        {
+            summary.push_str("\nYou ended this session: ");
+            summary.push_str(&kill.summary());
+        }
+        let mut mass_modifications = self
+            .events
+            .iter()
+            .filter(|event| event.decision.rule.as_deref() == Some(MASS_MODIFICATION_RULE));
+        if let Some(event) = mass_modifications.next() {
+            summary.push('\n');
+            summary.push_str(&event.decision.reason);
+            summary.push('.');
+            let more = mass_modifications.count();
+            if more > 0 {
+                summary.push_str(&format!(" It happened {more} more time(s)."));
+            }
+        }
+        if let Some(transition) = self.trust_transition.as_ref().filter(|t| t.changed()) {
+            summary.push('\n');
+            summary.push_str(&transition.summary());
+        }
+        for anomaly in self.trust_transition.iter().flat_map(|t| &t.anomalies) {
+            summary.push_str("\nUnusual for this game: ");
+            summary.push_str(&anomaly.detail);
+            summary.push('.');
+        }
+        summary
     }
 }
 
@@ -89,8 +132,12 @@ impl ReportStats {
             match event.decision.action {
                 DecisionAction::Allow => stats.allowed = stats.allowed.saturating_add(1),
                 DecisionAction::Deny => stats.denied = stats.denied.saturating_add(1),
-                DecisionAction::Redirect(_) => stats.redirected = stats.redirected.saturating_add(1),
-                DecisionAction::Virtualize(_) => stats.virtualized = stats.virtualized.saturating_add(1),
+                DecisionAction::Redirect(_) => {
+                    stats.redirected = stats.redirected.saturating_add(1)
+                }
+                DecisionAction::Virtualize(_) => {
+                    stats.virtualized = stats.virtualized.saturating_add(1)
+                }
             }
         }
 
@@ -1,15 +1,139 @@
+use std::fs;
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
 
 use winewarden_core::config::{Config, ConfigPaths};
+use winewarden_core::overlay::{self, EffectiveConfig, OverlayTarget};
+use winewarden_core::store::ExecutableIdentity;
+use winewarden_core::{migrate, validate};
 
-pub fn print_effective(config_path: Option<PathBuf>) -> Result<()> {
+pub fn print_effective(
+    config_path: Option<PathBuf>,
+    executable: Option<PathBuf>,
+    steam_app_id: Option<u32>,
+) -> Result<()> {
     let paths = ConfigPaths::resolve()?;
     let config_path = config_path.unwrap_or(paths.config_path);
     let config = Config::load(&config_path)
         .with_context(|| format!("load config {}", config_path.display()))?;
-    let output = config.to_toml_string()?;
+
+    let effective = match executable {
+        Some(executable) => {
+            let identity =
+                ExecutableIdentity::from_path_cached(&executable, &paths.hash_cache_path)?;
+            let target = OverlayTarget {
+                identity: &identity,
+                steam_app_id: steam_app_id.or_else(overlay::steam_app_id_from_env),
+            };
+            overlay::resolve_effective(config, &config_path, &target)?
+        }
+        None => EffectiveConfig::from_base(config, Some(&config_path))?,
+    };
+
+    let output = effective.config.to_toml_string()?;
     println!("{}", output);
+    print_sources(&effective);
     Ok(())
 }
+
+pub fn validate(
+    config_path: Option<PathBuf>,
+    executable: Option<PathBuf>,
+    steam_app_id: Option<u32>,
+) -> Result<()> {
+    let paths = ConfigPaths::resolve()?;
+    let config_path = config_path.unwrap_or(paths.config_path);
+    let contents = fs::read_to_string(&config_path)
+        .with_context(|| format!("read config at {}", config_path.display()))?;
+
+    let migration = migrate::migrate_str(&contents)?;
+    if !migration.is_noop() {
+        return Err(anyhow::anyhow!(
+            "{} uses config schema v{} (current is v{}); run `winewarden config migrate` first",
+            config_path.display(),
+            migration.from_version,
+            migration.to_version
+        ));
+    }
+
+    let issues = validate::validate_toml_str(&contents);
+    if !issues.is_empty() {
+        for issue in &issues {
+            eprintln!("{}", issue.display_in(&config_path.display().to_string()));
+        }
+        return Err(anyhow::anyhow!(
+            "{} problem(s) found in {}",
+            issues.len(),
+            config_path.display()
+        ));
+    }
+
+    let overlays = overlay::load_overlays(&overlay::overlay_dir(&config_path))?;
+    let config = Config::from_toml_str(&contents)?;
+    match executable {
+        Some(executable) => {
+            let identity =
+                ExecutableIdentity::from_path_cached(&executable, &paths.hash_cache_path)?;
+            let target = OverlayTarget {
+                identity: &identity,
+                steam_app_id: steam_app_id.or_else(overlay::steam_app_id_from_env),
+            };
+            overlay::merge_overlays(config, Some(&config_path), &overlays, &target)?.validate()?;
+        }
+        None => overlay::validate_overlays(&config, Some(&config_path), &overlays)?,
+    }
+
+    println!(
+        "Config OK: {} ({} game overlay(s))",
+        config_path.display(),
+        overlays.len()
+    );
+    Ok(())
+}
+
+pub fn migrate(config_path: Option<PathBuf>, dry_run: bool) -> Result<()> {
+    let paths = ConfigPaths::resolve()?;
+    let config_path = config_path.unwrap_or(paths.config_path);
+    let contents = fs::read_to_string(&config_path)
+        .with_context(|| format!("read config at {}", config_path.display()))?;
+    let migration = migrate::migrate_str(&contents)?;
+
+    if migration.is_noop() {
+        println!(
+            "Config already at schema v{}: {}",
+            migration.to_version,
+            config_path.display()
+        );
+        return Ok(());
+    }
+    for step in &migration.applied {
+        println!("# {step}");
+    }
+    if dry_run {
+        print!("{}", migration.diff(&config_path.display().to_string()));
+        return Ok(());
+    }
+    if let Some(backup) = migrate::upgrade_in_place(&config_path)? {
+        println!(
+            "Config upgraded to schema v{} (backup at {})",
+            migration.to_version,
+            backup.display()
+        );
+    }
+    Ok(())
+}
+
+fn print_sources(effective: &EffectiveConfig) {
+    println!("# Layers (later layers win):");
+    for layer in &effective.layers {
+        match &layer.path {
+            Some(path) => println!("#   {} = {}", layer.name, path.display()),
+            None => println!("#   {}", layer.name),
+        }
+    }
+    println!("# Value sources:");
+    for (key, layer) in &effective.sources {
+        println!("#   {key} <- {layer}");
+    }
+}
@@ -2,9 +2,12 @@ use std::path::PathBuf;
 use std::process::Command;
 
 use anyhow::{Context, Result};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 
 use winewarden_core::ipc::{
-    resolve_pid_path, resolve_socket_path, send_request, WineWardenRequest, WineWardenResponse,
+    resolve_pid_path, resolve_socket_path, send_request, IpcClient, JobOutcome, SessionEvent,
+    SessionEventPayload, SubscribeRequest, WineWardenRequest, WineWardenResponse,
 };
 
 #[derive(Debug)]
@@ -22,6 +25,11 @@ pub enum DaemonAction {
     Status {
         socket: Option<PathBuf>,
     },
+    Watch {
+        socket: Option<PathBuf>,
+        request: SubscribeRequest,
+        json: bool,
+    },
     SocketPath,
     PidPath,
 }
@@ -32,6 +40,11 @@ pub fn execute(action: DaemonAction) -> Result<()> {
         DaemonAction::Stop { pid } => stop_daemon(pid),
         DaemonAction::Ping { socket } => ping(socket),
         DaemonAction::Status { socket } => status(socket),
+        DaemonAction::Watch {
+            socket,
+            request,
+            json,
+        } => watch(socket, request, json),
         DaemonAction::SocketPath => {
             println!("{}", resolve_socket_path().display());
             Ok(())
@@ -71,10 +84,15 @@ fn stop_daemon(pid_override: Option<PathBuf>) -> Result<()> {
 
 fn ping(socket_override: Option<PathBuf>) -> Result<()> {
     let socket = socket_override.unwrap_or_else(resolve_socket_path);
-    let response = send_request(&socket, &WineWardenRequest::Ping)?;
+    let mut client = IpcClient::connect(&socket)?;
+    let response = client.request(&WineWardenRequest::Ping)?;
     match response {
         WineWardenResponse::Pong => {
-            println!("WineWarden daemon is healthy.");
+            let server = client.server();
+            println!(
+                "WineWarden daemon is healthy (protocol v{}, build {}).",
+                server.protocol_version, server.build_version
+            );
             Ok(())
         }
         WineWardenResponse::Error(error) => Err(anyhow::anyhow!(error.message)),
@@ -89,12 +107,93 @@ fn status(socket_override: Option<PathBuf>) -> Result<()> {
         WineWardenResponse::Status(payload) => {
             println!("WineWarden daemon is running.");
             println!("Uptime: {}s", payload.uptime_seconds);
+            println!("Active sessions: {}", payload.active_sessions);
             if let Some(summary) = payload.last_summary {
                 println!("Last session: {}", summary);
             }
+            if let Some(config) = payload.config {
+                match config.loaded_at {
+                    Some(at) => println!(
+                        "Config: {} (loaded {})",
+                        config.path.display(),
+                        format_time(at)
+                    ),
+                    None => println!(
+                        "Config: built-in defaults ({} not found)",
+                        config.path.display()
+                    ),
+                }
+                if let Some(error) = config.last_error {
+                    println!(
+                        "Config reload failed at {}, still using the previous config: {}",
+                        format_time(error.at),
+                        error.message
+                    );
+                }
+            }
+            for job in payload.jobs {
+                let last = match (job.last_run.map(format_time), job.last_outcome) {
+                    (Some(at), Some(JobOutcome::Succeeded(summary))) => {
+                        format!("last {at}: {summary}")
+                    }
+                    (Some(at), Some(JobOutcome::Failed(error))) => {
+                        format!("last {at} failed: {error}")
+                    }
+                    _ => "never run".to_string(),
+                };
+                println!(
+                    "Job {}: next {}, {last}",
+                    job.name,
+                    format_time(job.next_run)
+                );
+            }
             Ok(())
         }
         WineWardenResponse::Error(error) => Err(anyhow::anyhow!(error.message)),
         other => Err(anyhow::anyhow!("unexpected response: {other:?}")),
     }
 }
+
+fn watch(socket_override: Option<PathBuf>, request: SubscribeRequest, json: bool) -> Result<()> {
+    let socket = socket_override.unwrap_or_else(resolve_socket_path);
+    let mut subscription = IpcClient::connect(&socket)?.subscribe(request)?;
+    while let Some(event) = subscription.next_event()? {
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string(&event).context("render event JSON")?
+            );
+        } else {
+            println!("{}", describe_event(&event));
+        }
+    }
+    Ok(())
+}
+
+fn format_time(timestamp: OffsetDateTime) -> String {
+    timestamp
+        .format(&Rfc3339)
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+fn describe_event(event: &SessionEvent) -> String {
+    let detail = match &event.payload {
+        SessionEventPayload::Started {
+            executable,
+            trust_tier,
+        } => format!("started {} at {}", executable.display(), trust_tier),
+        SessionEventPayload::Access { attempt, decision } => format!(
+            "{:?} {:?} -> {:?}: {}",
+            attempt.kind, attempt.target, decision.action, decision.reason
+        ),
+        SessionEventPayload::TrustScore {
+            score,
+            recommended_tier,
+            ..
+        } => format!("trust score {score} ({recommended_tier})"),
+        SessionEventPayload::Finished { summary } => {
+            format!("finished: {}", summary.replace('\n', " "))
+        }
+    };
+    format!("{} {}", event.session_id, detail)
+}
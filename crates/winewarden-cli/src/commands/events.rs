@@ -0,0 +1,102 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime};
+
+use winewarden_core::config::ConfigPaths;
+use winewarden_core::ipc::{
+    resolve_socket_path, send_request, EventQuery, StoredEvent, WineWardenRequest,
+    WineWardenResponse,
+};
+use winewarden_core::store::ExecutableIdentity;
+use winewarden_core::types::AccessTarget;
+
+#[derive(Debug)]
+pub struct QueryInputs {
+    pub socket: Option<PathBuf>,
+    pub executable: Option<PathBuf>,
+    pub query: EventQuery,
+    pub json: bool,
+}
+
+pub fn query(inputs: QueryInputs) -> Result<()> {
+    let mut query = inputs.query;
+    if let Some(executable) = inputs.executable {
+        let paths = ConfigPaths::resolve()?;
+        let identity = ExecutableIdentity::from_path_cached(&executable, &paths.hash_cache_path)?;
+        query.executable_sha256 = Some(identity.sha256);
+    }
+
+    let socket = inputs.socket.unwrap_or_else(resolve_socket_path);
+    let events = match send_request(&socket, &WineWardenRequest::Query(query))? {
+        WineWardenResponse::Events(events) => events,
+        WineWardenResponse::Error(error) => return Err(anyhow::anyhow!(error.message)),
+        other => return Err(anyhow::anyhow!("unexpected response: {other:?}")),
+    };
+
+    if inputs.json {
+        for event in &events {
+            println!(
+                "{}",
+                serde_json::to_string(event).context("render event JSON")?
+            );
+        }
+    } else if events.is_empty() {
+        println!("No matching events.");
+    } else {
+        for event in &events {
+            println!("{}", describe(event));
+        }
+    }
+    Ok(())
+}
+
+/// Accepts an RFC 3339 timestamp or an age such as `30m`, `12h`, `7d` or
+/// `2w`, meaning that long before now.
+pub fn parse_time(value: &str) -> Result<OffsetDateTime, String> {
+    if let Ok(timestamp) = OffsetDateTime::parse(value, &Rfc3339) {
+        return Ok(timestamp);
+    }
+    let split = value.len().saturating_sub(1);
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("expected an RFC 3339 time or an age like 7d, got {value}"))?;
+    let age = match unit {
+        "s" => Duration::seconds(amount),
+        "m" => Duration::minutes(amount),
+        "h" => Duration::hours(amount),
+        "d" => Duration::days(amount),
+        "w" => Duration::weeks(amount),
+        _ => return Err(format!("unknown age unit in {value}; use s, m, h, d or w")),
+    };
+    Ok(OffsetDateTime::now_utc() - age)
+}
+
+fn describe(event: &StoredEvent) -> String {
+    let target = match &event.attempt.target {
+        AccessTarget::Path(path) => path.display().to_string(),
+        AccessTarget::Network(network) => format!("{}:{}", network.host, network.port),
+        AccessTarget::Device(device) => device.clone(),
+        AccessTarget::Socket(socket) => socket.clone(),
+    };
+    let zone = event
+        .decision
+        .zone_label
+        .as_deref()
+        .map(|label| format!(" [{label}]"))
+        .unwrap_or_default();
+    let timestamp = event
+        .attempt
+        .timestamp
+        .format(&Rfc3339)
+        .unwrap_or_else(|_| event.attempt.timestamp.to_string());
+    format!(
+        "{timestamp} {} {} {} {target} -> {}{zone}",
+        event.session_id,
+        event.executable.display(),
+        event.attempt.kind.as_str(),
+        event.decision.action.kind().as_str(),
+    )
+}
@@ -0,0 +1,177 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use policy_engine::{DecisionAction, DecisionTrace, PolicyContext, PolicyEngine};
+use winewarden_core::config::{Config, ConfigPaths};
+use winewarden_core::overlay::{self, ConfigOverlay, OverlayTarget};
+use winewarden_core::store::ExecutableIdentity;
+use winewarden_core::trust::TrustTier;
+use winewarden_core::types::{
+    AccessAttempt, AccessKind, AccessTarget, NetworkDirection, NetworkTarget,
+};
+
+use crate::commands::run::default_prefix_path;
+
+pub struct ExplainInputs {
+    pub config_path: Option<PathBuf>,
+    pub tier: Option<TrustTier>,
+    pub kind: Option<AccessKind>,
+    pub prefix: Option<PathBuf>,
+    /// Apply the game overlays that match this executable
+    pub executable: Option<PathBuf>,
+    pub target: String,
+    pub json: bool,
+}
+
+pub fn execute(inputs: ExplainInputs) -> Result<()> {
+    let paths = ConfigPaths::resolve()?;
+    let config_path = inputs.config_path.unwrap_or(paths.config_path.clone());
+    let mut config = Config::load_validated(&config_path).with_context(|| {
+        format!(
+            "load config at {} (run `winewarden init` if missing)",
+            config_path.display()
+        )
+    })?;
+    if let Some(executable) = &inputs.executable {
+        let overlays = overlay::load_overlays(&overlay::overlay_dir(&config_path))?;
+        config = with_overlays(config, &config_path, &overlays, executable, &paths)?;
+    }
+
+    let trust_tier = inputs.tier.unwrap_or(config.trust.default_tier);
+    let context = PolicyContext {
+        prefix_root: inputs
+            .prefix
+            .unwrap_or_else(|| default_prefix_path(&paths, trust_tier)),
+        trust_tier,
+    };
+    let engine = PolicyEngine::from_config(config, &paths)?;
+
+    let (subject, trace) = match parse_target(&inputs.target, inputs.kind)? {
+        Subject::Process(name) => {
+            let trace = engine.explain_process_spawn(&name, &context);
+            (format!("spawn {name}"), trace)
+        }
+        Subject::Access(attempt) => {
+            let trace = engine.explain(&attempt, &context);
+            (
+                format!("{} {}", attempt.kind.as_str(), inputs.target),
+                trace,
+            )
+        }
+    };
+
+    if inputs.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&trace).context("render trace JSON")?
+        );
+        return Ok(());
+    }
+    println!(
+        "Explaining {subject} at tier {trust_tier} (prefix {})",
+        context.prefix_root.display()
+    );
+    print_trace(&trace);
+    Ok(())
+}
+
+/// `config` with the overlays matching `executable` merged in, validated.
+pub fn with_overlays(
+    config: Config,
+    config_path: &Path,
+    overlays: &[ConfigOverlay],
+    executable: &Path,
+    paths: &ConfigPaths,
+) -> Result<Config> {
+    let identity = ExecutableIdentity::from_path_cached(executable, &paths.hash_cache_path)?;
+    let target = OverlayTarget {
+        identity: &identity,
+        steam_app_id: overlay::steam_app_id_from_env(),
+    };
+    let effective = overlay::merge_overlays(config, Some(config_path), overlays, &target)?;
+    effective.validate()?;
+    Ok(effective.config)
+}
+
+enum Subject {
+    Access(AccessAttempt),
+    Process(String),
+}
+
+/// A bare name is a process unless another kind is asked for, `host:port`
+/// is a network destination, and anything else is a path. Paths default
+/// to reads.
+fn parse_target(target: &str, kind: Option<AccessKind>) -> Result<Subject> {
+    let access = |kind: AccessKind, target: AccessTarget| {
+        Subject::Access(AccessAttempt {
+            timestamp: time::OffsetDateTime::now_utc(),
+            kind,
+            target,
+            note: Some("winewarden explain".to_string()),
+        })
+    };
+    let subject = match (kind, parse_destination(target)) {
+        (Some(AccessKind::Device), _) => {
+            access(AccessKind::Device, AccessTarget::Device(target.to_string()))
+        }
+        (Some(AccessKind::SystemSocket), _) => access(
+            AccessKind::SystemSocket,
+            AccessTarget::Socket(target.to_string()),
+        ),
+        (Some(AccessKind::Network) | None, Some(destination)) => {
+            access(AccessKind::Network, AccessTarget::Network(destination))
+        }
+        (Some(AccessKind::Network), None) => {
+            bail!("expected host:port for a network target, got {target}")
+        }
+        (None | Some(AccessKind::Execute), None) if !target.contains('/') => {
+            Subject::Process(target.to_string())
+        }
+        (kind, _) => {
+            let path =
+                std::path::absolute(target).with_context(|| format!("resolve path {target}"))?;
+            access(kind.unwrap_or(AccessKind::Read), AccessTarget::Path(path))
+        }
+    };
+    Ok(subject)
+}
+
+fn parse_destination(target: &str) -> Option<NetworkTarget> {
+    let (host, port) = target.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() || host.contains('/') {
+        return None;
+    }
+    Some(NetworkTarget {
+        host: host.to_string(),
+        port: port.parse().ok()?,
+        protocol: "tcp".to_string(),
+        direction: NetworkDirection::Connect,
+        names: Vec::new(),
+    })
+}
+
+fn print_trace(trace: &DecisionTrace) {
+    for (index, step) in trace.steps.iter().enumerate() {
+        let mark = if step.matched { "match" } else { "skip " };
+        println!("  {:>2}. {mark} {}: {}", index + 1, step.rule, step.detail);
+    }
+    let decision = &trace.decision;
+    let rule = decision
+        .rule
+        .as_deref()
+        .map(|rule| format!(" by rule \"{rule}\""))
+        .unwrap_or_default();
+    let destination = match &decision.action {
+        DecisionAction::Redirect(path) | DecisionAction::Virtualize(path) => {
+            format!(" to {}", path.display())
+        }
+        DecisionAction::Allow | DecisionAction::Deny => String::new(),
+    };
+    println!(
+        "Decision: {}{destination}{rule} - {}",
+        decision.action.kind().as_str(),
+        decision.reason
+    );
+}
@@ -1,7 +1,11 @@
 pub mod config;
 pub mod daemon;
+pub mod events;
+pub mod explain;
+pub mod policy;
 pub mod prefix;
 pub mod report;
 pub mod run;
+pub mod session;
 pub mod status;
 pub mod trust;
@@ -0,0 +1,191 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use monitor::{EventSource, JsonlEventSource};
+use policy_engine::{
+    simulate, ChangeKind, DecisionAction, DecisionChange, PolicyContext, PolicyDecision,
+    PolicyEngine,
+};
+use reporting::SessionReport;
+use winewarden_core::config::{Config, ConfigPaths};
+use winewarden_core::overlay;
+use winewarden_core::trust::TrustTier;
+use winewarden_core::types::{AccessAttempt, AccessTarget};
+
+use crate::commands::explain::with_overlays;
+use crate::commands::run::default_prefix_path;
+
+pub enum PolicyAction {
+    Simulate(SimulateInputs),
+}
+
+pub struct SimulateInputs {
+    /// The policy in use; the active config when unset
+    pub config_path: Option<PathBuf>,
+    /// The policy to try
+    pub against: PathBuf,
+    /// A JSONL event log or a stored session report
+    pub events: PathBuf,
+    pub tier: Option<TrustTier>,
+    pub prefix: Option<PathBuf>,
+    /// Apply the game overlays matching this executable; defaults to the
+    /// report's executable
+    pub executable: Option<PathBuf>,
+    pub json: bool,
+}
+
+/// Attempts read back from a log or report, with what the report says
+/// about the session.
+struct Recorded {
+    attempts: Vec<AccessAttempt>,
+    trust_tier: Option<TrustTier>,
+    executable: Option<PathBuf>,
+}
+
+pub fn execute(action: PolicyAction) -> Result<()> {
+    match action {
+        PolicyAction::Simulate(inputs) => simulate_change(inputs),
+    }
+}
+
+fn simulate_change(inputs: SimulateInputs) -> Result<()> {
+    let paths = ConfigPaths::resolve()?;
+    let config_path = inputs.config_path.unwrap_or(paths.config_path.clone());
+    let mut baseline = load_config(&config_path)?;
+    let mut candidate = load_config(&inputs.against)?;
+
+    let recorded = load_attempts(&inputs.events)?;
+    // Both sides use the active config's overlays, so the overlays alone
+    // never show up as a change
+    if let Some(executable) = inputs.executable.or(recorded.executable) {
+        let overlays = overlay::load_overlays(&overlay::overlay_dir(&config_path))?;
+        baseline = with_overlays(baseline, &config_path, &overlays, &executable, &paths)?;
+        candidate = with_overlays(candidate, &inputs.against, &overlays, &executable, &paths)?;
+    }
+    let trust_tier = inputs
+        .tier
+        .or(recorded.trust_tier)
+        .unwrap_or(baseline.trust.default_tier);
+    let context = PolicyContext {
+        prefix_root: inputs
+            .prefix
+            .unwrap_or_else(|| default_prefix_path(&paths, trust_tier)),
+        trust_tier,
+    };
+
+    let baseline = PolicyEngine::from_config(baseline, &paths)?;
+    let candidate = PolicyEngine::from_config(candidate, &paths)?;
+    let simulation = simulate(recorded.attempts, &context, &baseline, &candidate);
+
+    if inputs.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&simulation).context("render simulation JSON")?
+        );
+        return Ok(());
+    }
+
+    println!(
+        "Simulated {} attempt(s) from {} at tier {trust_tier}",
+        simulation.evaluated,
+        inputs.events.display()
+    );
+    println!(
+        "{} -> {}: {} newly denied, {} newly allowed, {} redirect(s) changed",
+        config_path.display(),
+        inputs.against.display(),
+        simulation.count(ChangeKind::NewlyDenied),
+        simulation.count(ChangeKind::NewlyAllowed),
+        simulation.count(ChangeKind::RedirectChanged)
+    );
+    for kind in ChangeKind::ALL {
+        let changes: Vec<&DecisionChange> = simulation
+            .changes
+            .iter()
+            .filter(|change| change.kind == kind)
+            .collect();
+        if changes.is_empty() {
+            continue;
+        }
+        println!();
+        println!("{}:", heading(kind));
+        for change in changes {
+            println!(
+                "  {} {}: {} -> {}",
+                change.attempt.kind.as_str(),
+                describe_target(&change.attempt),
+                describe_decision(&change.before),
+                describe_decision(&change.after)
+            );
+        }
+    }
+    Ok(())
+}
+
+fn load_config(path: &Path) -> Result<Config> {
+    Config::load_validated(path).with_context(|| format!("load config at {}", path.display()))
+}
+
+/// Reads a session report, or failing that a JSONL event log. Reports
+/// also say which tier the session ran at and which executable it ran.
+fn load_attempts(path: &Path) -> Result<Recorded> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("read events {}", path.display()))?;
+    if let Ok(report) = serde_json::from_str::<SessionReport>(&contents) {
+        return Ok(Recorded {
+            attempts: report
+                .events
+                .into_iter()
+                .map(|event| event.attempt)
+                .collect(),
+            trust_tier: Some(report.metadata.trust_tier),
+            executable: Some(report.metadata.executable),
+        });
+    }
+
+    let mut source = JsonlEventSource::from_path(path)?;
+    let mut attempts = Vec::new();
+    while let Some(attempt) = source
+        .next_event()
+        .with_context(|| format!("read {} as a report or JSONL event log", path.display()))?
+    {
+        attempts.push(attempt);
+    }
+    Ok(Recorded {
+        attempts,
+        trust_tier: None,
+        executable: None,
+    })
+}
+
+fn heading(kind: ChangeKind) -> &'static str {
+    match kind {
+        ChangeKind::NewlyDenied => "Newly denied",
+        ChangeKind::NewlyAllowed => "Newly allowed",
+        ChangeKind::RedirectChanged => "Redirect changed",
+    }
+}
+
+fn describe_target(attempt: &AccessAttempt) -> String {
+    match &attempt.target {
+        AccessTarget::Path(path) => path.display().to_string(),
+        AccessTarget::Network(network) => format!("{}:{}", network.host, network.port),
+        AccessTarget::Device(device) => device.clone(),
+        AccessTarget::Socket(socket) => socket.clone(),
+    }
+}
+
+fn describe_decision(decision: &PolicyDecision) -> String {
+    let action = match &decision.action {
+        DecisionAction::Redirect(path) | DecisionAction::Virtualize(path) => {
+            format!("{} {}", decision.action.kind().as_str(), path.display())
+        }
+        DecisionAction::Allow | DecisionAction::Deny => decision.action.kind().as_str().to_string(),
+    };
+    match &decision.rule {
+        Some(rule) => format!("{action} ({rule})"),
+        None => action,
+    }
+}
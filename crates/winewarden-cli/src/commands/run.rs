@@ -4,16 +4,18 @@ use std::path::PathBuf;
 
 use anyhow::{Context, Result};
 
-use monitor::{Monitor, RunRequest};
+use monitor::{Monitor, RunRequest, StoreSink};
 use policy_engine::PolicyEngine;
 use prefix_manager::PrefixManager;
 use reporting::SessionReport;
 use runner::{Runner, RunnerRequest};
-use winewarden_core::config::{Config, ConfigPaths};
+use winewarden_core::config::{Config, ConfigPaths, CURRENT_SCHEMA_VERSION};
 use winewarden_core::ipc::{
     resolve_socket_path, send_request, RunRequestPayload, WineWardenRequest, WineWardenResponse,
 };
-use winewarden_core::store::{ExecutableIdentity, TrustStore};
+use winewarden_core::migrate;
+use winewarden_core::overlay::{self, OverlayTarget};
+use winewarden_core::store::{EventStore, ExecutableIdentity, TierChange, TrustStore};
 use winewarden_core::trust::TrustTier;
 use winewarden_core::types::LiveMonitorConfig;
 
@@ -39,18 +41,42 @@ pub fn execute(inputs: RunInputs) -> Result<()> {
         .config_path
         .clone()
         .unwrap_or(paths.config_path.clone());
-    let config = Config::load(&config_path).with_context(|| {
+    if config_path.exists() {
+        if let Some(backup) = migrate::upgrade_in_place(&config_path)? {
+            println!(
+                "Config upgraded to schema v{} (backup at {})",
+                CURRENT_SCHEMA_VERSION,
+                backup.display()
+            );
+        }
+    }
+    let base_config = Config::load_validated(&config_path).with_context(|| {
         format!(
             "load config at {} (run `winewarden init` if missing)",
             config_path.display()
         )
     })?;
 
-    let mut trust_store = TrustStore::load(&paths.trust_db_path)?;
-    let identity = ExecutableIdentity::from_path(&inputs.executable)?;
+    let mut trust_store = TrustStore::open(&paths.trust_db_path)?;
+    let identity =
+        ExecutableIdentity::from_path_cached(&inputs.executable, &paths.hash_cache_path)?;
+    let effective = overlay::resolve_effective(
+        base_config,
+        &config_path,
+        &OverlayTarget {
+            identity: &identity,
+            steam_app_id: overlay::steam_app_id_from_env(),
+        },
+    )?;
+    effective.validate()?;
+    for layer in effective.layers.iter().skip(1) {
+        println!("Using game profile {}", layer.name);
+    }
+    let config = effective.config;
+    let existing = trust_store.get(&identity)?;
     let base_tier = inputs
         .trust_override
-        .or_else(|| trust_store.get_tier(&identity))
+        .or(existing.as_ref().map(|record| record.tier))
         .unwrap_or(config.trust.default_tier);
 
     let mut trust_tier = base_tier;
@@ -62,9 +88,7 @@ pub fn execute(inputs: RunInputs) -> Result<()> {
         .prefix
         .unwrap_or_else(|| default_prefix_path(&paths, trust_tier));
 
-    if config.prefix.snapshot_before_first_run
-        && !trust_store.records.contains_key(&identity.sha256)
-    {
+    if config.prefix.snapshot_before_first_run && existing.is_none() {
         let manager = PrefixManager::new(prefix_root.clone(), &paths);
         let _snapshot = manager.create_snapshot()?;
     }
@@ -86,10 +110,21 @@ pub fn execute(inputs: RunInputs) -> Result<()> {
     };
     let _command = runner.dry_run(&runner_request)?;
 
-    let policy = PolicyEngine::from_config(config.clone(), &paths)?;
-    let monitor = Monitor::new(policy);
+    let baseline = existing
+        .as_ref()
+        .map(|record| record.baseline.clone())
+        .unwrap_or_default();
+    let policy = PolicyEngine::from_config(config.clone(), &paths)?.with_baseline(baseline);
+    let mut monitor = Monitor::new(policy);
+    match EventStore::open(&paths.events_db_path) {
+        Ok(store) => {
+            monitor = monitor.with_sink(Box::new(StoreSink::new(store, identity.clone())));
+        }
+        Err(err) => eprintln!("Warning: events will not be recorded: {err:#}"),
+    }
 
     let request = RunRequest {
+        session_id: None,
         executable: inputs.executable.clone(),
         args: inputs.args.clone(),
         prefix_root: prefix_root.clone(),
@@ -99,10 +134,28 @@ pub fn execute(inputs: RunInputs) -> Result<()> {
         live_monitor: inputs.live_monitor.clone(),
     };
 
-    let report = monitor.run(request)?;
-
-    trust_store.record_run(identity, trust_tier);
-    trust_store.save(&paths.trust_db_path)?;
+    let mut report = monitor.run(request)?;
+
+    let prior_clean_runs = existing.map(|record| record.clean_runs).unwrap_or(0);
+    let systemic_risks = report.stats.systemic_risks;
+    let transition = monitor
+        .policy()
+        .assess_run(trust_tier, prior_clean_runs, systemic_risks);
+    let change = transition
+        .tier_change()
+        .unwrap_or_else(|| {
+            TierChange::for_run(base_tier, trust_tier, inputs.trust_override.is_some())
+        })
+        .with_session(report.session_id);
+    let run = monitor.policy().run_summary(&transition);
+    trust_store.record_run(
+        identity,
+        transition.new_tier,
+        transition.clean_runs,
+        change,
+        &run,
+    )?;
+    report.trust_transition = Some(transition);
 
     store_report(&paths, &config, &report)?;
     println!("{}", report.human_summary());
@@ -121,6 +174,7 @@ fn run_via_daemon(inputs: RunInputs) -> Result<()> {
         pirate_safe: inputs.pirate_safe,
         config_path: inputs.config_path,
         live_monitor: inputs.live_monitor.unwrap_or_default(),
+        steam_app_id: overlay::steam_app_id_from_env(),
     };
     let response = send_request(&socket_path, &WineWardenRequest::Run(payload))?;
     match response {
@@ -155,7 +209,7 @@ fn downgrade_tier(tier: TrustTier) -> TrustTier {
     }
 }
 
-fn default_prefix_path(paths: &ConfigPaths, tier: TrustTier) -> PathBuf {
+pub fn default_prefix_path(paths: &ConfigPaths, tier: TrustTier) -> PathBuf {
     let tier_name = tier_string(tier);
     paths.data_dir.join("prefixes").join(tier_name)
 }
@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use uuid::Uuid;
+
+use winewarden_core::ipc::{
+    resolve_socket_path, send_request, ControlRequest, WineWardenRequest, WineWardenResponse,
+};
+use winewarden_core::types::SessionAction;
+
+#[derive(Debug)]
+pub enum SessionCommandAction {
+    List,
+    Control {
+        session_id: Uuid,
+        action: SessionAction,
+    },
+}
+
+pub fn execute(socket_override: Option<PathBuf>, action: SessionCommandAction) -> Result<()> {
+    let socket = socket_override.unwrap_or_else(resolve_socket_path);
+    let request = match action {
+        SessionCommandAction::List => WineWardenRequest::ListSessions,
+        SessionCommandAction::Control { session_id, action } => {
+            WineWardenRequest::ControlSession(ControlRequest { session_id, action })
+        }
+    };
+    match send_request(&socket, &request)? {
+        WineWardenResponse::Sessions(sessions) if sessions.is_empty() => {
+            println!("No sessions are running.");
+            Ok(())
+        }
+        WineWardenResponse::Sessions(sessions) => {
+            for session in sessions {
+                let state = if session.paused { "paused" } else { "running" };
+                println!(
+                    "{} {} {} ({}) since {}",
+                    session.session_id,
+                    state,
+                    session.executable.display(),
+                    session.trust_tier,
+                    session.started_at
+                );
+            }
+            Ok(())
+        }
+        WineWardenResponse::Controlled(record) => {
+            println!("{}", record.summary());
+            Ok(())
+        }
+        WineWardenResponse::Error(error) => Err(anyhow::anyhow!(error.message)),
+        other => Err(anyhow::anyhow!("unexpected response: {other:?}")),
+    }
+}
@@ -15,16 +15,16 @@ pub fn execute(executable: Option<PathBuf>, use_daemon: bool) -> Result<()> {
     }
 
     let paths = ConfigPaths::resolve()?;
-    let store = TrustStore::load(&paths.trust_db_path)?;
+    let store = TrustStore::open(&paths.trust_db_path)?;
 
     if let Some(exe) = executable {
-        let identity = ExecutableIdentity::from_path(&exe)?;
-        let tier = store.get_tier(&identity).unwrap_or(TrustTier::Yellow);
+        let identity = ExecutableIdentity::from_path_cached(&exe, &paths.hash_cache_path)?;
+        let tier = store.get_tier(&identity)?.unwrap_or(TrustTier::Yellow);
         println!("Trust: {} ({})", tier, exe.display());
         return Ok(());
     }
 
-    println!("Trusted executables: {}", store.records.len());
+    println!("Trusted executables: {}", store.records()?.len());
     Ok(())
 }
 
@@ -1,9 +1,10 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
+use time::format_description::well_known::Rfc3339;
 
 use winewarden_core::config::ConfigPaths;
-use winewarden_core::store::{ExecutableIdentity, TrustStore};
+use winewarden_core::store::{ExecutableIdentity, TierChange, TrustChangeSource, TrustStore};
 use winewarden_core::trust::TrustTier;
 
 #[derive(Debug)]
@@ -14,25 +15,89 @@ pub enum TrustAction {
     Set {
         executable: PathBuf,
         tier: TrustTier,
+        reason: Option<String>,
+    },
+    History {
+        executable: PathBuf,
     },
 }
 
 pub fn execute(action: TrustAction) -> Result<()> {
     let paths = ConfigPaths::resolve()?;
-    let mut store = TrustStore::load(&paths.trust_db_path)?;
+    let mut store = TrustStore::open(&paths.trust_db_path)?;
 
     match action {
         TrustAction::Get { executable } => {
-            let identity = ExecutableIdentity::from_path(&executable)?;
-            let tier = store.get_tier(&identity).unwrap_or(TrustTier::Yellow);
+            let identity = identity_for(&executable, &paths)?;
+            let record = store.get(&identity)?;
+            let tier = record
+                .as_ref()
+                .map(|record| record.tier)
+                .unwrap_or(TrustTier::Yellow);
             println!("Trust: {} ({})", tier, executable.display());
+            let Some(baseline) = record.map(|record| record.baseline) else {
+                return Ok(());
+            };
+            if baseline.runs > 0 {
+                println!(
+                    "Baseline: {} clean runs, {} hosts, {} child processes, {:.0} file writes and {:.0} DNS queries per run",
+                    baseline.runs,
+                    baseline.destinations.len(),
+                    baseline.child_processes.len(),
+                    baseline.file_writes,
+                    baseline.dns_queries
+                );
+            }
+            if !baseline.scores.is_empty() {
+                let scores: Vec<String> = baseline.scores.iter().map(u32::to_string).collect();
+                println!("Recent scores: {}", scores.join(", "));
+            }
         }
-        TrustAction::Set { executable, tier } => {
-            let identity = ExecutableIdentity::from_path(&executable)?;
-            store.set_tier(identity, tier);
-            store.save(&paths.trust_db_path)?;
+        TrustAction::Set {
+            executable,
+            tier,
+            reason,
+        } => {
+            let identity = identity_for(&executable, &paths)?;
+            let reason = reason.unwrap_or_else(|| "set with `winewarden trust set`".to_string());
+            store.set_tier(
+                identity,
+                tier,
+                TierChange::new(TrustChangeSource::Manual, reason),
+            )?;
             println!("Trust updated to {}", tier);
         }
+        TrustAction::History { executable } => {
+            let identity = identity_for(&executable, &paths)?;
+            let history = store.history(&identity)?;
+            if history.is_empty() {
+                println!("No trust history for {}", executable.display());
+            }
+            for entry in history {
+                let timestamp = entry
+                    .timestamp
+                    .replace_nanosecond(0)
+                    .unwrap_or(entry.timestamp)
+                    .format(&Rfc3339)
+                    .unwrap_or_default();
+                let old_tier = entry
+                    .old_tier
+                    .map(|tier| tier.to_string())
+                    .unwrap_or_else(|| "-".to_string());
+                let session = entry
+                    .session_id
+                    .map(|id| format!(" session {id}"))
+                    .unwrap_or_default();
+                println!(
+                    "{} {} -> {} [{}]{}: {}",
+                    timestamp, old_tier, entry.new_tier, entry.source, session, entry.reason
+                );
+            }
+        }
     }
     Ok(())
 }
+
+fn identity_for(executable: &Path, paths: &ConfigPaths) -> Result<ExecutableIdentity> {
+    ExecutableIdentity::from_path_cached(executable, &paths.hash_cache_path)
+}
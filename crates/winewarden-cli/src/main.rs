@@ -3,8 +3,12 @@ use std::path::PathBuf;
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 
+use time::OffsetDateTime;
+use uuid::Uuid;
+use winewarden_core::decision::DecisionKind;
+use winewarden_core::ipc::{EventFilters, EventQuery, SessionEventKind, SubscribeRequest};
 use winewarden_core::trust::TrustTier;
-use winewarden_core::types::LiveMonitorConfig;
+use winewarden_core::types::{AccessKind, LiveMonitorConfig, SessionAction};
 
 mod commands;
 mod tui;
@@ -80,9 +84,56 @@ enum Commands {
         #[command(subcommand)]
         action: DaemonCommand,
     },
+    /// Search events stored by the daemon
+    Events {
+        #[command(subcommand)]
+        action: EventsCommand,
+    },
+    /// List and control games running under the daemon
+    Session {
+        #[arg(long)]
+        socket: Option<PathBuf>,
+        #[command(subcommand)]
+        action: SessionCommand,
+    },
     Config {
+        #[command(subcommand)]
+        action: Option<ConfigCommand>,
         #[arg(long)]
         print: bool,
+        /// Show the effective config for this executable, including game
+        /// overlays; implies --print
+        #[arg(long)]
+        executable: Option<PathBuf>,
+        /// Steam app id used to match overlays (defaults to SteamAppId from the environment)
+        #[arg(long)]
+        steam_app_id: Option<u32>,
+    },
+    /// Show which rules an access would pass through and what decides it,
+    /// e.g. `explain --tier red --kind write ~/.ssh/id_ed25519`
+    Explain {
+        /// Defaults to the config's trust.default_tier
+        #[arg(long)]
+        tier: Option<TrustTier>,
+        /// read, write, execute, delete, rename, network, device or system-socket
+        #[arg(long)]
+        kind: Option<AccessKind>,
+        /// Prefix the attempt comes from (defaults to the tier's prefix)
+        #[arg(long)]
+        prefix: Option<PathBuf>,
+        /// Apply the game overlays matching this executable
+        #[arg(long)]
+        executable: Option<PathBuf>,
+        /// Print the trace as JSON
+        #[arg(long)]
+        json: bool,
+        /// A path, host:port, or process name
+        target: String,
+    },
+    /// Try out policy changes
+    Policy {
+        #[command(subcommand)]
+        action: PolicyCommand,
     },
     /// Launch interactive TUI dashboard
     Monitor {
@@ -100,6 +151,59 @@ enum TrustCommand {
     Set {
         executable: PathBuf,
         tier: TrustTier,
+        /// Recorded in the trust history
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Show how an executable's tier changed over time
+    History {
+        executable: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Check the config and game overlays for mistakes
+    Validate {
+        /// Validate the effective config for this executable instead of
+        /// each overlay on its own
+        #[arg(long)]
+        executable: Option<PathBuf>,
+        #[arg(long)]
+        steam_app_id: Option<u32>,
+    },
+    /// Upgrade the config to the current schema version, keeping a backup
+    Migrate {
+        /// Print the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum PolicyCommand {
+    /// Compare the decisions of --config and --against over recorded events,
+    /// e.g. `policy simulate --events run.jsonl --config a.toml --against b.toml`
+    Simulate {
+        /// A JSONL event log or a session report
+        #[arg(long)]
+        events: PathBuf,
+        /// The config to compare with the current one
+        #[arg(long)]
+        against: PathBuf,
+        /// Defaults to the report's tier, then the config's trust.default_tier
+        #[arg(long)]
+        tier: Option<TrustTier>,
+        /// Prefix the events came from (defaults to the tier's prefix)
+        #[arg(long)]
+        prefix: Option<PathBuf>,
+        /// Apply the game overlays matching this executable (defaults to
+        /// the report's executable)
+        #[arg(long)]
+        executable: Option<PathBuf>,
+        /// Print the changed decisions as JSON
+        #[arg(long)]
+        json: bool,
     },
 }
 
@@ -129,10 +233,80 @@ enum DaemonCommand {
         #[arg(long)]
         socket: Option<PathBuf>,
     },
+    /// Stream live events from sessions run by the daemon
+    Watch {
+        #[arg(long)]
+        socket: Option<PathBuf>,
+        /// Follow one session instead of all of them
+        #[arg(long)]
+        session: Option<Uuid>,
+        /// lifecycle, access, process, network or trust-score; repeatable
+        #[arg(long = "kind")]
+        kinds: Vec<SessionEventKind>,
+        /// Skip access events that were allowed
+        #[arg(long)]
+        denied_only: bool,
+        /// Print one JSON object per event
+        #[arg(long)]
+        json: bool,
+    },
     SocketPath,
     PidPath,
 }
 
+#[derive(Subcommand, Debug)]
+enum EventsCommand {
+    /// e.g. `events query --kind read --decision deny --zone "SSH keys" --since 7d`
+    Query {
+        #[arg(long)]
+        socket: Option<PathBuf>,
+        #[arg(long)]
+        session: Option<Uuid>,
+        /// Only events from this executable (matched by hash)
+        #[arg(long)]
+        executable: Option<PathBuf>,
+        /// RFC 3339 time or an age such as 7d
+        #[arg(long, value_parser = commands::events::parse_time)]
+        since: Option<OffsetDateTime>,
+        #[arg(long, value_parser = commands::events::parse_time)]
+        until: Option<OffsetDateTime>,
+        /// read, write, execute, delete, rename, network, device or system-socket; repeatable
+        #[arg(long = "kind")]
+        kinds: Vec<AccessKind>,
+        /// allow, deny, redirect or virtualize; repeatable
+        #[arg(long = "decision")]
+        decisions: Vec<DecisionKind>,
+        /// Sacred zone label, e.g. "SSH keys"
+        #[arg(long)]
+        zone: Option<String>,
+        /// Substring of the path, host or device
+        #[arg(long)]
+        target: Option<String>,
+        #[arg(long, default_value_t = 100)]
+        limit: u32,
+        /// Print one JSON object per event
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SessionCommand {
+    List,
+    /// Stop every process in the session with SIGSTOP
+    Pause {
+        session: Uuid,
+    },
+    /// Continue a paused session
+    Resume {
+        session: Uuid,
+    },
+    /// Shut the session's wineserver down, then SIGKILL what is left
+    Kill {
+        session: Uuid,
+    },
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
@@ -170,8 +344,17 @@ fn main() -> Result<()> {
                 TrustCommand::Get { executable } => {
                     commands::trust::TrustAction::Get { executable }
                 }
-                TrustCommand::Set { executable, tier } => {
-                    commands::trust::TrustAction::Set { executable, tier }
+                TrustCommand::Set {
+                    executable,
+                    tier,
+                    reason,
+                } => commands::trust::TrustAction::Set {
+                    executable,
+                    tier,
+                    reason,
+                },
+                TrustCommand::History { executable } => {
+                    commands::trust::TrustAction::History { executable }
                 }
             };
             commands::trust::execute(action)
@@ -196,17 +379,121 @@ fn main() -> Result<()> {
                 DaemonCommand::Status { socket } => {
                     commands::daemon::DaemonAction::Status { socket }
                 }
+                DaemonCommand::Watch {
+                    socket,
+                    session,
+                    kinds,
+                    denied_only,
+                    json,
+                } => commands::daemon::DaemonAction::Watch {
+                    socket,
+                    request: SubscribeRequest {
+                        session_id: session,
+                        filters: EventFilters { kinds, denied_only },
+                    },
+                    json,
+                },
                 DaemonCommand::SocketPath => commands::daemon::DaemonAction::SocketPath,
                 DaemonCommand::PidPath => commands::daemon::DaemonAction::PidPath,
             };
             commands::daemon::execute(action)
         }
-        Commands::Config { print } => {
-            if print {
-                commands::config::print_effective(cli.config)
-            } else {
-                Ok(())
+        Commands::Events { action } => match action {
+            EventsCommand::Query {
+                socket,
+                session,
+                executable,
+                since,
+                until,
+                kinds,
+                decisions,
+                zone,
+                target,
+                limit,
+                json,
+            } => commands::events::query(commands::events::QueryInputs {
+                socket,
+                executable,
+                query: EventQuery {
+                    session_id: session,
+                    executable_sha256: None,
+                    since,
+                    until,
+                    kinds,
+                    decisions,
+                    zone_label: zone,
+                    target_contains: target,
+                    limit: Some(limit),
+                },
+                json,
+            }),
+        },
+        Commands::Session { socket, action } => {
+            use commands::session::SessionCommandAction;
+            let (session_id, action) = match action {
+                SessionCommand::List => {
+                    return commands::session::execute(socket, SessionCommandAction::List)
+                }
+                SessionCommand::Pause { session } => (session, SessionAction::Pause),
+                SessionCommand::Resume { session } => (session, SessionAction::Resume),
+                SessionCommand::Kill { session } => (session, SessionAction::Kill),
+            };
+            commands::session::execute(socket, SessionCommandAction::Control { session_id, action })
+        }
+        Commands::Config {
+            action,
+            print,
+            executable,
+            steam_app_id,
+        } => match action {
+            Some(ConfigCommand::Validate {
+                executable,
+                steam_app_id,
+            }) => commands::config::validate(cli.config, executable, steam_app_id),
+            Some(ConfigCommand::Migrate { dry_run }) => {
+                commands::config::migrate(cli.config, dry_run)
+            }
+            None if print || executable.is_some() => {
+                commands::config::print_effective(cli.config, executable, steam_app_id)
             }
+            None => Ok(()),
+        },
+        Commands::Explain {
+            tier,
+            kind,
+            prefix,
+            executable,
+            json,
+            target,
+        } => commands::explain::execute(commands::explain::ExplainInputs {
+            config_path: cli.config,
+            tier,
+            kind,
+            prefix,
+            executable,
+            target,
+            json,
+        }),
+        Commands::Policy { action } => {
+            let action = match action {
+                PolicyCommand::Simulate {
+                    events,
+                    against,
+                    tier,
+                    prefix,
+                    executable,
+                    json,
+                } => commands::policy::PolicyAction::Simulate(commands::policy::SimulateInputs {
+                    config_path: cli.config,
+                    against,
+                    events,
+                    tier,
+                    prefix,
+                    executable,
+                    json,
+                }),
+            };
+            commands::policy::execute(action)
         }
         Commands::Monitor { session: _ } => {
             // Launch the TUI
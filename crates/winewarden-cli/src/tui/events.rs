@@ -132,32 +132,24 @@ fn handle_normal_keys(app: &mut App, key: KeyEvent) -> anyhow::Result<()> {
         }
 
         // Scrolling (for events screen)
-        KeyCode::Up => {
-            if app.current_screen == Screen::Events {
-                app.auto_scroll = false;
-                app.selected_event = app.selected_event.saturating_sub(1);
-            }
-        }
-        KeyCode::Down => {
-            if app.current_screen == Screen::Events {
-                let max = app.filtered_events().len().saturating_sub(1);
-                app.selected_event = (app.selected_event + 1).min(max);
-                if app.selected_event == max {
-                    app.auto_scroll = true;
-                }
+        KeyCode::Up if app.current_screen == Screen::Events => {
+            app.auto_scroll = false;
+            app.selected_event = app.selected_event.saturating_sub(1);
+        }
+        KeyCode::Down if app.current_screen == Screen::Events => {
+            let max = app.filtered_events().len().saturating_sub(1);
+            app.selected_event = (app.selected_event + 1).min(max);
+            if app.selected_event == max {
+                app.auto_scroll = true;
             }
         }
-        KeyCode::Home => {
-            if app.current_screen == Screen::Events {
-                app.auto_scroll = false;
-                app.selected_event = 0;
-            }
+        KeyCode::Home if app.current_screen == Screen::Events => {
+            app.auto_scroll = false;
+            app.selected_event = 0;
         }
-        KeyCode::End => {
-            if app.current_screen == Screen::Events {
-                app.selected_event = app.filtered_events().len().saturating_sub(1);
-                app.auto_scroll = true;
-            }
+        KeyCode::End if app.current_screen == Screen::Events => {
+            app.selected_event = app.filtered_events().len().saturating_sub(1);
+            app.auto_scroll = true;
         }
 
         // Number keys for direct screen navigation
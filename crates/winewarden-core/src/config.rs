@@ -5,21 +5,38 @@ use anyhow::{Context, Result};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 
+use crate::devices::DeviceClass;
+use crate::migrate;
+use crate::network::{Cidr, PortRange};
 use crate::paths::PathAction;
 use crate::trust::TrustTier;
+use crate::types::{AccessKind, NetworkDirection};
+use crate::validate;
+
+/// Bumped whenever the on-disk layout changes; see `migrate`.
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
+    pub schema_version: u32,
     pub winewarden: WineWardenConfig,
     pub trust: TrustConfig,
     pub process: ProcessConfig,
+    pub write_heuristics: WriteHeuristicsConfig,
+    pub devices: DevicesConfig,
     pub sacred_zones: Vec<SacredZoneConfig>,
+    /// Checked in order for every access attempt; the first match decides.
+    pub rules: Vec<PolicyRule>,
     pub network: NetworkConfig,
     pub prefix: PrefixConfig,
     pub reporting: ReportConfig,
+    pub maintenance: MaintenanceConfig,
+    pub daemon: DaemonConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WineWardenConfig {
     pub enabled: bool,
     pub no_prompts_during_gameplay: bool,
@@ -28,14 +45,60 @@ pub struct WineWardenConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TrustConfig {
     pub default_tier: TrustTier,
     pub pirate_safe: bool,
     pub auto_promote: bool,
     pub promotion_after_runs: u32,
+    /// Lowest end-of-run trust score (0-100) that still counts as a clean run.
+    pub promotion_min_score: u32,
+    pub scoring: TrustScoringConfig,
+    pub baseline: BaselineConfig,
+}
+
+/// Weights and penalties for the end-of-run trust score.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrustScoringConfig {
+    /// Scores (0-100) below this count as suspicious.
+    pub suspicion_threshold: u32,
+    pub network_activity_weight: f32,
+    pub filesystem_activity_weight: f32,
+    pub process_activity_weight: f32,
+    /// Per sensitive path access.
+    pub sensitive_path_penalty: i32,
+    /// Per ten outbound destinations, past the first ten.
+    pub outbound_connection_penalty: i32,
+    /// Per ten child processes, past the first ten.
+    pub child_process_penalty: i32,
+    /// For a run with no suspicious activity and no denials.
+    pub consistency_bonus: i32,
+    /// Per departure from the executable's behavior baseline.
+    pub anomaly_penalty: i32,
 }
 
+/// When a run counts as a departure from what the executable usually does.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BaselineConfig {
+    pub enabled: bool,
+    /// Clean runs learned before departures are flagged.
+    pub min_runs: u32,
+    /// Hosts never contacted in a clean run before.
+    pub new_destinations: u32,
+    /// Child processes never spawned in a clean run before.
+    pub new_child_processes: u32,
+    /// How many times the usual file write or DNS query count a run may reach.
+    pub volume_factor: f64,
+    /// Write counts below this are never flagged.
+    pub min_file_writes: u32,
+    /// DNS query counts below this are never flagged.
+    pub min_dns_queries: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ProcessConfig {
     pub allowed_patterns: Vec<String>,
     pub blocked_patterns: Vec<String>,
@@ -44,20 +107,185 @@ pub struct ProcessConfig {
     pub allow_script_execution: bool,
 }
 
+/// Ransomware-style mass changes to files, judged over a sliding window of
+/// recent writes, renames and deletes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WriteHeuristicsConfig {
+    pub enabled: bool,
+    pub window_secs: u64,
+    /// Files written or renamed within the window that make a burst...
+    pub burst_writes: u32,
+    /// ...when they span at least this many directories.
+    pub burst_directories: u32,
+    /// Bits per byte (0-8) above which written content looks encrypted.
+    pub entropy_threshold: f64,
+    /// Files under `protected_paths` written within the window with content
+    /// that looks encrypted.
+    pub encrypted_files: u32,
+    /// Files within the window deleted under `protected_paths`.
+    pub mass_deletes: u32,
+    /// Path patterns, as in rules, whose contents are the user's.
+    pub protected_paths: Vec<String>,
+    /// Pause the session when a heuristic fires.
+    pub freeze_session: bool,
+}
+
+/// Device nodes by class. A node belongs to the first class, in the order
+/// below, that covers it; nodes no class covers are left to later rules.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DevicesConfig {
+    pub gamepad: DeviceClassConfig,
+    pub gpu: DeviceClassConfig,
+    pub audio: DeviceClassConfig,
+    pub camera: DeviceClassConfig,
+    pub uinput: DeviceClassConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceClassConfig {
+    /// Globs over device node paths, such as `/dev/input/js*`.
+    pub paths: Vec<String>,
+    /// Tiers that may open the class's nodes.
+    pub tiers: Vec<TrustTier>,
+    /// USB ids as `vendor:product` in hex, with `*` for any product. When
+    /// set, only nodes of a listed device belong to the class.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ids: Vec<String>,
+}
+
+impl DevicesConfig {
+    pub fn class(&self, class: DeviceClass) -> &DeviceClassConfig {
+        match class {
+            DeviceClass::Gamepad => &self.gamepad,
+            DeviceClass::Gpu => &self.gpu,
+            DeviceClass::Audio => &self.audio,
+            DeviceClass::Camera => &self.camera,
+            DeviceClass::Uinput => &self.uinput,
+        }
+    }
+}
+
+/// `path` is a file or directory and may use the glob syntax `*`, `**`, `?`
+/// and `[...]`; the zone covers whatever it matches and everything below.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SacredZoneConfig {
     pub label: String,
     pub path: String,
     pub action: PathAction,
     pub redirect_to: Option<String>,
+    /// Overrides `action` for particular kinds of access.
+    #[serde(default, skip_serializing_if = "ZoneActions::is_empty")]
+    pub actions: ZoneActions,
+    /// Read `path` as a regex over the whole path instead.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub regex: bool,
 }
 
+/// Per-kind zone actions. Deletes and renames modify the zone, so they fall
+/// back to `write` before the zone's own `action`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZoneActions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read: Option<PathAction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub write: Option<PathAction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execute: Option<PathAction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete: Option<PathAction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rename: Option<PathAction>,
+}
+
+/// One entry in the ordered policy. The first rule whose `when` holds
+/// decides, either directly or by handing the attempt to a built-in check.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyRule {
+    pub label: String,
+    #[serde(default, skip_serializing_if = "RuleConditions::is_empty")]
+    pub when: RuleConditions,
+    pub then: RuleOutcome,
+    /// Target for `redirect` and `virtualize`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_to: Option<String>,
+}
+
+/// Every condition that is set must hold; an empty `when` matches anything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleConditions {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tier: Vec<TrustTier>,
+    #[serde(skip_serializing_if = "Vec::is_empty", with = "access_kinds")]
+    pub kind: Vec<AccessKind>,
+    /// Globs over the target path. `${PREFIX}` stands for the session's
+    /// prefix and may only start a pattern.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub path: Vec<String>,
+    /// Hashes of the target file, for execute attempts.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sha256: Vec<String>,
+    /// Network hosts; each entry also matches its subdomains.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub host: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleOutcome {
+    Allow,
+    Deny,
+    Redirect,
+    Virtualize,
+    /// Apply the most specific `[[sacred_zones]]` entry; if none matches,
+    /// carry on with the next rule.
+    SacredZones,
+    /// Decide with the `[network]` settings.
+    Network,
+    /// Decide with the `[devices]` classes; if no class covers the target,
+    /// carry on with the next rule.
+    Devices,
+}
+
+/// Access kinds are written in lowercase in the config, as `as_str` spells
+/// them, while events keep their original serialized form.
+mod access_kinds {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::types::AccessKind;
+
+    pub fn serialize<S: Serializer>(
+        kinds: &[AccessKind],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let names: Vec<&str> = kinds.iter().map(|kind| kind.as_str()).collect();
+        names.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<AccessKind>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|name| name.parse().map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct NetworkConfig {
     pub mode: NetworkMode,
     pub dns_awareness: bool,
     pub destination_monitoring: bool,
     pub block_on_malicious: bool,
+    /// Enforce mode: what happens when no rule matches, per tier.
+    pub defaults: NetworkTierDefaults,
+    /// Enforce mode: checked in order; the first matching rule decides.
+    pub rules: Vec<NetworkRule>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,9 +293,59 @@ pub struct NetworkConfig {
 pub enum NetworkMode {
     Observe,
     Permissive,
+    /// Allow or deny each connect and bind by `rules` and `defaults`.
+    Enforce,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkAction {
+    Allow,
+    Deny,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkProtocol {
+    #[default]
+    Any,
+    Tcp,
+    Udp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkTierDefaults {
+    pub green: NetworkAction,
+    pub yellow: NetworkAction,
+    pub red: NetworkAction,
+}
+
+/// One network rule. Every criterion that is set must match; a host
+/// matches if it falls under any of `domains` or `cidrs`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkRule {
+    pub label: String,
+    pub action: NetworkAction,
+    /// Each entry matches the domain and its subdomains.
+    #[serde(default)]
+    pub domains: Vec<String>,
+    #[serde(default)]
+    pub cidrs: Vec<Cidr>,
+    #[serde(default)]
+    pub ports: Vec<PortRange>,
+    #[serde(default)]
+    pub protocol: NetworkProtocol,
+    /// Connect or bind; unset matches both.
+    #[serde(default)]
+    pub direction: Option<NetworkDirection>,
+    /// Tiers the rule applies to; empty means all of them.
+    #[serde(default)]
+    pub tiers: Vec<TrustTier>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PrefixConfig {
     pub separate_by_trust: bool,
     pub snapshot_before_first_run: bool,
@@ -76,100 +354,430 @@ pub struct PrefixConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ReportConfig {
     pub store_reports: bool,
     pub human_summary: bool,
     pub structured_json: bool,
 }
 
+/// Recurring jobs the daemon runs. An interval of 0 turns a job off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    pub enabled: bool,
+    pub hygiene_scan_interval_hours: u64,
+    pub report_prune_interval_hours: u64,
+    /// Reports and stored events older than this are deleted.
+    pub report_retention_days: u32,
+    pub snapshot_prune_interval_hours: u64,
+    /// Newest snapshots kept for each prefix.
+    pub snapshots_per_prefix: u32,
+    pub trust_review_interval_hours: u64,
+    /// Green executables not run for this long drop to yellow.
+    pub trust_stale_after_days: u32,
+    pub hash_cache_cleanup_interval_hours: u64,
+}
+
+/// How the daemon follows edits to the config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DaemonConfig {
+    /// Reload when the config file or an overlay changes on disk.
+    pub watch_config: bool,
+    /// Also push new rules, sacred zones and process rules into running sessions.
+    pub update_running_sessions: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            winewarden: WineWardenConfig::default(),
+            trust: TrustConfig::default(),
+            process: ProcessConfig::default(),
+            write_heuristics: WriteHeuristicsConfig::default(),
+            devices: DevicesConfig::default(),
+            sacred_zones: default_sacred_zones(),
+            rules: default_rules(),
+            network: NetworkConfig::default(),
+            prefix: PrefixConfig::default(),
+            reporting: ReportConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            daemon: DaemonConfig::default(),
+        }
+    }
+}
+
+impl Default for WineWardenConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            no_prompts_during_gameplay: true,
+            emergency_only: true,
+            systemic_risk_only: true,
+        }
+    }
+}
+
+impl Default for TrustConfig {
+    fn default() -> Self {
+        Self {
+            default_tier: TrustTier::Yellow,
+            pirate_safe: false,
+            auto_promote: true,
+            promotion_after_runs: 3,
+            promotion_min_score: 60,
+            scoring: TrustScoringConfig::default(),
+            baseline: BaselineConfig::default(),
+        }
+    }
+}
+
+impl Default for TrustScoringConfig {
+    fn default() -> Self {
+        Self {
+            suspicion_threshold: 50,
+            network_activity_weight: 0.4,
+            filesystem_activity_weight: 0.3,
+            process_activity_weight: 0.3,
+            sensitive_path_penalty: -10,
+            outbound_connection_penalty: -5,
+            child_process_penalty: -3,
+            consistency_bonus: 5,
+            anomaly_penalty: -15,
+        }
+    }
+}
+
+impl Default for BaselineConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_runs: 3,
+            new_destinations: 10,
+            new_child_processes: 3,
+            volume_factor: 3.0,
+            min_file_writes: 100,
+            min_dns_queries: 50,
+        }
+    }
+}
+
+impl Default for WriteHeuristicsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_secs: 10,
+            burst_writes: 300,
+            burst_directories: 25,
+            entropy_threshold: 7.5,
+            encrypted_files: 20,
+            mass_deletes: 50,
+            protected_paths: [
+                "${HOME}/Documents/**",
+                "${HOME}/Pictures/**",
+                "${HOME}/Desktop/**",
+                "${DATA_DIR}/virtual/home/**",
+                "${PREFIX}/drive_c/users/*/Documents/**",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            freeze_session: false,
+        }
+    }
+}
+
+impl Default for DevicesConfig {
+    fn default() -> Self {
+        let all = vec![TrustTier::Red, TrustTier::Yellow, TrustTier::Green];
+        let class = |paths: &[&str], tiers: Vec<TrustTier>| DeviceClassConfig {
+            paths: paths.iter().map(|path| path.to_string()).collect(),
+            tiers,
+            ids: Vec::new(),
+        };
+        Self {
+            gamepad: DeviceClassConfig {
+                // Xbox 360/One/Series, then every PlayStation, Nintendo,
+                // Valve and 8BitDo device
+                ids: [
+                    "045e:028e",
+                    "045e:02ea",
+                    "045e:0b12",
+                    "045e:0b13",
+                    "054c:*",
+                    "057e:*",
+                    "28de:*",
+                    "2dc8:*",
+                ]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+                ..class(
+                    &["/dev/input/js*", "/dev/input/event*", "/dev/hidraw*"],
+                    all.clone(),
+                )
+            },
+            gpu: class(&["/dev/dri/*", "/dev/nvidia*"], all.clone()),
+            audio: class(&["/dev/snd/*"], all),
+            camera: class(&["/dev/video*"], vec![TrustTier::Green]),
+            uinput: class(&["/dev/uinput", "/dev/input/uinput"], Vec::new()),
+        }
+    }
+}
+
+impl Default for ProcessConfig {
+    fn default() -> Self {
+        Self {
+            allowed_patterns: vec![
+                "wine*".to_string(),
+                "wineserver".to_string(),
+                "*.exe".to_string(),
+            ],
+            blocked_patterns: vec![
+                "*nc*".to_string(),
+                "*netcat*".to_string(),
+                "*powershell*".to_string(),
+                "*cmd.exe*".to_string(),
+            ],
+            max_child_processes: 50,
+            allow_shell_execution: false,
+            allow_script_execution: false,
+        }
+    }
+}
+
+impl ZoneActions {
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// The action for `kind`, or `fallback` when none is set for it.
+    pub fn for_kind(&self, kind: AccessKind, fallback: PathAction) -> PathAction {
+        let action = match kind {
+            AccessKind::Read => self.read,
+            AccessKind::Write => self.write,
+            AccessKind::Execute => self.execute,
+            AccessKind::Delete => self.delete.or(self.write),
+            AccessKind::Rename => self.rename.or(self.write),
+            AccessKind::Network | AccessKind::Device | AccessKind::SystemSocket => None,
+        };
+        action.unwrap_or(fallback)
+    }
+
+    /// The actions that are set, by kind.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, PathAction)> {
+        [
+            ("read", self.read),
+            ("write", self.write),
+            ("execute", self.execute),
+            ("delete", self.delete),
+            ("rename", self.rename),
+        ]
+        .into_iter()
+        .filter_map(|(kind, action)| action.map(|action| (kind, action)))
+    }
+}
+
+impl RuleConditions {
+    pub fn is_empty(&self) -> bool {
+        self.tier.is_empty()
+            && self.kind.is_empty()
+            && self.path.is_empty()
+            && self.sha256.is_empty()
+            && self.host.is_empty()
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            mode: NetworkMode::Observe,
+            dns_awareness: true,
+            destination_monitoring: true,
+            block_on_malicious: true,
+            defaults: NetworkTierDefaults::default(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl Default for NetworkTierDefaults {
+    fn default() -> Self {
+        Self {
+            green: NetworkAction::Allow,
+            yellow: NetworkAction::Allow,
+            red: NetworkAction::Deny,
+        }
+    }
+}
+
+impl NetworkTierDefaults {
+    pub fn for_tier(&self, tier: TrustTier) -> NetworkAction {
+        match tier {
+            TrustTier::Green => self.green,
+            TrustTier::Yellow => self.yellow,
+            TrustTier::Red => self.red,
+        }
+    }
+}
+
+impl Default for PrefixConfig {
+    fn default() -> Self {
+        Self {
+            separate_by_trust: true,
+            snapshot_before_first_run: true,
+            hygiene_scan_on_run: true,
+            disposable_prefix_for_untrusted: true,
+        }
+    }
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            store_reports: true,
+            human_summary: true,
+            structured_json: true,
+        }
+    }
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            hygiene_scan_interval_hours: 24,
+            report_prune_interval_hours: 24,
+            report_retention_days: 30,
+            snapshot_prune_interval_hours: 24,
+            snapshots_per_prefix: 5,
+            trust_review_interval_hours: 168,
+            trust_stale_after_days: 90,
+            hash_cache_cleanup_interval_hours: 168,
+        }
+    }
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            watch_config: true,
+            update_running_sessions: false,
+        }
+    }
+}
+
+fn default_sacred_zones() -> Vec<SacredZoneConfig> {
+    vec![
+        SacredZoneConfig {
+            label: "Home outside prefix".to_string(),
+            path: "${HOME}".to_string(),
+            action: PathAction::Redirect,
+            redirect_to: Some("${DATA_DIR}/virtual/home".to_string()),
+            actions: ZoneActions::default(),
+            regex: false,
+        },
+        SacredZoneConfig {
+            label: "SSH keys".to_string(),
+            path: "${HOME}/.ssh".to_string(),
+            action: PathAction::Deny,
+            redirect_to: None,
+            actions: ZoneActions::default(),
+            regex: false,
+        },
+        SacredZoneConfig {
+            label: "GPG keys".to_string(),
+            path: "${HOME}/.gnupg".to_string(),
+            action: PathAction::Deny,
+            redirect_to: None,
+            actions: ZoneActions::default(),
+            regex: false,
+        },
+        SacredZoneConfig {
+            label: "User config".to_string(),
+            path: "${HOME}/.config".to_string(),
+            action: PathAction::Redirect,
+            redirect_to: Some("${DATA_DIR}/virtual/config".to_string()),
+            actions: ZoneActions::default(),
+            regex: false,
+        },
+    ]
+}
+
+/// The built-in policy written out as rules: sacred zones first, then the
+/// fixed denials, the network settings and finally the prefix boundary.
+fn default_rules() -> Vec<PolicyRule> {
+    let rule = |label: &str, when: RuleConditions, then: RuleOutcome| PolicyRule {
+        label: label.to_string(),
+        when,
+        then,
+        redirect_to: None,
+    };
+    let kinds = |kind: Vec<AccessKind>| RuleConditions {
+        kind,
+        ..RuleConditions::default()
+    };
+    vec![
+        rule(
+            "Sacred zones",
+            RuleConditions::default(),
+            RuleOutcome::SacredZones,
+        ),
+        rule("Devices", RuleConditions::default(), RuleOutcome::Devices),
+        rule(
+            "Sacred devices",
+            kinds(vec![AccessKind::Device]),
+            RuleOutcome::Deny,
+        ),
+        rule(
+            "System sockets",
+            kinds(vec![AccessKind::SystemSocket]),
+            RuleOutcome::Deny,
+        ),
+        rule(
+            "Network",
+            kinds(vec![AccessKind::Network]),
+            RuleOutcome::Network,
+        ),
+        rule(
+            "Within prefix",
+            RuleConditions {
+                path: vec!["${PREFIX}".to_string(), "${PREFIX}/**".to_string()],
+                ..RuleConditions::default()
+            },
+            RuleOutcome::Allow,
+        ),
+        rule(
+            "Prefix boundary",
+            RuleConditions::default(),
+            RuleOutcome::Deny,
+        ),
+    ]
+}
+
 #[derive(Debug, Clone)]
 pub struct ConfigPaths {
     pub config_path: PathBuf,
     pub data_dir: PathBuf,
     pub report_dir: PathBuf,
     pub trust_db_path: PathBuf,
+    pub hash_cache_path: PathBuf,
+    pub events_db_path: PathBuf,
+    pub scheduler_state_path: PathBuf,
     pub snapshot_dir: PathBuf,
 }
 
 impl Config {
     pub fn default_config() -> Self {
-        Self {
-            winewarden: WineWardenConfig {
-                enabled: true,
-                no_prompts_during_gameplay: true,
-                emergency_only: true,
-                systemic_risk_only: true,
-            },
-            trust: TrustConfig {
-                default_tier: TrustTier::Yellow,
-                pirate_safe: false,
-                auto_promote: true,
-                promotion_after_runs: 3,
-            },
-            process: ProcessConfig {
-                allowed_patterns: vec![
-                    "wine*".to_string(),
-                    "wineserver".to_string(),
-                    "*.exe".to_string(),
-                ],
-                blocked_patterns: vec![
-                    "*nc*".to_string(),
-                    "*netcat*".to_string(),
-                    "*powershell*".to_string(),
-                    "*cmd.exe*".to_string(),
-                ],
-                max_child_processes: 50,
-                allow_shell_execution: false,
-                allow_script_execution: false,
-            },
-            sacred_zones: vec![
-                SacredZoneConfig {
-                    label: "Home outside prefix".to_string(),
-                    path: "${HOME}".to_string(),
-                    action: PathAction::Redirect,
-                    redirect_to: Some("${DATA_DIR}/virtual/home".to_string()),
-                },
-                SacredZoneConfig {
-                    label: "SSH keys".to_string(),
-                    path: "${HOME}/.ssh".to_string(),
-                    action: PathAction::Deny,
-                    redirect_to: None,
-                },
-                SacredZoneConfig {
-                    label: "GPG keys".to_string(),
-                    path: "${HOME}/.gnupg".to_string(),
-                    action: PathAction::Deny,
-                    redirect_to: None,
-                },
-                SacredZoneConfig {
-                    label: "User config".to_string(),
-                    path: "${HOME}/.config".to_string(),
-                    action: PathAction::Redirect,
-                    redirect_to: Some("${DATA_DIR}/virtual/config".to_string()),
-                },
-            ],
-            network: NetworkConfig {
-                mode: NetworkMode::Observe,
-                dns_awareness: true,
-                destination_monitoring: true,
-                block_on_malicious: true,
-            },
-            prefix: PrefixConfig {
-                separate_by_trust: true,
-                snapshot_before_first_run: true,
-                hygiene_scan_on_run: true,
-                disposable_prefix_for_untrusted: true,
-            },
-            reporting: ReportConfig {
-                store_reports: true,
-                human_summary: true,
-                structured_json: true,
-            },
-        }
+        Self::default()
     }
 
+    /// Parses config source, upgrading older schema versions in memory.
     pub fn from_toml_str(contents: &str) -> Result<Self> {
-        let config: Config = toml::from_str(contents).context("parse config TOML")?;
+        let migration = migrate::migrate_str(contents)?;
+        let config: Config = toml::from_str(&migration.migrated).context("parse config TOML")?;
         Ok(config)
     }
 
@@ -184,6 +792,19 @@ impl Config {
         Self::from_toml_str(&contents)
     }
 
+    /// Like `load`, but rejects configs that fail validation.
+    pub fn load_validated(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("read config at {}", path.display()))?;
+        let migration = migrate::migrate_str(&contents)
+            .with_context(|| format!("migrate config at {}", path.display()))?;
+        validate::ensure_valid(
+            &validate::validate_toml_str(&migration.migrated),
+            &path.display().to_string(),
+        )?;
+        Self::from_toml_str(&migration.migrated)
+    }
+
     pub fn save(&self, path: &Path) -> Result<()> {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)
@@ -199,17 +820,28 @@ impl ConfigPaths {
     pub fn resolve() -> Result<Self> {
         let project_dirs = ProjectDirs::from("io", "winewarden", "winewarden")
             .ok_or_else(|| anyhow::anyhow!("unable to determine project directories"))?;
-        let config_dir = project_dirs.config_dir();
-        let data_dir = project_dirs.data_dir();
-        let report_dir = data_dir.join("reports");
-        let trust_db_path = data_dir.join("trust.json");
-        let snapshot_dir = data_dir.join("snapshots");
-        Ok(Self {
+        Ok(Self::layout(
+            project_dirs.config_dir(),
+            project_dirs.data_dir(),
+        ))
+    }
+
+    /// The usual layout with config and data under `dir`, as tests and
+    /// throwaway setups want it.
+    pub fn under(dir: &Path) -> Self {
+        Self::layout(dir, &dir.join("data"))
+    }
+
+    fn layout(config_dir: &Path, data_dir: &Path) -> Self {
+        Self {
             config_path: config_dir.join("config.toml"),
             data_dir: data_dir.to_path_buf(),
-            report_dir,
-            trust_db_path,
-            snapshot_dir,
-        })
+            report_dir: data_dir.join("reports"),
+            trust_db_path: data_dir.join("trust.db"),
+            hash_cache_path: data_dir.join("hash_cache.db"),
+            events_db_path: data_dir.join("events.db"),
+            scheduler_state_path: data_dir.join("scheduler.json"),
+            snapshot_dir: data_dir.join("snapshots"),
+        }
     }
 }
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyDecision {
+    pub action: DecisionAction,
+    pub reason: String,
+    pub zone_label: Option<String>,
+    pub systemic_risk: bool,
+    /// The configured rule that decided, when one did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DecisionAction {
+    Allow,
+    Deny,
+    Redirect(PathBuf),
+    Virtualize(PathBuf),
+}
+
+/// A `DecisionAction` without its target, for filtering and indexing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionKind {
+    Allow,
+    Deny,
+    Redirect,
+    Virtualize,
+}
+
+impl DecisionAction {
+    pub fn kind(&self) -> DecisionKind {
+        match self {
+            DecisionAction::Allow => DecisionKind::Allow,
+            DecisionAction::Deny => DecisionKind::Deny,
+            DecisionAction::Redirect(_) => DecisionKind::Redirect,
+            DecisionAction::Virtualize(_) => DecisionKind::Virtualize,
+        }
+    }
+}
+
+impl DecisionKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DecisionKind::Allow => "allow",
+            DecisionKind::Deny => "deny",
+            DecisionKind::Redirect => "redirect",
+            DecisionKind::Virtualize => "virtualize",
+        }
+    }
+}
+
+impl FromStr for DecisionKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "allow" | "allowed" => Ok(DecisionKind::Allow),
+            "deny" | "denied" => Ok(DecisionKind::Deny),
+            "redirect" | "redirected" => Ok(DecisionKind::Redirect),
+            "virtualize" | "virtualized" => Ok(DecisionKind::Virtualize),
+            _ => Err(format!("unknown decision: {value}")),
+        }
+    }
+}
+
+/// How a decision was reached: each rule considered, in order, and the
+/// decision it led to. Only built on request, e.g. by `winewarden explain`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionTrace {
+    pub steps: Vec<TraceStep>,
+    pub decision: PolicyDecision,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceStep {
+    pub rule: String,
+    /// Whether the rule's conditions held. A matched rule that delegates
+    /// can still pass the attempt on to the next rule.
+    pub matched: bool,
+    /// Why the rule did not match, or what it made of the attempt.
+    pub detail: String,
+}
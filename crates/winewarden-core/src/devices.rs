@@ -0,0 +1,280 @@
+//! Device classes: which device nodes a game may open at each trust tier.
+//!
+//! The classes decide device attempts through a `devices` rule. The Landlock
+//! sandbox is offered every node a class covers and keeps those the rules
+//! allow, so both see the same decision.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{DeviceClassConfig, DevicesConfig};
+use crate::trust::TrustTier;
+
+/// How deep `nodes` looks below the device root.
+const MAX_DEPTH: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceClass {
+    Gamepad,
+    Gpu,
+    Audio,
+    Camera,
+    Uinput,
+}
+
+impl DeviceClass {
+    /// A node belongs to the first class in this order that covers it.
+    pub const ALL: [DeviceClass; 5] = [
+        DeviceClass::Gamepad,
+        DeviceClass::Gpu,
+        DeviceClass::Audio,
+        DeviceClass::Camera,
+        DeviceClass::Uinput,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeviceClass::Gamepad => "gamepad",
+            DeviceClass::Gpu => "gpu",
+            DeviceClass::Audio => "audio",
+            DeviceClass::Camera => "camera",
+            DeviceClass::Uinput => "uinput",
+        }
+    }
+}
+
+impl fmt::Display for DeviceClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A USB `vendor:product` id in hex; a `*` product matches any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceId {
+    pub vendor: u16,
+    pub product: Option<u16>,
+}
+
+impl DeviceId {
+    pub fn matches(self, vendor: u16, product: u16) -> bool {
+        self.vendor == vendor && self.product.is_none_or(|wanted| wanted == product)
+    }
+}
+
+impl FromStr for DeviceId {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected vendor:product in hex, got \"{value}\"");
+        let (vendor, product) = value.split_once(':').ok_or_else(invalid)?;
+        let vendor = u16::from_str_radix(vendor, 16).map_err(|_| invalid())?;
+        let product = match product {
+            "*" => None,
+            product => Some(u16::from_str_radix(product, 16).map_err(|_| invalid())?),
+        };
+        Ok(DeviceId { vendor, product })
+    }
+}
+
+/// The `[devices]` section, compiled.
+#[derive(Debug, Clone, Default)]
+pub struct DevicePolicy {
+    classes: Vec<CompiledClass>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledClass {
+    class: DeviceClass,
+    paths: Vec<GlobMatcher>,
+    tiers: Vec<TrustTier>,
+    ids: Vec<DeviceId>,
+}
+
+impl DevicePolicy {
+    pub fn compile(config: &DevicesConfig) -> Result<Self> {
+        let classes = DeviceClass::ALL
+            .into_iter()
+            .map(|class| compile_class(class, config.class(class)))
+            .collect::<Result<_>>()?;
+        Ok(Self { classes })
+    }
+
+    /// The class `node` belongs to, if any.
+    pub fn classify(&self, node: &Path) -> Option<DeviceClass> {
+        self.classify_with(node, device_id)
+    }
+
+    fn classify_with(
+        &self,
+        node: &Path,
+        id_of: impl Fn(&Path) -> Option<(u16, u16)>,
+    ) -> Option<DeviceClass> {
+        self.classes
+            .iter()
+            .find(|class| {
+                class.paths.iter().any(|pattern| pattern.is_match(node))
+                    && (class.ids.is_empty()
+                        || id_of(node).is_some_and(|(vendor, product)| {
+                            class.ids.iter().any(|id| id.matches(vendor, product))
+                        }))
+            })
+            .map(|class| class.class)
+    }
+
+    pub fn allows(&self, class: DeviceClass, tier: TrustTier) -> bool {
+        self.classes
+            .iter()
+            .any(|compiled| compiled.class == class && compiled.tiers.contains(&tier))
+    }
+
+    /// The nodes under `dev_root` some class covers, whatever the tier, for
+    /// the sandbox to decide on. Symlinks are not followed.
+    pub fn nodes(&self, dev_root: &Path) -> Vec<PathBuf> {
+        let mut nodes = Vec::new();
+        let mut pending = vec![(dev_root.to_path_buf(), 0)];
+        while let Some((dir, depth)) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let Ok(file_type) = entry.file_type() else {
+                    continue;
+                };
+                let path = entry.path();
+                if file_type.is_dir() {
+                    if depth + 1 < MAX_DEPTH {
+                        pending.push((path, depth + 1));
+                    }
+                } else if !file_type.is_symlink() && self.classify(&path).is_some() {
+                    nodes.push(path);
+                }
+            }
+        }
+        nodes.sort();
+        nodes
+    }
+}
+
+fn compile_class(class: DeviceClass, config: &DeviceClassConfig) -> Result<CompiledClass> {
+    let paths = config
+        .paths
+        .iter()
+        .map(|pattern| {
+            Ok(GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("parse device pattern {pattern}"))?
+                .compile_matcher())
+        })
+        .collect::<Result<_>>()?;
+    let ids = config
+        .ids
+        .iter()
+        .map(|id| id.parse().map_err(|err: String| anyhow::anyhow!(err)))
+        .collect::<Result<_>>()
+        .with_context(|| format!("compile device class {class}"))?;
+    Ok(CompiledClass {
+        class,
+        paths,
+        tiers: config.tiers.clone(),
+        ids,
+    })
+}
+
+/// The USB id of the device behind an input or hidraw node, from sysfs.
+pub fn device_id(node: &Path) -> Option<(u16, u16)> {
+    let name = node.file_name()?.to_str()?;
+    if name.starts_with("hidraw") {
+        // HID_ID=<bus>:<vendor>:<product>, each zero-padded hex
+        let uevent = fs::read_to_string(format!("/sys/class/hidraw/{name}/device/uevent")).ok()?;
+        let id = uevent
+            .lines()
+            .find_map(|line| line.strip_prefix("HID_ID="))?;
+        let mut parts = id.split(':').skip(1);
+        let mut next = || {
+            u32::from_str_radix(parts.next()?, 16)
+                .ok()
+                .and_then(|id| u16::try_from(id).ok())
+        };
+        return Some((next()?, next()?));
+    }
+    let read = |field: &str| {
+        let id = fs::read_to_string(format!("/sys/class/input/{name}/device/id/{field}")).ok()?;
+        u16::from_str_radix(id.trim(), 16).ok()
+    };
+    Some((read("vendor")?, read("product")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> DevicePolicy {
+        DevicePolicy::compile(&DevicesConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn test_default_classes_per_tier() {
+        let policy = policy();
+        let gpu = Path::new("/dev/dri/renderD128");
+        assert_eq!(policy.classify(gpu), Some(DeviceClass::Gpu));
+        assert_eq!(
+            policy.classify(Path::new("/dev/nvidia0")),
+            Some(DeviceClass::Gpu)
+        );
+        assert_eq!(
+            policy.classify(Path::new("/dev/snd/pcmC0D0p")),
+            Some(DeviceClass::Audio)
+        );
+        assert_eq!(
+            policy.classify(Path::new("/dev/video0")),
+            Some(DeviceClass::Camera)
+        );
+        assert_eq!(
+            policy.classify(Path::new("/dev/uinput")),
+            Some(DeviceClass::Uinput)
+        );
+        assert_eq!(policy.classify(Path::new("/dev/sda")), None);
+
+        assert!(policy.allows(DeviceClass::Gpu, TrustTier::Red));
+        assert!(!policy.allows(DeviceClass::Camera, TrustTier::Yellow));
+        assert!(policy.allows(DeviceClass::Camera, TrustTier::Green));
+        assert!(!policy.allows(DeviceClass::Uinput, TrustTier::Green));
+    }
+
+    #[test]
+    fn test_gamepads_are_matched_by_id() {
+        let policy = policy();
+        let node = Path::new("/dev/hidraw3");
+        let xbox = |_: &Path| Some((0x045e, 0x028e));
+        let security_key = |_: &Path| Some((0x1050, 0x0407));
+        assert_eq!(policy.classify_with(node, xbox), Some(DeviceClass::Gamepad));
+        assert_eq!(policy.classify_with(node, security_key), None);
+        assert_eq!(policy.classify_with(node, |_| None), None);
+    }
+
+    #[test]
+    fn test_parse_device_id() {
+        assert_eq!(
+            "28de:*".parse::<DeviceId>(),
+            Ok(DeviceId {
+                vendor: 0x28de,
+                product: None
+            })
+        );
+        assert!("045E:028E"
+            .parse::<DeviceId>()
+            .unwrap()
+            .matches(0x045e, 0x028e));
+        assert!("045e".parse::<DeviceId>().is_err());
+        assert!("xbox:1".parse::<DeviceId>().is_err());
+    }
+}
@@ -10,18 +10,36 @@ pub struct ExecId(pub Uuid);
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct PrefixId(pub Uuid);
 
+impl Default for RunId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RunId {
     pub fn new() -> Self {
         Self(Uuid::new_v4())
     }
 }
 
+impl Default for ExecId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ExecId {
     pub fn new() -> Self {
         Self(Uuid::new_v4())
     }
 }
 
+impl Default for PrefixId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PrefixId {
     pub fn new() -> Self {
         Self(Uuid::new_v4())
@@ -0,0 +1,189 @@
+//! Live session events streamed to subscribers.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::decision::{DecisionAction, PolicyDecision};
+use crate::trust::TrustTier;
+use crate::types::{AccessAttempt, AccessKind};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubscribeRequest {
+    /// Only events from this session; `None` follows every session.
+    pub session_id: Option<Uuid>,
+    #[serde(default)]
+    pub filters: EventFilters,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventFilters {
+    /// Event kinds to deliver; empty means all of them.
+    #[serde(default)]
+    pub kinds: Vec<SessionEventKind>,
+    /// Drop access events the policy allowed.
+    #[serde(default)]
+    pub denied_only: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEventKind {
+    Lifecycle,
+    Access,
+    Process,
+    Network,
+    TrustScore,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEvent {
+    pub session_id: Uuid,
+    pub timestamp: OffsetDateTime,
+    pub payload: SessionEventPayload,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload")]
+pub enum SessionEventPayload {
+    Started {
+        executable: PathBuf,
+        trust_tier: TrustTier,
+    },
+    /// An evaluated access attempt, as it will appear in the report.
+    Access {
+        attempt: Box<AccessAttempt>,
+        decision: PolicyDecision,
+    },
+    TrustScore {
+        score: u32,
+        recommended_tier: TrustTier,
+        assessment: String,
+    },
+    Finished {
+        summary: String,
+    },
+}
+
+impl SessionEvent {
+    pub fn now(session_id: Uuid, payload: SessionEventPayload) -> Self {
+        Self {
+            session_id,
+            timestamp: OffsetDateTime::now_utc(),
+            payload,
+        }
+    }
+
+    /// Process spawns and network connections are access events with their
+    /// own kinds, so subscribers can follow them separately.
+    pub fn kind(&self) -> SessionEventKind {
+        match &self.payload {
+            SessionEventPayload::Started { .. } | SessionEventPayload::Finished { .. } => {
+                SessionEventKind::Lifecycle
+            }
+            SessionEventPayload::Access { attempt, .. } => match attempt.kind {
+                AccessKind::Execute => SessionEventKind::Process,
+                AccessKind::Network => SessionEventKind::Network,
+                _ => SessionEventKind::Access,
+            },
+            SessionEventPayload::TrustScore { .. } => SessionEventKind::TrustScore,
+        }
+    }
+}
+
+impl FromStr for SessionEventKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().replace('-', "_").as_str() {
+            "lifecycle" => Ok(SessionEventKind::Lifecycle),
+            "access" => Ok(SessionEventKind::Access),
+            "process" => Ok(SessionEventKind::Process),
+            "network" => Ok(SessionEventKind::Network),
+            "trust_score" => Ok(SessionEventKind::TrustScore),
+            _ => Err(format!("unknown event kind: {value}")),
+        }
+    }
+}
+
+impl SubscribeRequest {
+    pub fn matches(&self, event: &SessionEvent) -> bool {
+        if self.session_id.is_some_and(|id| id != event.session_id) {
+            return false;
+        }
+        if !self.filters.kinds.is_empty() && !self.filters.kinds.contains(&event.kind()) {
+            return false;
+        }
+        match &event.payload {
+            SessionEventPayload::Access { decision, .. } if self.filters.denied_only => {
+                !matches!(decision.action, DecisionAction::Allow)
+            }
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AccessTarget, NetworkDirection, NetworkTarget};
+
+    fn access(session_id: Uuid, kind: AccessKind, action: DecisionAction) -> SessionEvent {
+        let target = match kind {
+            AccessKind::Network => AccessTarget::Network(NetworkTarget {
+                host: "example.com".to_string(),
+                port: 443,
+                protocol: "tcp".to_string(),
+                direction: NetworkDirection::Connect,
+                names: Vec::new(),
+            }),
+            _ => AccessTarget::Path(PathBuf::from("/tmp/file")),
+        };
+        SessionEvent::now(
+            session_id,
+            SessionEventPayload::Access {
+                attempt: Box::new(AccessAttempt {
+                    timestamp: OffsetDateTime::now_utc(),
+                    kind,
+                    target,
+                    note: None,
+                }),
+                decision: PolicyDecision {
+                    action,
+                    reason: "test".to_string(),
+                    zone_label: None,
+                    systemic_risk: false,
+                    rule: None,
+                },
+            },
+        )
+    }
+
+    #[test]
+    fn test_filters() {
+        let session = Uuid::new_v4();
+        let network = access(session, AccessKind::Network, DecisionAction::Allow);
+        let denied_write = access(session, AccessKind::Write, DecisionAction::Deny);
+        assert_eq!(network.kind(), SessionEventKind::Network);
+
+        assert!(SubscribeRequest::default().matches(&network));
+        let other_session = SubscribeRequest {
+            session_id: Some(Uuid::new_v4()),
+            ..Default::default()
+        };
+        assert!(!other_session.matches(&network));
+
+        let denied_only = SubscribeRequest {
+            session_id: Some(session),
+            filters: EventFilters {
+                kinds: vec![SessionEventKind::Access, SessionEventKind::Network],
+                denied_only: true,
+            },
+        };
+        assert!(!denied_only.matches(&network));
+        assert!(denied_only.matches(&denied_write));
+    }
+}
@@ -0,0 +1,203 @@
+//! Length-prefixed framing and the version handshake.
+//!
+//! Each frame is a 4-byte big-endian length followed by that many bytes of
+//! JSON. Frames are capped at `MAX_FRAME_LEN`, so the first byte of a framed
+//! connection is always `0x00`; the pre-framing protocol sent a bare JSON
+//! object per line, whose first byte is `{`. The daemon uses that byte to
+//! pick the protocol for a connection.
+
+use std::io::{self, Read, Write};
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{ErrorPayload, WineWardenRequest, WineWardenResponse};
+
+/// Bumped on any incompatible change to frames or message shapes, new
+/// request and response variants included, since an older peer cannot parse
+/// them. Version 2 added subscriptions, session listing and control, and
+/// event queries.
+pub const PROTOCOL_VERSION: u32 = 2;
+/// Version of the crate that built this binary, exchanged in the handshake.
+pub const BUILD_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Largest frame either side accepts.
+pub const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+/// First byte of a connection that uses newline-delimited JSON.
+pub const LEGACY_FIRST_BYTE: u8 = b'{';
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hello {
+    pub protocol_version: u32,
+    pub build_version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload")]
+pub enum ClientFrame {
+    Hello(Hello),
+    /// `id` is a correlation id, echoed by the response. The daemon answers
+    /// a connection's requests one at a time, in order; ids do not let
+    /// several requests be in flight on one connection.
+    Request {
+        id: u64,
+        request: WineWardenRequest,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload")]
+pub enum ServerFrame {
+    Welcome(Hello),
+    /// Sent instead of `Welcome` when the protocol versions differ, or in
+    /// place of a response when a frame cannot be parsed; the daemon closes
+    /// the connection afterwards.
+    Rejected {
+        server: Hello,
+        error: ErrorPayload,
+    },
+    Response {
+        id: u64,
+        response: WineWardenResponse,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum IpcError {
+    #[error(
+        "peer speaks protocol v{peer} (build {peer_build}) but this build speaks v{ours} \
+         (build {ours_build}); run the CLI and daemon from the same build"
+    )]
+    VersionMismatch {
+        ours: u32,
+        ours_build: String,
+        peer: u32,
+        peer_build: String,
+    },
+    #[error("frame of {0} bytes exceeds the {MAX_FRAME_LEN} byte limit")]
+    FrameTooLarge(usize),
+    #[error("connection closed before a complete frame arrived")]
+    Truncated,
+    #[error("unexpected frame: {0}")]
+    UnexpectedFrame(String),
+    #[error("response id {got} does not match request id {expected}")]
+    IdMismatch { expected: u64, got: u64 },
+}
+
+impl Hello {
+    pub fn current() -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            build_version: BUILD_VERSION.to_string(),
+        }
+    }
+
+    /// Checks a peer's hello against this build.
+    pub fn check_compatible(&self) -> Result<(), IpcError> {
+        if self.protocol_version == PROTOCOL_VERSION {
+            return Ok(());
+        }
+        Err(IpcError::VersionMismatch {
+            ours: PROTOCOL_VERSION,
+            ours_build: BUILD_VERSION.to_string(),
+            peer: self.protocol_version,
+            peer_build: self.build_version.clone(),
+        })
+    }
+}
+
+pub fn write_frame<T: Serialize>(writer: &mut impl Write, message: &T) -> Result<()> {
+    let body = serde_json::to_vec(message).context("serialize frame")?;
+    if body.len() > MAX_FRAME_LEN {
+        return Err(IpcError::FrameTooLarge(body.len()).into());
+    }
+    writer.write_all(&(body.len() as u32).to_be_bytes())?;
+    writer.write_all(&body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads one frame. Returns `None` if the peer closed the connection
+/// cleanly between frames.
+pub fn read_frame<T: DeserializeOwned>(reader: &mut impl Read) -> Result<Option<T>> {
+    let mut header = [0u8; 4];
+    let mut filled = 0;
+    while filled < header.len() {
+        match reader.read(&mut header[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err(IpcError::Truncated.into()),
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    let len = u32::from_be_bytes(header) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(IpcError::FrameTooLarge(len).into());
+    }
+    let mut body = vec![0; len];
+    reader
+        .read_exact(&mut body)
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::UnexpectedEof => IpcError::Truncated.into(),
+            _ => anyhow::Error::from(err),
+        })?;
+    let message = serde_json::from_slice(&body).context("parse frame JSON")?;
+    Ok(Some(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_round_trip() {
+        let mut buffer = Vec::new();
+        let frame = ClientFrame::Request {
+            id: 7,
+            request: WineWardenRequest::Ping,
+        };
+        write_frame(&mut buffer, &frame).unwrap();
+        assert_eq!(buffer[0], 0);
+
+        let mut reader = buffer.as_slice();
+        let decoded: ClientFrame = read_frame(&mut reader).unwrap().unwrap();
+        assert!(matches!(
+            decoded,
+            ClientFrame::Request {
+                id: 7,
+                request: WineWardenRequest::Ping
+            }
+        ));
+        assert!(read_frame::<ClientFrame>(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_oversized_and_truncated_frames() {
+        let header = ((MAX_FRAME_LEN + 1) as u32).to_be_bytes();
+        let err = read_frame::<ClientFrame>(&mut header.as_slice()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<IpcError>(),
+            Some(IpcError::FrameTooLarge(_))
+        ));
+
+        let partial = [0u8, 0, 0, 10, b'{'];
+        let err = read_frame::<ClientFrame>(&mut partial.as_slice()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<IpcError>(),
+            Some(IpcError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        assert!(Hello::current().check_compatible().is_ok());
+        let future = Hello {
+            protocol_version: PROTOCOL_VERSION + 1,
+            build_version: "9.9.9".to_string(),
+        };
+        let err = future.check_compatible().unwrap_err();
+        assert!(err.to_string().contains("9.9.9"));
+    }
+}
@@ -0,0 +1,366 @@
+use std::io::{BufReader, BufWriter};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::trust::TrustTier;
+use crate::types::{ControlRecord, LiveMonitorConfig, SessionAction};
+
+mod events;
+mod frame;
+mod query;
+
+pub use events::{
+    EventFilters, SessionEvent, SessionEventKind, SessionEventPayload, SubscribeRequest,
+};
+pub use frame::{
+    read_frame, write_frame, ClientFrame, Hello, IpcError, ServerFrame, BUILD_VERSION,
+    LEGACY_FIRST_BYTE, MAX_FRAME_LEN, PROTOCOL_VERSION,
+};
+pub use query::{EventQuery, StoredEvent};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRequestPayload {
+    pub executable: PathBuf,
+    pub args: Vec<String>,
+    pub prefix_root: Option<PathBuf>,
+    pub event_log: Option<PathBuf>,
+    pub trust_override: Option<TrustTier>,
+    pub no_run: bool,
+    pub pirate_safe: bool,
+    pub config_path: Option<PathBuf>,
+    #[serde(default)]
+    pub live_monitor: LiveMonitorConfig,
+    #[serde(default)]
+    pub steam_app_id: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusPayload {
+    pub started_at: OffsetDateTime,
+    pub uptime_seconds: u64,
+    pub active_sessions: u32,
+    pub last_session_id: Option<Uuid>,
+    pub last_summary: Option<String>,
+    /// Maintenance jobs, in schedule order.
+    #[serde(default)]
+    pub jobs: Vec<JobStatus>,
+    /// The config new runs use; absent from older daemons.
+    #[serde(default)]
+    pub config: Option<ConfigStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigStatus {
+    pub path: PathBuf,
+    /// When the config in use was read; `None` means built-in defaults.
+    pub loaded_at: Option<OffsetDateTime>,
+    /// The latest reload that was rejected, kept until one succeeds.
+    pub last_error: Option<ReloadError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReloadError {
+    pub at: OffsetDateTime,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub name: String,
+    pub interval_seconds: u64,
+    pub next_run: OffsetDateTime,
+    pub last_run: Option<OffsetDateTime>,
+    pub last_outcome: Option<JobOutcome>,
+}
+
+/// How a maintenance job's last run ended, with a one-line description.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "message", rename_all = "snake_case")]
+pub enum JobOutcome {
+    Succeeded(String),
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunResult {
+    pub session_id: Uuid,
+    pub summary: String,
+}
+
+/// A session the daemon is monitoring right now.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub session_id: Uuid,
+    pub executable: PathBuf,
+    pub trust_tier: TrustTier,
+    pub started_at: OffsetDateTime,
+    pub paused: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlRequest {
+    pub session_id: Uuid,
+    pub action: SessionAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorPayload {
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload")]
+pub enum WineWardenRequest {
+    Ping,
+    Status,
+    Run(RunRequestPayload),
+    /// Framed connections only. Answered with `Subscribed`, then one `Event`
+    /// response per matching event until the client disconnects.
+    Subscribe(SubscribeRequest),
+    ListSessions,
+    /// Pause, resume or kill a running session's whole process tree.
+    ControlSession(ControlRequest),
+    /// Search stored events; answered with `Events`, newest first.
+    Query(EventQuery),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload")]
+pub enum WineWardenResponse {
+    Pong,
+    Status(StatusPayload),
+    RunResult(RunResult),
+    Subscribed,
+    Event(SessionEvent),
+    Sessions(Vec<SessionSummary>),
+    Controlled(ControlRecord),
+    Events(Vec<StoredEvent>),
+    Error(ErrorPayload),
+}
+
+pub fn default_socket_path() -> PathBuf {
+    if let Ok(runtime) = std::env::var("XDG_RUNTIME_DIR") {
+        return PathBuf::from(runtime)
+            .join("winewarden")
+            .join("winewarden.sock");
+    }
+    PathBuf::from("/tmp").join("winewarden.sock")
+}
+
+pub fn default_pid_path() -> PathBuf {
+    if let Ok(runtime) = std::env::var("XDG_RUNTIME_DIR") {
+        return PathBuf::from(runtime)
+            .join("winewarden")
+            .join("winewarden.pid");
+    }
+    PathBuf::from("/tmp").join("winewarden.pid")
+}
+
+pub fn resolve_socket_path() -> PathBuf {
+    if let Ok(value) = std::env::var("WINEWARDEN_SOCKET") {
+        return PathBuf::from(value);
+    }
+    default_socket_path()
+}
+
+pub fn resolve_pid_path() -> PathBuf {
+    if let Ok(value) = std::env::var("WINEWARDEN_PID") {
+        return PathBuf::from(value);
+    }
+    default_pid_path()
+}
+
+/// A framed, handshaken connection to the daemon.
+pub struct IpcClient {
+    reader: BufReader<UnixStream>,
+    writer: BufWriter<UnixStream>,
+    server: Hello,
+    next_id: u64,
+}
+
+impl IpcClient {
+    /// Connects and exchanges hellos; fails with `IpcError::VersionMismatch`
+    /// if the daemon speaks a different protocol version.
+    pub fn connect(socket_path: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(socket_path)
+            .with_context(|| format!("connect to daemon at {}", socket_path.display()))?;
+        let mut writer = BufWriter::new(stream.try_clone()?);
+        let mut reader = BufReader::new(stream);
+
+        write_frame(&mut writer, &ClientFrame::Hello(Hello::current()))?;
+        let server = match read_frame(&mut reader)? {
+            Some(ServerFrame::Welcome(server)) => server,
+            Some(ServerFrame::Rejected { server, error }) => {
+                return Err(match server.check_compatible() {
+                    Err(mismatch) => mismatch.into(),
+                    Ok(()) => anyhow::anyhow!(error.message),
+                })
+            }
+            Some(other) => return Err(IpcError::UnexpectedFrame(format!("{other:?}")).into()),
+            None => return Err(IpcError::Truncated.into()),
+        };
+        server.check_compatible()?;
+
+        Ok(Self {
+            reader,
+            writer,
+            server,
+            next_id: 1,
+        })
+    }
+
+    /// The daemon's hello, including its build version.
+    pub fn server(&self) -> &Hello {
+        &self.server
+    }
+
+    pub fn request(&mut self, request: &WineWardenRequest) -> Result<WineWardenResponse> {
+        let id = self.send(request)?;
+        self.receive(id)
+    }
+
+    /// Turns this connection into an event stream.
+    pub fn subscribe(mut self, request: SubscribeRequest) -> Result<Subscription> {
+        let id = self.send(&WineWardenRequest::Subscribe(request))?;
+        match self.receive(id)? {
+            WineWardenResponse::Subscribed => Ok(Subscription { client: self, id }),
+            WineWardenResponse::Error(error) => Err(anyhow::anyhow!(error.message)),
+            other => Err(IpcError::UnexpectedFrame(format!("{other:?}")).into()),
+        }
+    }
+
+    fn send(&mut self, request: &WineWardenRequest) -> Result<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+        write_frame(
+            &mut self.writer,
+            &ClientFrame::Request {
+                id,
+                request: request.clone(),
+            },
+        )?;
+        Ok(id)
+    }
+
+    fn receive(&mut self, id: u64) -> Result<WineWardenResponse> {
+        match read_frame(&mut self.reader)? {
+            Some(ServerFrame::Response { id: got, response }) if got == id => Ok(response),
+            Some(ServerFrame::Response { id: got, .. }) => {
+                Err(IpcError::IdMismatch { expected: id, got }.into())
+            }
+            Some(ServerFrame::Rejected { error, .. }) => Err(anyhow::anyhow!(error.message)),
+            Some(other) => Err(IpcError::UnexpectedFrame(format!("{other:?}")).into()),
+            None => Err(IpcError::Truncated.into()),
+        }
+    }
+}
+
+/// Events from a `Subscribe` request.
+pub struct Subscription {
+    client: IpcClient,
+    id: u64,
+}
+
+impl Subscription {
+    /// Blocks until the next event; `None` once the daemon closes the stream.
+    pub fn next_event(&mut self) -> Result<Option<SessionEvent>> {
+        match read_frame(&mut self.client.reader)? {
+            Some(ServerFrame::Response {
+                id,
+                response: WineWardenResponse::Event(event),
+            }) if id == self.id => Ok(Some(event)),
+            Some(ServerFrame::Response {
+                response: WineWardenResponse::Error(error),
+                ..
+            }) => Err(anyhow::anyhow!(error.message)),
+            Some(other) => Err(IpcError::UnexpectedFrame(format!("{other:?}")).into()),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Sends one request over a fresh connection.
+pub fn send_request(socket_path: &Path, request: &WineWardenRequest) -> Result<WineWardenResponse> {
+    IpcClient::connect(socket_path)?.request(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+
+    /// Answers one client: the first frame after its hello, the rest after
+    /// its first request (with the request's id filled in).
+    fn serve_once(reply: impl FnOnce(ClientFrame) -> Vec<ServerFrame> + Send + 'static) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("winewarden-ipc-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("test.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = BufWriter::new(stream);
+            let hello: ClientFrame = read_frame(&mut reader).unwrap().unwrap();
+            let mut frames = reply(hello).into_iter();
+            if let Some(first) = frames.next() {
+                write_frame(&mut writer, &first).unwrap();
+            }
+            if let Ok(Some(ClientFrame::Request { id, .. })) = read_frame(&mut reader) {
+                for frame in frames {
+                    let frame = match frame {
+                        ServerFrame::Response { response, .. } => {
+                            ServerFrame::Response { id, response }
+                        }
+                        other => other,
+                    };
+                    write_frame(&mut writer, &frame).unwrap();
+                }
+            }
+        });
+        socket
+    }
+
+    #[test]
+    fn test_client_handshake_and_request() {
+        let socket = serve_once(|hello| {
+            assert!(matches!(hello, ClientFrame::Hello(ref hello) if *hello == Hello::current()));
+            vec![
+                ServerFrame::Welcome(Hello::current()),
+                ServerFrame::Response {
+                    id: 0,
+                    response: WineWardenResponse::Pong,
+                },
+            ]
+        });
+        let mut client = IpcClient::connect(&socket).unwrap();
+        assert_eq!(client.server().build_version, BUILD_VERSION);
+        let response = client.request(&WineWardenRequest::Ping).unwrap();
+        assert!(matches!(response, WineWardenResponse::Pong));
+    }
+
+    #[test]
+    fn test_client_reports_version_mismatch() {
+        let socket = serve_once(|_| {
+            let server = Hello {
+                protocol_version: PROTOCOL_VERSION + 1,
+                build_version: "9.9.9".to_string(),
+            };
+            vec![ServerFrame::Rejected {
+                server,
+                error: ErrorPayload {
+                    message: "version mismatch".to_string(),
+                },
+            }]
+        });
+        let err = IpcClient::connect(&socket).err().unwrap();
+        assert!(matches!(
+            err.downcast_ref::<IpcError>(),
+            Some(IpcError::VersionMismatch { peer_build, .. }) if peer_build == "9.9.9"
+        ));
+    }
+}
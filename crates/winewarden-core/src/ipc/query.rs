@@ -0,0 +1,47 @@
+//! Queries against the daemon's event store.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::decision::{DecisionKind, PolicyDecision};
+use crate::types::{AccessAttempt, AccessKind};
+
+/// Every set field must match; empty lists match anything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventQuery {
+    #[serde(default)]
+    pub session_id: Option<Uuid>,
+    /// SHA-256 of the executable, as in the trust store.
+    #[serde(default)]
+    pub executable_sha256: Option<String>,
+    #[serde(default)]
+    pub since: Option<OffsetDateTime>,
+    #[serde(default)]
+    pub until: Option<OffsetDateTime>,
+    #[serde(default)]
+    pub kinds: Vec<AccessKind>,
+    #[serde(default)]
+    pub decisions: Vec<DecisionKind>,
+    /// Sacred zone label, compared case-insensitively.
+    #[serde(default)]
+    pub zone_label: Option<String>,
+    /// Substring of the path, host or device the event touched.
+    #[serde(default)]
+    pub target_contains: Option<String>,
+    /// Most recent events to return; the daemon caps this.
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+/// A `ReportEvent` with the session and executable it came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredEvent {
+    pub session_id: Uuid,
+    pub executable: PathBuf,
+    pub executable_sha256: String,
+    pub attempt: AccessAttempt,
+    pub decision: PolicyDecision,
+}
@@ -1,18 +1,26 @@
 pub mod config;
+pub mod decision;
+pub mod devices;
 pub mod error;
 pub mod ids;
 pub mod ipc;
+pub mod migrate;
+pub mod network;
+pub mod overlay;
 pub mod paths;
+pub mod store;
 pub mod time;
 pub mod trust;
 pub mod types;
-pub mod store;
+pub mod validate;
 
 pub use config::{Config, ConfigPaths};
 pub use error::WineWardenError;
 pub use ids::{ExecId, PrefixId, RunId};
 pub use ipc::{WineWardenRequest, WineWardenResponse};
-pub use paths::{PathAction, SacredZone};
+pub use paths::{PathAction, SacredZone, ZoneSet};
+pub use store::{ExecutableIdentity, TrustRecord, TrustStore};
 pub use trust::{TrustSignal, TrustTier};
-pub use types::{AccessAttempt, AccessKind, AccessTarget, LiveMonitorConfig, NetworkTarget, RunMetadata};
-pub use store::{TrustStore, TrustRecord, ExecutableIdentity};
+pub use types::{
+    AccessAttempt, AccessKind, AccessTarget, LiveMonitorConfig, NetworkTarget, RunMetadata,
+};
@@ -0,0 +1,404 @@
+//! Config schema migrations.
+//!
+//! Each step rewrites the TOML document from one schema version to the next,
+//! editing it in place so comments and formatting survive. A file without a
+//! `schema_version` key is treated as version 0.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table};
+
+use crate::config::CURRENT_SCHEMA_VERSION;
+
+type MigrationStep = fn(&mut DocumentMut) -> Result<()>;
+
+/// Steps indexed by the version they upgrade from.
+const STEPS: &[(&str, MigrationStep)] = &[
+    ("move [sacred_zones].zones to [[sacred_zones]]", v0_to_v1),
+    (
+        "add a devices rule ahead of the stock \"Sacred devices\" rule",
+        v1_to_v2,
+    ),
+];
+
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub applied: Vec<String>,
+    pub original: String,
+    pub migrated: String,
+}
+
+impl Migration {
+    pub fn is_noop(&self) -> bool {
+        self.from_version == self.to_version
+    }
+
+    /// Line diff between the original and migrated config.
+    pub fn diff(&self, label: &str) -> String {
+        let mut output = format!("--- {label}\n+++ {label} (schema v{})\n", self.to_version);
+        for line in diff_lines(&self.original, &self.migrated) {
+            output.push_str(&line);
+            output.push('\n');
+        }
+        output
+    }
+}
+
+pub fn schema_version(document: &DocumentMut) -> Result<u32> {
+    match document.get("schema_version") {
+        None => Ok(0),
+        Some(item) => item
+            .as_integer()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| anyhow::anyhow!("schema_version must be a non-negative integer")),
+    }
+}
+
+/// Upgrades config source to the current schema without touching disk.
+pub fn migrate_str(contents: &str) -> Result<Migration> {
+    let mut document: DocumentMut = contents.parse().context("parse config TOML")?;
+    let from_version = schema_version(&document)?;
+    if from_version > CURRENT_SCHEMA_VERSION {
+        return Err(anyhow::anyhow!(
+            "config schema version {from_version} is newer than this build supports ({CURRENT_SCHEMA_VERSION})"
+        ));
+    }
+
+    let mut applied = Vec::new();
+    for version in from_version..CURRENT_SCHEMA_VERSION {
+        let (description, step) = STEPS
+            .get(version as usize)
+            .ok_or_else(|| anyhow::anyhow!("no migration from config schema v{version}"))?;
+        step(&mut document).with_context(|| format!("migrate config from schema v{version}"))?;
+        applied.push(format!("v{version} -> v{}: {description}", version + 1));
+    }
+
+    let migrated = if applied.is_empty() {
+        contents.to_string()
+    } else {
+        document["schema_version"] = toml_edit::value(i64::from(CURRENT_SCHEMA_VERSION));
+        document.to_string()
+    };
+    Ok(Migration {
+        from_version,
+        to_version: CURRENT_SCHEMA_VERSION,
+        applied,
+        original: contents.to_string(),
+        migrated,
+    })
+}
+
+/// Rewrites an outdated config file, keeping a backup of the original.
+/// Returns the backup path when the file was changed. The new contents are
+/// renamed into place, so a crash leaves either the old or the new file.
+pub fn upgrade_in_place(path: &Path) -> Result<Option<PathBuf>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("read config at {}", path.display()))?;
+    let migration =
+        migrate_str(&contents).with_context(|| format!("migrate config at {}", path.display()))?;
+    if migration.is_noop() {
+        return Ok(None);
+    }
+
+    // Replace the file a symlinked config points at, not the link.
+    let path =
+        fs::canonicalize(path).with_context(|| format!("resolve config at {}", path.display()))?;
+    let backup = backup_path(&path, migration.from_version);
+    fs::copy(&path, &backup).with_context(|| format!("back up config to {}", backup.display()))?;
+    let temp = path.with_extension("toml.tmp");
+    fs::write(&temp, &migration.migrated)
+        .with_context(|| format!("write config at {}", temp.display()))?;
+    let permissions = fs::metadata(&path)
+        .with_context(|| format!("stat config at {}", path.display()))?
+        .permissions();
+    fs::set_permissions(&temp, permissions)
+        .with_context(|| format!("set permissions on {}", temp.display()))?;
+    fs::rename(&temp, &path).with_context(|| format!("replace config at {}", path.display()))?;
+    Ok(Some(backup))
+}
+
+fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "config.toml".to_string());
+    let mut candidate = path.with_file_name(format!("{name}.v{version}.bak"));
+    let mut counter = 1;
+    while candidate.exists() {
+        candidate = path.with_file_name(format!("{name}.v{version}.bak.{counter}"));
+        counter += 1;
+    }
+    candidate
+}
+
+/// Early configs nested zones as `[sacred_zones]` with a `zones` array.
+fn v0_to_v1(document: &mut DocumentMut) -> Result<()> {
+    let Some(Item::Table(table)) = document.get_mut("sacred_zones") else {
+        return Ok(());
+    };
+    let header = table.decor().prefix().cloned();
+    let zones = table.remove("zones");
+    if !table.is_empty() {
+        let keys: Vec<&str> = table.iter().map(|(key, _)| key).collect();
+        return Err(anyhow::anyhow!(
+            "unexpected keys in legacy [sacred_zones]: {}",
+            keys.join(", ")
+        ));
+    }
+    let mut zones = match zones {
+        Some(item) => item
+            .into_array_of_tables()
+            .map_err(|_| anyhow::anyhow!("legacy sacred_zones.zones must be an array of tables"))?,
+        None => toml_edit::ArrayOfTables::new(),
+    };
+    // Carry the old header's leading comments and spacing over to the first zone.
+    if let (Some(header), Some(first)) = (header, zones.get_mut(0)) {
+        first.decor_mut().set_prefix(header);
+    }
+    document.insert("sacred_zones", Item::ArrayOfTables(zones));
+    Ok(())
+}
+
+/// Device attempts used to be denied outright by the stock `Sacred devices`
+/// rule. Put a `devices` rule right in front of it, so the `[devices]`
+/// classes decide before it but after any device rule the user placed
+/// earlier; rule lists without it are left alone.
+fn v1_to_v2(document: &mut DocumentMut) -> Result<()> {
+    let Some(Item::ArrayOfTables(rules)) = document.get_mut("rules") else {
+        return Ok(());
+    };
+    let then = |rule: &Table| rule.get("then").and_then(Item::as_str).map(str::to_string);
+    if rules
+        .iter()
+        .any(|rule| then(rule).as_deref() == Some("devices"))
+    {
+        return Ok(());
+    }
+    let is_stock = |rule: &Table| {
+        rule.get("label").and_then(Item::as_str) == Some("Sacred devices")
+            && then(rule).as_deref() == Some("deny")
+    };
+    let Some(index) = rules.iter().position(is_stock) else {
+        return Ok(());
+    };
+
+    let mut tables: Vec<Table> = rules.iter().cloned().collect();
+    let mut devices = Table::new();
+    devices.insert("label", toml_edit::value("Devices"));
+    devices.insert("then", toml_edit::value("devices"));
+    // The new rule takes over the comments above the one it now precedes,
+    // and its position; tables sharing a position keep their array order.
+    let next = &mut tables[index];
+    devices
+        .decor_mut()
+        .set_prefix(next.decor().prefix().cloned().unwrap_or_default());
+    next.decor_mut().set_prefix("\n");
+    if let Some(position) = next.position() {
+        devices.set_position(position);
+    }
+    tables.insert(index, devices);
+
+    let mut rebuilt = ArrayOfTables::new();
+    for table in tables {
+        rebuilt.push(table);
+    }
+    *rules = rebuilt;
+    Ok(())
+}
+
+/// Minimal LCS line diff; config files are small enough for the quadratic table.
+fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut output = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            output.push(format!(" {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            output.push(format!("-{}", old[i]));
+            i += 1;
+        } else {
+            output.push(format!("+{}", new[j]));
+            j += 1;
+        }
+    }
+    output.extend(old[i..].iter().map(|line| format!("-{line}")));
+    output.extend(new[j..].iter().map(|line| format!("+{line}")));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    const LEGACY: &str = r#"# my config
+[trust]
+default_tier = "yellow"
+
+[sacred_zones]
+[[sacred_zones.zones]]
+label = "SSH keys"
+path = "${HOME}/.ssh"
+action = "deny"
+
+[[sacred_zones.zones]]
+label = "Home directory"
+path = "${HOME}"
+action = "redirect"
+redirect_to = "${DATA_DIR}/virtual/home"
+"#;
+
+    #[test]
+    fn test_legacy_zones_are_migrated() {
+        let migration = migrate_str(LEGACY).unwrap();
+        assert_eq!(migration.from_version, 0);
+        assert_eq!(migration.applied.len(), 2);
+        assert!(migration
+            .migrated
+            .starts_with(&format!("schema_version = {CURRENT_SCHEMA_VERSION}\n")));
+        assert!(migration.migrated.contains("# my config"));
+
+        let config = Config::from_toml_str(&migration.migrated).unwrap();
+        assert_eq!(config.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(config.sacred_zones.len(), 2);
+        assert_eq!(config.sacred_zones[1].label, "Home directory");
+    }
+
+    #[test]
+    fn test_current_config_is_untouched() {
+        let contents = Config::default_config().to_toml_string().unwrap();
+        let migration = migrate_str(&contents).unwrap();
+        assert!(migration.is_noop());
+        assert_eq!(migration.migrated, contents);
+    }
+
+    #[test]
+    fn test_every_version_has_a_step() {
+        assert_eq!(STEPS.len(), CURRENT_SCHEMA_VERSION as usize);
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        assert!(migrate_str("schema_version = 99\n").is_err());
+    }
+
+    #[test]
+    fn test_partial_config_loads_with_defaults() {
+        let config = Config::from_toml_str("[trust]\ndefault_tier = \"red\"\n").unwrap();
+        assert_eq!(config.process.max_child_processes, 50);
+        assert!(!config.sacred_zones.is_empty());
+    }
+
+    #[test]
+    fn test_upgrade_in_place_keeps_backup() {
+        let dir = std::env::temp_dir().join(format!("winewarden-migrate-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, LEGACY).unwrap();
+
+        let backup = upgrade_in_place(&path).unwrap().expect("backup");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), LEGACY);
+        let upgraded = fs::read_to_string(&path).unwrap();
+        assert!(upgraded.starts_with(&format!("schema_version = {CURRENT_SCHEMA_VERSION}")));
+        assert!(upgrade_in_place(&path).unwrap().is_none());
+        assert!(!path.with_extension("toml.tmp").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_devices_rule_is_added() {
+        let v1 = r#"schema_version = 1
+
+[[rules]]
+label = "Sacred zones"
+then = "sacred_zones"
+
+# Nothing touches devices
+[[rules]]
+label = "Sacred devices"
+when = { kind = ["device"] }
+then = "deny"
+
+[network]
+mode = "enforce"
+
+[[rules]]
+label = "Everything else"
+then = "deny"
+"#;
+        let migration = migrate_str(v1).unwrap();
+        assert_eq!(migration.applied.len(), 1);
+        assert!(migration.migrated.contains(
+            "# Nothing touches devices\n[[rules]]\nlabel = \"Devices\"\nthen = \"devices\"\n\n[[rules]]\nlabel = \"Sacred devices\""
+        ));
+        let config = Config::from_toml_str(&migration.migrated).unwrap();
+        let labels: Vec<&str> = config
+            .rules
+            .iter()
+            .map(|rule| rule.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                "Sacred zones",
+                "Devices",
+                "Sacred devices",
+                "Everything else"
+            ]
+        );
+
+        let custom = "schema_version = 1\n[[rules]]\nlabel = \"All\"\nthen = \"allow\"\n";
+        let migration = migrate_str(custom).unwrap();
+        assert!(!migration.migrated.contains("Devices"));
+
+        // A device rule of the user's own keeps deciding first
+        let own = r#"schema_version = 1
+
+[[rules]]
+label = "No cameras"
+when = { kind = ["device"], path = ["/dev/video*"] }
+then = "deny"
+
+[[rules]]
+label = "Sacred devices"
+when = { kind = ["device"] }
+then = "deny"
+"#;
+        let config = Config::from_toml_str(&migrate_str(own).unwrap().migrated).unwrap();
+        let labels: Vec<&str> = config
+            .rules
+            .iter()
+            .map(|rule| rule.label.as_str())
+            .collect();
+        assert_eq!(labels, vec!["No cameras", "Devices", "Sacred devices"]);
+    }
+
+    #[test]
+    fn test_diff_marks_changes() {
+        let migration = migrate_str(LEGACY).unwrap();
+        let diff = migration.diff("config.toml");
+        assert!(diff.contains(&format!("+schema_version = {CURRENT_SCHEMA_VERSION}")));
+        assert!(diff.contains("-[[sacred_zones.zones]]"));
+        assert!(diff.contains("+[[sacred_zones]]"));
+    }
+}
@@ -0,0 +1,439 @@
+//! Per-game configuration overlays.
+//!
+//! Overlays are TOML files stored next to the base config (in `games/`).
+//! Each one carries a `[match]` table and any subset of the regular config
+//! sections. Matching overlays are merged on top of the base config, least
+//! specific first: path globs, then Steam app ids, then executable hashes.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use crate::config::Config;
+use crate::error::WineWardenError;
+use crate::store::ExecutableIdentity;
+use crate::validate;
+
+pub const BASE_LAYER: &str = "base";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OverlayMatch {
+    pub sha256: Option<String>,
+    pub path_glob: Option<String>,
+    pub steam_app_id: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigOverlay {
+    pub name: String,
+    pub source: PathBuf,
+    pub matcher: OverlayMatch,
+    path_glob: Option<GlobMatcher>,
+    patch: Table,
+}
+
+/// What an overlay is matched against.
+#[derive(Debug, Clone)]
+pub struct OverlayTarget<'a> {
+    pub identity: &'a ExecutableIdentity,
+    pub steam_app_id: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigLayer {
+    pub name: String,
+    pub path: Option<PathBuf>,
+}
+
+/// The merged config for one executable, with the layer each value came from.
+#[derive(Debug, Clone)]
+pub struct EffectiveConfig {
+    pub config: Config,
+    pub layers: Vec<ConfigLayer>,
+    pub sources: BTreeMap<String, String>,
+}
+
+impl EffectiveConfig {
+    /// The base config on its own, with every value attributed to it.
+    pub fn from_base(base: Config, base_path: Option<&Path>) -> Result<Self> {
+        let value = Value::try_from(&base).context("convert base config to TOML")?;
+        let mut sources = BTreeMap::new();
+        record_sources(&value, "", BASE_LAYER, &mut sources);
+        Ok(Self {
+            config: base,
+            layers: vec![ConfigLayer {
+                name: BASE_LAYER.to_string(),
+                path: base_path.map(Path::to_path_buf),
+            }],
+            sources,
+        })
+    }
+
+    /// Validates the merged result, naming the layers it was built from.
+    pub fn validate(&self) -> Result<(), WineWardenError> {
+        let names: Vec<&str> = self
+            .layers
+            .iter()
+            .map(|layer| layer.name.as_str())
+            .collect();
+        validate::ensure_valid(
+            &validate::validate_config(&self.config),
+            &format!("effective config ({})", names.join(" + ")),
+        )
+    }
+}
+
+impl ConfigOverlay {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("read overlay {}", path.display()))?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "overlay".to_string());
+        Self::from_toml_str(&name, path, &contents)
+    }
+
+    pub fn from_toml_str(name: &str, source: &Path, contents: &str) -> Result<Self> {
+        let mut patch: Table = toml::from_str(contents)
+            .with_context(|| format!("parse overlay TOML {}", source.display()))?;
+        let matcher: OverlayMatch = match patch.remove("match") {
+            Some(value) => value
+                .try_into()
+                .with_context(|| format!("parse [match] in overlay {}", source.display()))?,
+            None => OverlayMatch::default(),
+        };
+        if matcher.sha256.is_none() && matcher.path_glob.is_none() && matcher.steam_app_id.is_none()
+        {
+            return Err(anyhow::anyhow!(
+                "overlay {} has no [match] criteria (sha256, path_glob or steam_app_id)",
+                source.display()
+            ));
+        }
+        let path_glob = match &matcher.path_glob {
+            Some(pattern) => Some(
+                GlobBuilder::new(pattern)
+                    .literal_separator(true)
+                    .build()
+                    .with_context(|| format!("parse path_glob in overlay {}", source.display()))?
+                    .compile_matcher(),
+            ),
+            None => None,
+        };
+        Ok(Self {
+            name: name.to_string(),
+            source: source.to_path_buf(),
+            matcher,
+            path_glob,
+            patch,
+        })
+    }
+
+    /// Every criterion present in `[match]` must hold.
+    pub fn matches(&self, target: &OverlayTarget<'_>) -> bool {
+        if let Some(sha256) = &self.matcher.sha256 {
+            if !sha256.eq_ignore_ascii_case(&target.identity.sha256) {
+                return false;
+            }
+        }
+        if let Some(glob) = &self.path_glob {
+            if !glob.is_match(&target.identity.path) {
+                return false;
+            }
+        }
+        if let Some(app_id) = self.matcher.steam_app_id {
+            if target.steam_app_id != Some(app_id) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Higher values are merged later and therefore win.
+    fn specificity(&self) -> u8 {
+        if self.matcher.sha256.is_some() {
+            3
+        } else if self.matcher.steam_app_id.is_some() {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn layer_name(&self) -> String {
+        format!("game:{}", self.name)
+    }
+}
+
+/// Overlays live in a `games/` directory beside the base config file.
+pub fn overlay_dir(config_path: &Path) -> PathBuf {
+    config_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("games")
+}
+
+pub fn load_overlays(dir: &Path) -> Result<Vec<ConfigOverlay>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("read overlay dir {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths.iter().map(|path| ConfigOverlay::load(path)).collect()
+}
+
+/// Reads the Steam app id the launcher exports to the game environment.
+pub fn steam_app_id_from_env() -> Option<u32> {
+    ["SteamAppId", "STEAM_COMPAT_APP_ID"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .find_map(|value| value.trim().parse().ok())
+}
+
+/// Loads the overlays beside `base_path` and merges the matching ones.
+pub fn resolve_effective(
+    base: Config,
+    base_path: &Path,
+    target: &OverlayTarget<'_>,
+) -> Result<EffectiveConfig> {
+    let overlays = load_overlays(&overlay_dir(base_path))?;
+    merge_overlays(base, Some(base_path), &overlays, target)
+}
+
+pub fn merge_overlays(
+    base: Config,
+    base_path: Option<&Path>,
+    overlays: &[ConfigOverlay],
+    target: &OverlayTarget<'_>,
+) -> Result<EffectiveConfig> {
+    let mut matching: Vec<&ConfigOverlay> = overlays
+        .iter()
+        .filter(|overlay| overlay.matches(target))
+        .collect();
+    matching.sort_by_key(|overlay| overlay.specificity());
+    merge(base, base_path, matching)
+}
+
+/// Checks each overlay merged on its own over `base`, whatever it
+/// matches, so a broken overlay is caught before any game picks it up.
+pub fn validate_overlays(
+    base: &Config,
+    base_path: Option<&Path>,
+    overlays: &[ConfigOverlay],
+) -> Result<()> {
+    for overlay in overlays {
+        merge(base.clone(), base_path, vec![overlay])?.validate()?;
+    }
+    Ok(())
+}
+
+fn merge(
+    base: Config,
+    base_path: Option<&Path>,
+    matching: Vec<&ConfigOverlay>,
+) -> Result<EffectiveConfig> {
+    if matching.is_empty() {
+        return EffectiveConfig::from_base(base, base_path);
+    }
+
+    let mut merged = Value::try_from(&base).context("convert base config to TOML")?;
+    let EffectiveConfig {
+        mut layers,
+        mut sources,
+        ..
+    } = EffectiveConfig::from_base(base, base_path)?;
+
+    for overlay in matching {
+        let layer = overlay.layer_name();
+        if let Value::Table(table) = &mut merged {
+            merge_table(table, &overlay.patch, "", &layer, &mut sources);
+        }
+        layers.push(ConfigLayer {
+            name: layer,
+            path: Some(overlay.source.clone()),
+        });
+    }
+
+    let config: Config = merged.try_into().with_context(|| {
+        let names: Vec<&str> = layers.iter().map(|layer| layer.name.as_str()).collect();
+        format!("merge config layers {}", names.join(", "))
+    })?;
+    Ok(EffectiveConfig {
+        config,
+        layers,
+        sources,
+    })
+}
+
+/// Tables merge key by key; scalars and arrays are replaced wholesale.
+fn merge_table(
+    base: &mut Table,
+    patch: &Table,
+    prefix: &str,
+    layer: &str,
+    sources: &mut BTreeMap<String, String>,
+) {
+    for (key, value) in patch {
+        let path = join_key(prefix, key);
+        match (base.get_mut(key), value) {
+            (Some(Value::Table(existing)), Value::Table(incoming)) => {
+                merge_table(existing, incoming, &path, layer, sources);
+            }
+            _ => {
+                sources.retain(|existing, _| !is_within(existing, &path));
+                record_sources(value, &path, layer, sources);
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn record_sources(value: &Value, path: &str, layer: &str, sources: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                record_sources(value, &join_key(path, key), layer, sources);
+            }
+        }
+        _ => {
+            sources.insert(path.to_string(), layer.to_string());
+        }
+    }
+}
+
+fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{prefix}.{key}")
+    }
+}
+
+fn is_within(candidate: &str, path: &str) -> bool {
+    candidate == path
+        || candidate
+            .strip_prefix(path)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NetworkMode;
+    use crate::trust::TrustTier;
+
+    fn identity(path: &str, sha256: &str) -> ExecutableIdentity {
+        ExecutableIdentity {
+            path: PathBuf::from(path),
+            sha256: sha256.to_string(),
+        }
+    }
+
+    fn overlay(name: &str, contents: &str) -> ConfigOverlay {
+        ConfigOverlay::from_toml_str(name, Path::new(name), contents).unwrap()
+    }
+
+    #[test]
+    fn test_overlay_requires_match_criteria() {
+        let result = ConfigOverlay::from_toml_str(
+            "empty",
+            Path::new("empty.toml"),
+            "[trust]\ndefault_tier = \"green\"\n",
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_overlay_matching() {
+        let exe = identity("/games/steamapps/common/Foo/foo.exe", "ABC123");
+        let target = OverlayTarget {
+            identity: &exe,
+            steam_app_id: Some(42),
+        };
+
+        assert!(overlay("hash", "[match]\nsha256 = \"abc123\"\n").matches(&target));
+        assert!(overlay("glob", "[match]\npath_glob = \"/games/**/Foo/*.exe\"\n").matches(&target));
+        assert!(!overlay("glob", "[match]\npath_glob = \"/games/*.exe\"\n").matches(&target));
+        assert!(overlay("steam", "[match]\nsteam_app_id = 42\n").matches(&target));
+        assert!(
+            !overlay("both", "[match]\nsteam_app_id = 42\nsha256 = \"ffff\"\n").matches(&target)
+        );
+    }
+
+    #[test]
+    fn test_merge_tracks_sources() {
+        let exe = identity("/games/foo.exe", "abc");
+        let target = OverlayTarget {
+            identity: &exe,
+            steam_app_id: None,
+        };
+        let overlays = vec![
+            overlay(
+                "hash",
+                "[match]\nsha256 = \"abc\"\n[trust]\ndefault_tier = \"green\"\n",
+            ),
+            overlay(
+                "glob",
+                "[match]\npath_glob = \"/games/*\"\n[trust]\ndefault_tier = \"red\"\n[network]\nmode = \"permissive\"\n",
+            ),
+        ];
+
+        let effective = merge_overlays(Config::default_config(), None, &overlays, &target).unwrap();
+
+        // The hash overlay is more specific than the glob, so it wins.
+        assert_eq!(effective.config.trust.default_tier, TrustTier::Green);
+        assert!(matches!(
+            effective.config.network.mode,
+            NetworkMode::Permissive
+        ));
+        assert_eq!(effective.sources["trust.default_tier"], "game:hash");
+        assert_eq!(effective.sources["network.mode"], "game:glob");
+        assert_eq!(effective.sources["trust.auto_promote"], BASE_LAYER);
+        assert_eq!(effective.layers.len(), 3);
+    }
+
+    #[test]
+    fn test_arrays_replace() {
+        let exe = identity("/games/foo.exe", "abc");
+        let target = OverlayTarget {
+            identity: &exe,
+            steam_app_id: None,
+        };
+        let overlays = vec![overlay(
+            "zones",
+            "[match]\nsha256 = \"abc\"\n[[sacred_zones]]\nlabel = \"Saves\"\npath = \"${HOME}/Saves\"\naction = \"allow\"\n",
+        )];
+
+        let effective = merge_overlays(Config::default_config(), None, &overlays, &target).unwrap();
+        assert_eq!(effective.config.sacred_zones.len(), 1);
+        assert_eq!(effective.sources["sacred_zones"], "game:zones");
+    }
+
+    #[test]
+    fn test_validate_overlays_ignores_matching() {
+        let base = Config::default_config();
+        let good = overlay(
+            "good",
+            "[match]\nsha256 = \"abc\"\n[process]\nmax_child_processes = 4\n",
+        );
+        let bad = overlay(
+            "bad",
+            "[match]\nsteam_app_id = 7\n[process]\nmax_child_processes = 0\n",
+        );
+
+        assert!(validate_overlays(&base, None, std::slice::from_ref(&good)).is_ok());
+        let err = validate_overlays(&base, None, &[good, bad]).unwrap_err();
+        assert!(format!("{err:#}").contains("game:bad"));
+    }
+}
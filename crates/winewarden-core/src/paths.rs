@@ -1,9 +1,13 @@
+use std::cmp::Reverse;
 use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use globset::{GlobBuilder, GlobMatcher, GlobSet, GlobSetBuilder};
+use regex::RegexSet;
 use serde::{Deserialize, Serialize};
 
-use crate::config::{ConfigPaths, SacredZoneConfig};
+use crate::config::{ConfigPaths, SacredZoneConfig, ZoneActions};
+use crate::types::AccessKind;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -14,40 +18,429 @@ pub enum PathAction {
     Virtualize,
 }
 
+/// One compiled `[[sacred_zones]]` entry. Matching happens in `ZoneSet`.
 #[derive(Debug, Clone)]
 pub struct SacredZone {
     pub label: String,
-    pub path: PathBuf,
+    /// The configured path with placeholders expanded.
+    pub pattern: String,
     pub action: PathAction,
+    pub actions: ZoneActions,
     pub redirect_to: Option<PathBuf>,
+    /// Path components of `pattern` before the first wildcard, then the
+    /// characters that are not wildcards; the zone with more is the more
+    /// specific.
+    specificity: (usize, usize),
 }
 
 impl SacredZone {
-    pub fn from_config(config: &SacredZoneConfig, paths: &ConfigPaths) -> Result<Self> {
-        let base_path = expand_path_template(&config.path, paths)?;
-        let redirect_to = match &config.redirect_to {
-            Some(value) => Some(expand_path_template(value, paths)?),
-            None => None,
-        };
+    pub fn action_for(&self, kind: AccessKind) -> PathAction {
+        self.actions.for_kind(kind, self.action)
+    }
+}
+
+/// Sacred zones compiled into one glob set and one regex set, so a lookup
+/// costs one pass per path component however many zones there are.
+///
+/// A zone covers every path its pattern matches and everything below them.
+/// When several zones cover a path the most specific wins: the pattern with
+/// the deeper literal directory before its first wildcard, then the one with
+/// more literal characters, then the one matching deeper along the path,
+/// then the zone listed first. A broad `${HOME}/**` or `${HOME}/**/*.txt`
+/// matches a file under `~/.ssh` itself, yet `${HOME}/.ssh` still wins.
+#[derive(Debug, Clone, Default)]
+pub struct ZoneSet {
+    zones: Vec<SacredZone>,
+    globs: GlobSet,
+    /// Zone index for each glob in `globs`.
+    glob_zones: Vec<usize>,
+    regexes: RegexSet,
+    regex_zones: Vec<usize>,
+}
+
+impl ZoneSet {
+    pub fn compile(configs: &[SacredZoneConfig], paths: &ConfigPaths) -> Result<Self> {
+        let mut zones = Vec::with_capacity(configs.len());
+        let mut globs = GlobSetBuilder::new();
+        let mut glob_zones = Vec::new();
+        let mut regexes = Vec::new();
+        let mut regex_zones = Vec::new();
+
+        for (index, config) in configs.iter().enumerate() {
+            let template = match config.path.trim_end_matches('/') {
+                "" => "/",
+                trimmed => trimmed,
+            };
+            let pattern = expand_path_template(template, paths)?
+                .to_string_lossy()
+                .into_owned();
+            let specificity = if config.regex {
+                let regex = expand_template(template, paths, regex::escape);
+                regexes.push(format!("^(?:{regex})$"));
+                regex_zones.push(index);
+                let literal_len = pattern.chars().filter(|c| !REGEX_META.contains(*c)).count();
+                (literal_depth(&pattern, is_literal_regex), literal_len)
+            } else {
+                let glob = GlobBuilder::new(&expand_template(template, paths, globset::escape))
+                    .literal_separator(true)
+                    .build()
+                    .with_context(|| format!("parse path of sacred zone \"{}\"", config.label))?;
+                globs.add(glob);
+                glob_zones.push(index);
+                let literal_len = pattern.chars().filter(|c| !GLOB_META.contains(*c)).count();
+                (literal_depth(&pattern, is_literal_glob), literal_len)
+            };
+            let redirect_to = match &config.redirect_to {
+                Some(value) => Some(expand_path_template(value, paths)?),
+                None => None,
+            };
+            zones.push(SacredZone {
+                label: config.label.clone(),
+                pattern,
+                action: config.action,
+                actions: config.actions,
+                redirect_to,
+                specificity,
+            });
+        }
+
         Ok(Self {
-            label: config.label.clone(),
-            path: base_path,
-            action: config.action,
-            redirect_to,
+            zones,
+            globs: globs.build().context("compile sacred zone globs")?,
+            glob_zones,
+            regexes: RegexSet::new(&regexes).context("compile sacred zone regexes")?,
+            regex_zones,
         })
     }
 
-    pub fn matches(&self, candidate: &Path) -> bool {
-        candidate.starts_with(&self.path)
+    pub fn zones(&self) -> &[SacredZone] {
+        &self.zones
+    }
+
+    /// The most specific zone covering `path`, if any. Every ancestor is
+    /// checked, since a zone on a parent can be more specific than one
+    /// matching the path itself.
+    pub fn find(&self, path: &Path) -> Option<&SacredZone> {
+        let mut best = None;
+        for candidate in path
+            .ancestors()
+            .filter(|candidate| !candidate.as_os_str().is_empty())
+        {
+            let depth = candidate.components().count();
+            let by_glob = self
+                .globs
+                .matches(candidate)
+                .into_iter()
+                .map(|index| self.glob_zones[index]);
+            let by_regex = self
+                .regexes
+                .matches(&candidate.to_string_lossy())
+                .into_iter()
+                .map(|index| self.regex_zones[index])
+                .collect::<Vec<_>>();
+            for index in by_glob.chain(by_regex) {
+                let (literal_depth, literal_len) = self.zones[index].specificity;
+                let rank = (Reverse((literal_depth, literal_len, depth)), index);
+                if best.is_none_or(|best| rank < best) {
+                    best = Some(rank);
+                }
+            }
+        }
+        best.map(|(_, index)| &self.zones[index])
+    }
+}
+
+const GLOB_META: &str = "*?[]{}";
+const REGEX_META: &str = "\\.^$*+?()[]{}|";
+
+/// How many components lead `pattern` before one that is not literal.
+fn literal_depth(pattern: &str, is_literal: fn(&str) -> bool) -> usize {
+    pattern
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .take_while(|component| is_literal(component))
+        .count()
+}
+
+fn is_literal_glob(component: &str) -> bool {
+    !component.contains(|c| GLOB_META.contains(c))
+}
+
+/// Escaped punctuation such as `\.` is literal; classes such as `\d` are not.
+fn is_literal_regex(component: &str) -> bool {
+    let mut chars = component.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if !chars
+                .next()
+                .is_some_and(|escaped| escaped.is_ascii_punctuation())
+            {
+                return false;
+            }
+        } else if REGEX_META.contains(c) {
+            return false;
+        }
     }
+    true
 }
 
 pub fn expand_path_template(template: &str, paths: &ConfigPaths) -> Result<PathBuf> {
+    Ok(PathBuf::from(expand_template(
+        template,
+        paths,
+        str::to_string,
+    )))
+}
+
+/// Replaces the placeholders, passing each expanded directory through
+/// `escape` so it is matched literally by globs and regexes.
+fn expand_template(template: &str, paths: &ConfigPaths, escape: impl Fn(&str) -> String) -> String {
     let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
-    let replaced = template
-        .replace("${HOME}", &home_dir)
-        .replace("${DATA_DIR}", &paths.data_dir.to_string_lossy())
-        .replace("${CONFIG_DIR}", &paths.config_path.parent().unwrap_or(&paths.data_dir).to_string_lossy());
-    let path = PathBuf::from(replaced);
-    Ok(path)
+    let config_dir = paths.config_path.parent().unwrap_or(&paths.data_dir);
+    template
+        .replace("${HOME}", &escape(&home_dir))
+        .replace("${DATA_DIR}", &escape(&paths.data_dir.to_string_lossy()))
+        .replace("${CONFIG_DIR}", &escape(&config_dir.to_string_lossy()))
+}
+
+/// Stands for the session's prefix in path patterns.
+pub const PREFIX_PLACEHOLDER: &str = "${PREFIX}";
+
+/// A path glob from the config. `*` stays within one path component and
+/// `**` crosses them. `${PREFIX}` is resolved per session, the other
+/// placeholders when the pattern is compiled.
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    template: String,
+    prefix_relative: bool,
+    matcher: GlobMatcher,
+}
+
+impl PathPattern {
+    pub fn compile(template: &str, paths: &ConfigPaths) -> Result<Self> {
+        let (prefix_relative, rest) = match template.strip_prefix(PREFIX_PLACEHOLDER) {
+            Some(rest) => (true, rest.trim_start_matches('/')),
+            None => (false, template),
+        };
+        if rest.contains(PREFIX_PLACEHOLDER) {
+            return Err(anyhow!(
+                "${{PREFIX}} can only start a path pattern: {template}"
+            ));
+        }
+        let matcher = GlobBuilder::new(&expand_template(rest, paths, globset::escape))
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("parse path pattern {template}"))?
+            .compile_matcher();
+        Ok(Self {
+            template: template.to_string(),
+            prefix_relative,
+            matcher,
+        })
+    }
+
+    /// The pattern as written in the config.
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    pub fn matches(&self, candidate: &Path, prefix_root: &Path) -> bool {
+        if !self.prefix_relative {
+            return self.matcher.is_match(candidate);
+        }
+        match candidate.strip_prefix(prefix_root) {
+            Ok(relative) => self.matcher.is_match(relative),
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths() -> ConfigPaths {
+        ConfigPaths::under(Path::new("/srv/winewarden"))
+    }
+
+    fn zone(label: &str, path: &str, action: PathAction, regex: bool) -> SacredZoneConfig {
+        SacredZoneConfig {
+            label: label.to_string(),
+            path: path.to_string(),
+            action,
+            redirect_to: None,
+            actions: ZoneActions::default(),
+            regex,
+        }
+    }
+
+    fn label_for<'a>(zones: &'a ZoneSet, path: &str) -> Option<&'a str> {
+        zones.find(Path::new(path)).map(|zone| zone.label.as_str())
+    }
+
+    #[test]
+    fn test_most_specific_zone_wins() {
+        let zones = ZoneSet::compile(
+            &[
+                zone("Home", "/home/me", PathAction::Redirect, false),
+                zone(
+                    "Password stores",
+                    "/home/me/**/*.kdbx",
+                    PathAction::Deny,
+                    false,
+                ),
+                zone("SSH keys", "/home/me/.ssh/", PathAction::Deny, false),
+                zone(
+                    "Cookies",
+                    "/home/me/.mozilla/*/cookies.sqlite",
+                    PathAction::Deny,
+                    false,
+                ),
+                zone(
+                    "Any sqlite",
+                    "/home/me/**/*.sqlite",
+                    PathAction::Allow,
+                    false,
+                ),
+                zone(
+                    "Wallets",
+                    r"/home/me/\.(bitcoin|monero)/wallet.*",
+                    PathAction::Deny,
+                    true,
+                ),
+            ],
+            &paths(),
+        )
+        .unwrap();
+
+        assert_eq!(label_for(&zones, "/home/me/notes.txt"), Some("Home"));
+        assert_eq!(label_for(&zones, "/home/me"), Some("Home"));
+        assert_eq!(label_for(&zones, "/home/meow"), None);
+        // The zone with the deeper literal directory wins, whatever the config
+        // order and even when the other matches the path itself.
+        assert_eq!(
+            label_for(&zones, "/home/me/.ssh/id_ed25519"),
+            Some("SSH keys")
+        );
+        assert_eq!(
+            label_for(&zones, "/home/me/vault.kdbx"),
+            Some("Password stores")
+        );
+        assert_eq!(
+            label_for(&zones, "/home/me/docs/old/vault.kdbx"),
+            Some("Password stores")
+        );
+        // Both match the file; the one with more literal characters wins.
+        assert_eq!(
+            label_for(&zones, "/home/me/.mozilla/abc.default/cookies.sqlite"),
+            Some("Cookies")
+        );
+        assert_eq!(
+            label_for(&zones, "/home/me/.mozilla/abc.default/places.sqlite"),
+            Some("Any sqlite")
+        );
+        // `*` does not cross directories.
+        assert_eq!(
+            label_for(&zones, "/home/me/.mozilla/a/b/cookies.sqlite"),
+            Some("Any sqlite")
+        );
+        // Regexes match the whole path, and zones cover what lies below.
+        assert_eq!(
+            label_for(&zones, "/home/me/.monero/wallet.keys"),
+            Some("Wallets")
+        );
+        assert_eq!(
+            label_for(&zones, "/home/me/.bitcoin/wallets/main.dat"),
+            Some("Wallets")
+        );
+        assert_eq!(
+            label_for(&zones, "/home/me/.bitcoin/blocks/blk0.dat"),
+            Some("Home")
+        );
+    }
+
+    #[test]
+    fn test_broad_glob_loses_to_directory_zone() {
+        let zones = ZoneSet::compile(
+            &[
+                zone(
+                    "Everything at home",
+                    "/home/me/**",
+                    PathAction::Redirect,
+                    false,
+                ),
+                zone("SSH keys", "/home/me/.ssh", PathAction::Deny, false),
+                zone("Any key", "**/id_*", PathAction::Allow, false),
+            ],
+            &paths(),
+        )
+        .unwrap();
+        // `/home/me/**` matches the key file itself, `/home/me/.ssh` only
+        // its directory
+        assert_eq!(label_for(&zones, "/home/me/.ssh/id_rsa"), Some("SSH keys"));
+        assert_eq!(label_for(&zones, "/home/me/.ssh"), Some("SSH keys"));
+        assert_eq!(
+            label_for(&zones, "/home/me/games/save.dat"),
+            Some("Everything at home")
+        );
+        assert_eq!(label_for(&zones, "/srv/id_rsa"), Some("Any key"));
+
+        // A suffix glob has more literal characters than `.ssh`, but a
+        // shallower literal directory
+        let zones = ZoneSet::compile(
+            &[
+                zone("Text files", "/home/me/**/*.txt", PathAction::Allow, false),
+                zone("SSH keys", "/home/me/.ssh", PathAction::Deny, false),
+                zone("GnuPG", r"/home/me/\.gnupg", PathAction::Deny, true),
+            ],
+            &paths(),
+        )
+        .unwrap();
+        assert_eq!(
+            label_for(&zones, "/home/me/.ssh/notes.txt"),
+            Some("SSH keys")
+        );
+        assert_eq!(
+            label_for(&zones, "/home/me/.gnupg/notes.txt"),
+            Some("GnuPG")
+        );
+        assert_eq!(
+            label_for(&zones, "/home/me/docs/notes.txt"),
+            Some("Text files")
+        );
+
+        // Equally literal patterns: the deeper match wins
+        let zones = ZoneSet::compile(
+            &[
+                zone("Parent", "/data/a?", PathAction::Redirect, false),
+                zone("Child", "/data/*/?", PathAction::Deny, false),
+            ],
+            &paths(),
+        )
+        .unwrap();
+        assert_eq!(label_for(&zones, "/data/ab/c"), Some("Child"));
+        assert_eq!(label_for(&zones, "/data/ab"), Some("Parent"));
+    }
+
+    #[test]
+    fn test_placeholders_match_literally() {
+        let zones = ZoneSet::compile(
+            &[
+                zone("Data", "${DATA_DIR}/virtual", PathAction::Allow, false),
+                zone("Config", "${CONFIG_DIR}/.*\\.toml", PathAction::Deny, true),
+            ],
+            &paths(),
+        )
+        .unwrap();
+        assert_eq!(
+            label_for(&zones, "/srv/winewarden/data/virtual/home/x"),
+            Some("Data")
+        );
+        assert_eq!(
+            label_for(&zones, "/srv/winewarden/config.toml"),
+            Some("Config")
+        );
+        assert_eq!(label_for(&zones, "/srv/winewardenXconfig.toml"), None);
+        assert_eq!(zones.zones()[0].pattern, "/srv/winewarden/data/virtual");
+    }
 }
@@ -0,0 +1,147 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+/// Hosts and process names remembered per executable, at most.
+const MAX_KNOWN_NAMES: usize = 512;
+/// End-of-run scores remembered per executable.
+const MAX_SCORES: usize = 20;
+/// New hosts and process names learned from a clean run that departed from
+/// the baseline, at most.
+const MAX_LEARNED_FROM_ANOMALOUS: usize = 5;
+
+/// What one run did, as far as baselines are concerned.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunBehavior {
+    pub destinations: BTreeSet<String>,
+    pub child_processes: BTreeSet<String>,
+    pub file_writes: u32,
+    pub dns_queries: u32,
+}
+
+/// How a finished run went, for the executable's trust record.
+#[derive(Debug, Clone, Default)]
+pub struct RunSummary {
+    pub score: u32,
+    /// Only clean runs are learned into the baseline.
+    pub clean: bool,
+    /// The run departed from the baseline; only a few of its new names
+    /// are learned.
+    pub anomalous: bool,
+    pub behavior: RunBehavior,
+}
+
+/// What an executable usually does, learned from its clean runs and kept
+/// with its trust record.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BehaviorBaseline {
+    /// Clean runs learned from.
+    pub runs: u32,
+    pub destinations: BTreeSet<String>,
+    pub child_processes: BTreeSet<String>,
+    /// Mean file writes per run.
+    pub file_writes: f64,
+    /// Mean DNS queries per run.
+    pub dns_queries: f64,
+    /// End-of-run trust scores of recent runs, clean or not, oldest first.
+    pub scores: Vec<u32>,
+}
+
+impl BehaviorBaseline {
+    pub fn record(&mut self, run: &RunSummary) {
+        if self.scores.len() >= MAX_SCORES {
+            self.scores.remove(0);
+        }
+        self.scores.push(run.score);
+        if run.clean && run.anomalous {
+            self.learn_names(&run.behavior);
+        } else if run.clean {
+            self.learn(&run.behavior);
+        }
+    }
+
+    /// Keeps the baseline following a game whose hosts keep changing,
+    /// without letting one run teach it everything it did.
+    fn learn_names(&mut self, run: &RunBehavior) {
+        for (known, seen) in [
+            (&mut self.destinations, &run.destinations),
+            (&mut self.child_processes, &run.child_processes),
+        ] {
+            let new: Vec<&String> = seen
+                .iter()
+                .filter(|name| !known.contains(*name))
+                .take(MAX_LEARNED_FROM_ANOMALOUS)
+                .collect();
+            remember(known, new);
+        }
+    }
+
+    fn learn(&mut self, run: &RunBehavior) {
+        self.runs = self.runs.saturating_add(1);
+        remember(&mut self.destinations, &run.destinations);
+        remember(&mut self.child_processes, &run.child_processes);
+        let runs = f64::from(self.runs);
+        self.file_writes += (f64::from(run.file_writes) - self.file_writes) / runs;
+        self.dns_queries += (f64::from(run.dns_queries) - self.dns_queries) / runs;
+    }
+}
+
+/// Once full, new names are no longer remembered and keep counting as new.
+fn remember<'a>(known: &mut BTreeSet<String>, seen: impl IntoIterator<Item = &'a String>) {
+    for name in seen {
+        if known.len() >= MAX_KNOWN_NAMES {
+            break;
+        }
+        known.insert(name.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(hosts: &[&str], file_writes: u32, clean: bool) -> RunSummary {
+        RunSummary {
+            score: if clean { 90 } else { 30 },
+            clean,
+            anomalous: false,
+            behavior: RunBehavior {
+                destinations: hosts.iter().map(|host| host.to_string()).collect(),
+                child_processes: BTreeSet::new(),
+                file_writes,
+                dns_queries: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_learns_from_clean_runs_only() {
+        let mut baseline = BehaviorBaseline::default();
+        baseline.record(&run(&["steam.com"], 10, true));
+        baseline.record(&run(&["evil.example"], 500, false));
+        baseline.record(&run(&["steam.com", "cdn.steam.com"], 30, true));
+
+        assert_eq!(baseline.runs, 2);
+        assert_eq!(baseline.destinations.len(), 2);
+        assert!(!baseline.destinations.contains("evil.example"));
+        assert_eq!(baseline.file_writes, 20.0);
+        assert_eq!(baseline.scores, vec![90, 30, 90]);
+    }
+
+    #[test]
+    fn test_anomalous_runs_teach_a_few_names() {
+        let mut baseline = BehaviorBaseline::default();
+        baseline.record(&run(&["steam.com"], 10, true));
+        let hosts: Vec<String> = (0..40).map(|n| format!("cdn{n:02}.example")).collect();
+        let hosts: Vec<&str> = hosts.iter().map(String::as_str).collect();
+        let mut anomalous = run(&hosts, 500, true);
+        anomalous.anomalous = true;
+        baseline.record(&anomalous);
+
+        assert_eq!(baseline.runs, 1);
+        assert_eq!(baseline.destinations.len(), 1 + MAX_LEARNED_FROM_ANOMALOUS);
+        assert!(baseline.destinations.contains("cdn00.example"));
+        assert_eq!(baseline.file_writes, 10.0);
+    }
+}
@@ -0,0 +1,341 @@
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::{Context, Result};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, Row};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::sqlite::BUSY_TIMEOUT;
+use super::ExecutableIdentity;
+use crate::decision::PolicyDecision;
+use crate::ipc::{EventQuery, StoredEvent};
+use crate::types::{AccessAttempt, AccessTarget};
+
+/// Stored in `PRAGMA user_version`.
+const SCHEMA_VERSION: i32 = 1;
+/// Upper bound on events returned by one query, so a reply fits in a frame.
+pub const MAX_QUERY_LIMIT: u32 = 10_000;
+const DEFAULT_QUERY_LIMIT: u32 = 100;
+
+/// Every evaluated event from monitored sessions, daemon and CLI runs
+/// alike, indexed for `Query` requests. Clones share one connection.
+#[derive(Clone)]
+pub struct EventStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl EventStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("create event store dir {}", parent.display()))?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("open event store {}", path.display()))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+            .context("enable WAL on event store")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+
+        let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(anyhow::anyhow!(
+                "event store schema {version} is newer than this build supports ({SCHEMA_VERSION})"
+            ));
+        }
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                sha256 TEXT NOT NULL,
+                executable BLOB NOT NULL,
+                timestamp_ns INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                decision TEXT NOT NULL,
+                zone_label TEXT,
+                target TEXT NOT NULL,
+                attempt_json TEXT NOT NULL,
+                decision_json TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS events_session ON events (session_id);
+            CREATE INDEX IF NOT EXISTS events_sha256 ON events (sha256, timestamp_ns);
+            CREATE INDEX IF NOT EXISTS events_time ON events (timestamp_ns);
+            CREATE INDEX IF NOT EXISTS events_kind ON events (kind, timestamp_ns);
+            CREATE INDEX IF NOT EXISTS events_decision ON events (decision, timestamp_ns);
+            CREATE INDEX IF NOT EXISTS events_zone ON events (zone_label COLLATE NOCASE);",
+        )?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Stores one evaluated event, as the session produces it.
+    pub fn record(
+        &self,
+        session_id: Uuid,
+        identity: &ExecutableIdentity,
+        attempt: &AccessAttempt,
+        decision: &PolicyDecision,
+    ) -> Result<()> {
+        self.lock().execute(
+            "INSERT INTO events (
+                session_id, sha256, executable, timestamp_ns, kind, decision,
+                zone_label, target, attempt_json, decision_json
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                session_id.to_string(),
+                identity.sha256,
+                identity.path.as_os_str().as_bytes(),
+                timestamp_ns(attempt.timestamp),
+                attempt.kind.as_str(),
+                decision.action.kind().as_str(),
+                decision.zone_label,
+                target_text(&attempt.target),
+                serde_json::to_string(attempt).context("serialize attempt")?,
+                serde_json::to_string(decision).context("serialize decision")?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Deletes events that happened before `cutoff`; returns how many.
+    pub fn prune_before(&self, cutoff: OffsetDateTime) -> Result<usize> {
+        let removed = self.lock().execute(
+            "DELETE FROM events WHERE timestamp_ns < ?1",
+            params![timestamp_ns(cutoff)],
+        )?;
+        Ok(removed)
+    }
+
+    /// Matching events, newest first.
+    pub fn query(&self, query: &EventQuery) -> Result<Vec<StoredEvent>> {
+        let mut clauses = Vec::new();
+        let mut values = Vec::new();
+        if let Some(session_id) = query.session_id {
+            clauses.push("session_id = ?".to_string());
+            values.push(Value::Text(session_id.to_string()));
+        }
+        if let Some(sha256) = &query.executable_sha256 {
+            clauses.push("sha256 = ?".to_string());
+            values.push(Value::Text(sha256.clone()));
+        }
+        if let Some(since) = query.since {
+            clauses.push("timestamp_ns >= ?".to_string());
+            values.push(Value::Integer(timestamp_ns(since)));
+        }
+        if let Some(until) = query.until {
+            clauses.push("timestamp_ns < ?".to_string());
+            values.push(Value::Integer(timestamp_ns(until)));
+        }
+        if !query.kinds.is_empty() {
+            clauses.push(in_clause("kind", query.kinds.len()));
+            values.extend(
+                query
+                    .kinds
+                    .iter()
+                    .map(|kind| Value::Text(kind.as_str().to_string())),
+            );
+        }
+        if !query.decisions.is_empty() {
+            clauses.push(in_clause("decision", query.decisions.len()));
+            values.extend(
+                query
+                    .decisions
+                    .iter()
+                    .map(|kind| Value::Text(kind.as_str().to_string())),
+            );
+        }
+        if let Some(zone) = &query.zone_label {
+            clauses.push("zone_label = ? COLLATE NOCASE".to_string());
+            values.push(Value::Text(zone.clone()));
+        }
+        if let Some(fragment) = &query.target_contains {
+            clauses.push("instr(target, ?) > 0".to_string());
+            values.push(Value::Text(fragment.clone()));
+        }
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_QUERY_LIMIT)
+            .min(MAX_QUERY_LIMIT);
+        values.push(Value::Integer(i64::from(limit)));
+
+        let filter = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        };
+        let sql = format!(
+            "SELECT session_id, sha256, executable, attempt_json, decision_json FROM events
+             {filter} ORDER BY timestamp_ns DESC, id DESC LIMIT ?"
+        );
+        let conn = self.lock();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(values), read_row)?;
+        let mut events = Vec::new();
+        for row in rows {
+            events.push(row??);
+        }
+        Ok(events)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.conn
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn read_row(row: &Row<'_>) -> rusqlite::Result<Result<StoredEvent>> {
+    let session_id: String = row.get(0)?;
+    let executable_sha256: String = row.get(1)?;
+    let executable: Vec<u8> = row.get(2)?;
+    let attempt: String = row.get(3)?;
+    let decision: String = row.get(4)?;
+    Ok((|| {
+        Ok(StoredEvent {
+            session_id: Uuid::parse_str(&session_id).context("parse stored session id")?,
+            executable: PathBuf::from(std::ffi::OsStr::from_bytes(&executable)),
+            executable_sha256,
+            attempt: serde_json::from_str(&attempt).context("parse stored attempt")?,
+            decision: serde_json::from_str(&decision).context("parse stored decision")?,
+        })
+    })())
+}
+
+fn in_clause(column: &str, count: usize) -> String {
+    format!("{column} IN ({})", vec!["?"; count].join(", "))
+}
+
+fn timestamp_ns(timestamp: OffsetDateTime) -> i64 {
+    timestamp.unix_timestamp_nanos() as i64
+}
+
+fn target_text(target: &AccessTarget) -> String {
+    match target {
+        AccessTarget::Path(path) => path.display().to_string(),
+        AccessTarget::Network(network) => format!("{}:{}", network.host, network.port),
+        AccessTarget::Device(device) => device.clone(),
+        AccessTarget::Socket(socket) => socket.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decision::{DecisionAction, DecisionKind};
+    use crate::types::AccessKind;
+
+    fn event(
+        kind: AccessKind,
+        path: &str,
+        action: DecisionAction,
+        zone: Option<&str>,
+    ) -> (AccessAttempt, PolicyDecision) {
+        let attempt = AccessAttempt {
+            timestamp: OffsetDateTime::now_utc(),
+            kind,
+            target: AccessTarget::Path(PathBuf::from(path)),
+            note: None,
+        };
+        let decision = PolicyDecision {
+            action,
+            reason: "test".to_string(),
+            zone_label: zone.map(str::to_string),
+            systemic_risk: false,
+            rule: None,
+        };
+        (attempt, decision)
+    }
+
+    #[test]
+    fn test_query_filters_across_sessions() {
+        let dir = std::env::temp_dir().join(format!("winewarden-events-{}", Uuid::new_v4()));
+        let store = EventStore::open(&dir.join("events.db")).unwrap();
+        let game = ExecutableIdentity {
+            path: PathBuf::from("/games/game.exe"),
+            sha256: "aa".repeat(32),
+        };
+        let other = ExecutableIdentity {
+            path: PathBuf::from("/games/other.exe"),
+            sha256: "bb".repeat(32),
+        };
+        let ssh = "/home/user/.ssh/id_ed25519";
+        let record = |identity: &ExecutableIdentity,
+                      events: Vec<(AccessAttempt, PolicyDecision)>| {
+            let session_id = Uuid::new_v4();
+            for (attempt, decision) in &events {
+                store
+                    .record(session_id, identity, attempt, decision)
+                    .unwrap();
+            }
+        };
+        record(
+            &game,
+            vec![
+                event(
+                    AccessKind::Read,
+                    ssh,
+                    DecisionAction::Deny,
+                    Some("SSH keys"),
+                ),
+                event(
+                    AccessKind::Read,
+                    "/prefix/drive_c/game.ini",
+                    DecisionAction::Allow,
+                    None,
+                ),
+            ],
+        );
+        record(
+            &other,
+            vec![event(
+                AccessKind::Read,
+                ssh,
+                DecisionAction::Deny,
+                Some("SSH keys"),
+            )],
+        );
+
+        let denied_ssh = EventQuery {
+            since: Some(OffsetDateTime::now_utc() - time::Duration::days(7)),
+            kinds: vec![AccessKind::Read],
+            decisions: vec![DecisionKind::Deny],
+            zone_label: Some("ssh KEYS".to_string()),
+            ..Default::default()
+        };
+        let found = store.query(&denied_ssh).unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(found
+            .iter()
+            .all(|event| event.decision.zone_label.as_deref() == Some("SSH keys")));
+
+        let one_game = EventQuery {
+            executable_sha256: Some(game.sha256.clone()),
+            target_contains: Some(".ssh".to_string()),
+            ..Default::default()
+        };
+        let found = store.query(&one_game).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].executable, game.path);
+
+        let future = EventQuery {
+            since: Some(OffsetDateTime::now_utc() + time::Duration::hours(1)),
+            ..Default::default()
+        };
+        assert!(store.query(&future).unwrap().is_empty());
+
+        assert_eq!(
+            store
+                .prune_before(OffsetDateTime::now_utc() + time::Duration::hours(1))
+                .unwrap(),
+            3
+        );
+        assert!(store.query(&EventQuery::default()).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,292 @@
+use std::ffi::OsStr;
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use sha2::{Digest, Sha256};
+
+use super::sqlite::BUSY_TIMEOUT;
+
+/// Stored in `PRAGMA user_version`.
+const SCHEMA_VERSION: i32 = 1;
+/// Read size for streaming hashes; keeps memory flat for multi-GB binaries.
+const HASH_CHUNK: usize = 1 << 20;
+
+/// Digests keyed by `(device, inode, size, mtime, ctime)`. A file whose stat
+/// still matches is not read again. The ctime catches a rewrite that puts
+/// the old mtime back, since it cannot be set from userspace. Lives next to the trust database and shares
+/// its WAL setup so parallel runs can fill it.
+#[derive(Debug)]
+pub struct HashCache {
+    conn: Connection,
+}
+
+/// The stat fields a cached digest is only valid for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct StatKey {
+    dev: u64,
+    ino: u64,
+    size: u64,
+    mtime_ns: i64,
+    ctime_ns: i64,
+}
+
+impl HashCache {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("create hash cache dir {}", parent.display()))?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("open hash cache {}", path.display()))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+            .context("enable WAL on hash cache")?;
+
+        let mut cache = Self { conn };
+        cache.migrate()?;
+        Ok(cache)
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let version: i32 = tx.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(anyhow::anyhow!(
+                "hash cache schema {version} is newer than this build supports ({SCHEMA_VERSION})"
+            ));
+        }
+        if version < 1 {
+            // Entries from before the ctime was kept never match again and
+            // are replaced the next time their file is hashed.
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS hash_cache (
+                    dev INTEGER NOT NULL,
+                    ino INTEGER NOT NULL,
+                    size INTEGER NOT NULL,
+                    mtime_ns INTEGER NOT NULL,
+                    path BLOB NOT NULL,
+                    sha256 TEXT NOT NULL,
+                    PRIMARY KEY (dev, ino)
+                );
+                ALTER TABLE hash_cache ADD COLUMN ctime_ns INTEGER NOT NULL DEFAULT 0;",
+            )?;
+        }
+        tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        tx.commit().context("migrate hash cache")?;
+        Ok(())
+    }
+
+    /// Returns the SHA-256 of `path`, hashing only on a cache miss. Lookup
+    /// and store failures fall back to hashing; the cache never decides a
+    /// digest on its own.
+    pub fn sha256(&self, path: &Path) -> Result<String> {
+        let before = stat_key(&metadata(path)?);
+        if let Ok(Some(sha256)) = self.lookup(&before) {
+            return Ok(sha256);
+        }
+        let sha256 = sha256_file(path)?;
+        // Only cache a digest if the file did not change while it was read.
+        if stat_key(&metadata(path)?) == before {
+            let _ = self.store(&before, path, &sha256);
+        }
+        Ok(sha256)
+    }
+
+    /// Drops entries whose file is gone or has changed since it was hashed.
+    /// Returns how many were removed.
+    pub fn prune(&self) -> Result<usize> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT dev, ino, size, mtime_ns, ctime_ns, path FROM hash_cache")?;
+        let rows = stmt.query_map([], |row| {
+            let key = StatKey {
+                dev: row.get::<_, i64>(0)? as u64,
+                ino: row.get::<_, i64>(1)? as u64,
+                size: row.get::<_, i64>(2)? as u64,
+                mtime_ns: row.get(3)?,
+                ctime_ns: row.get(4)?,
+            };
+            Ok((key, row.get::<_, Vec<u8>>(5)?))
+        })?;
+        let mut stale = Vec::new();
+        for row in rows {
+            let (key, path) = row?;
+            let current = fs::metadata(Path::new(OsStr::from_bytes(&path)))
+                .ok()
+                .map(|metadata| stat_key(&metadata));
+            if current != Some(key) {
+                stale.push(key);
+            }
+        }
+        for key in &stale {
+            self.conn.execute(
+                "DELETE FROM hash_cache WHERE dev = ?1 AND ino = ?2",
+                params![key.dev as i64, key.ino as i64],
+            )?;
+        }
+        Ok(stale.len())
+    }
+
+    fn lookup(&self, key: &StatKey) -> Result<Option<String>> {
+        let sha256 = self
+            .conn
+            .query_row(
+                "SELECT sha256 FROM hash_cache
+                 WHERE dev = ?1 AND ino = ?2 AND size = ?3 AND mtime_ns = ?4 AND ctime_ns = ?5",
+                params![
+                    key.dev as i64,
+                    key.ino as i64,
+                    key.size as i64,
+                    key.mtime_ns,
+                    key.ctime_ns
+                ],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(sha256)
+    }
+
+    fn store(&self, key: &StatKey, path: &Path, sha256: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO hash_cache (dev, ino, size, mtime_ns, ctime_ns, path, sha256)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(dev, ino) DO UPDATE SET
+                size = excluded.size,
+                mtime_ns = excluded.mtime_ns,
+                ctime_ns = excluded.ctime_ns,
+                path = excluded.path,
+                sha256 = excluded.sha256",
+            params![
+                key.dev as i64,
+                key.ino as i64,
+                key.size as i64,
+                key.mtime_ns,
+                key.ctime_ns,
+                path.as_os_str().as_bytes(),
+                sha256,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+/// Streams `path` through SHA-256 in fixed-size chunks.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file =
+        File::open(path).with_context(|| format!("read executable {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; HASH_CHUNK];
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => {
+                return Err(err).with_context(|| format!("read executable {}", path.display()))
+            }
+        };
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn metadata(path: &Path) -> Result<Metadata> {
+    fs::metadata(path).with_context(|| format!("stat executable {}", path.display()))
+}
+
+fn stat_key(metadata: &Metadata) -> StatKey {
+    StatKey {
+        dev: metadata.dev(),
+        ino: metadata.ino(),
+        size: metadata.size(),
+        mtime_ns: metadata
+            .mtime()
+            .saturating_mul(1_000_000_000)
+            .saturating_add(metadata.mtime_nsec()),
+        ctime_ns: metadata
+            .ctime()
+            .saturating_mul(1_000_000_000)
+            .saturating_add(metadata.ctime_nsec()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("winewarden-hash-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_streaming_hash_matches_one_shot() {
+        let dir = temp_dir();
+        let exe = dir.join("game.exe");
+        let contents: Vec<u8> = (0..(HASH_CHUNK * 2 + 17)).map(|i| i as u8).collect();
+        fs::write(&exe, &contents).unwrap();
+
+        assert_eq!(
+            sha256_file(&exe).unwrap(),
+            hex::encode(Sha256::digest(&contents))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cache_hit_skips_hashing() {
+        let dir = temp_dir();
+        let exe = dir.join("game.exe");
+        fs::write(&exe, b"original").unwrap();
+        let cache = HashCache::open(&dir.join("hash_cache.db")).unwrap();
+
+        let digest = cache.sha256(&exe).unwrap();
+        assert_eq!(digest, hex::encode(Sha256::digest(b"original")));
+
+        // A matching stat key is trusted without reading the file.
+        let key = stat_key(&fs::metadata(&exe).unwrap());
+        cache.store(&key, &exe, "cached").unwrap();
+        assert_eq!(cache.sha256(&exe).unwrap(), "cached");
+
+        // A different size invalidates the entry.
+        fs::write(&exe, b"patched build").unwrap();
+        assert_eq!(
+            cache.sha256(&exe).unwrap(),
+            hex::encode(Sha256::digest(b"patched build"))
+        );
+
+        // So does a same-size rewrite that puts the old mtime back.
+        let modified = fs::metadata(&exe).unwrap().modified().unwrap();
+        cache.sha256(&exe).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        fs::write(&exe, b"PATCHED BUILD").unwrap();
+        File::options()
+            .write(true)
+            .open(&exe)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert_eq!(
+            cache.sha256(&exe).unwrap(),
+            hex::encode(Sha256::digest(b"PATCHED BUILD"))
+        );
+
+        // Entries for deleted files are pruned; live ones stay.
+        let gone = dir.join("gone.exe");
+        fs::write(&gone, b"temporary").unwrap();
+        cache.sha256(&gone).unwrap();
+        fs::remove_file(&gone).unwrap();
+        assert_eq!(cache.prune().unwrap(), 1);
+        assert_eq!(cache.prune().unwrap(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
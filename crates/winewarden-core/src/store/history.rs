@@ -0,0 +1,129 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::trust::TrustTier;
+
+/// Who or what changed a tier.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustChangeSource {
+    /// First time the executable was seen.
+    Initial,
+    /// `winewarden trust set` or an explicit `--trust` override.
+    Manual,
+    AutoPromotion,
+    ScoreDemotion,
+    PirateSafeDowngrade,
+    /// Lowered by the daemon after a long time without runs.
+    StaleReview,
+}
+
+/// Why a write is happening; recorded in history when the tier changes.
+#[derive(Debug, Clone)]
+pub struct TierChange {
+    pub source: TrustChangeSource,
+    pub session_id: Option<Uuid>,
+    pub reason: String,
+}
+
+/// One append-only history row for a trust record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustHistoryEntry {
+    pub sha256: String,
+    pub old_tier: Option<TrustTier>,
+    pub new_tier: TrustTier,
+    pub timestamp: OffsetDateTime,
+    pub source: TrustChangeSource,
+    pub session_id: Option<Uuid>,
+    pub reason: String,
+}
+
+impl TierChange {
+    pub fn new(source: TrustChangeSource, reason: impl Into<String>) -> Self {
+        Self {
+            source,
+            session_id: None,
+            reason: reason.into(),
+        }
+    }
+
+    pub fn with_session(mut self, session_id: Uuid) -> Self {
+        self.session_id = Some(session_id);
+        self
+    }
+
+    /// Explains the tier a run is about to store. `base_tier` is the tier
+    /// before pirate-safe mode was applied.
+    pub fn for_run(base_tier: TrustTier, tier: TrustTier, overridden: bool) -> Self {
+        if tier != base_tier {
+            Self::new(
+                TrustChangeSource::PirateSafeDowngrade,
+                format!("pirate-safe mode lowered {base_tier} to {tier}"),
+            )
+        } else if overridden {
+            Self::new(TrustChangeSource::Manual, "--trust override on run")
+        } else {
+            Self::new(
+                TrustChangeSource::Initial,
+                "first run at the configured tier",
+            )
+        }
+    }
+
+    pub(crate) fn entry(
+        &self,
+        sha256: &str,
+        old_tier: Option<TrustTier>,
+        new_tier: TrustTier,
+        timestamp: OffsetDateTime,
+    ) -> TrustHistoryEntry {
+        TrustHistoryEntry {
+            sha256: sha256.to_string(),
+            old_tier,
+            new_tier,
+            timestamp,
+            source: self.source,
+            session_id: self.session_id,
+            reason: self.reason.clone(),
+        }
+    }
+}
+
+impl TrustChangeSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrustChangeSource::Initial => "initial",
+            TrustChangeSource::Manual => "manual",
+            TrustChangeSource::AutoPromotion => "auto_promotion",
+            TrustChangeSource::ScoreDemotion => "score_demotion",
+            TrustChangeSource::PirateSafeDowngrade => "pirate_safe_downgrade",
+            TrustChangeSource::StaleReview => "stale_review",
+        }
+    }
+}
+
+impl fmt::Display for TrustChangeSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TrustChangeSource {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "initial" => Ok(TrustChangeSource::Initial),
+            "manual" => Ok(TrustChangeSource::Manual),
+            "auto_promotion" => Ok(TrustChangeSource::AutoPromotion),
+            "score_demotion" => Ok(TrustChangeSource::ScoreDemotion),
+            "pirate_safe_downgrade" => Ok(TrustChangeSource::PirateSafeDowngrade),
+            "stale_review" => Ok(TrustChangeSource::StaleReview),
+            _ => Err(format!("unknown trust change source: {value}")),
+        }
+    }
+}
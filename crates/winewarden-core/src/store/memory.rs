@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use super::{TrustBackend, TrustHistoryEntry, TrustRecord, TrustUpdate};
+
+/// Process-local backend, mainly for tests and dry runs.
+#[derive(Debug, Default)]
+pub struct MemoryTrustBackend {
+    records: HashMap<String, TrustRecord>,
+    history: Vec<TrustHistoryEntry>,
+}
+
+impl TrustBackend for MemoryTrustBackend {
+    fn get(&self, sha256: &str) -> Result<Option<TrustRecord>> {
+        Ok(self.records.get(sha256).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<TrustRecord>> {
+        Ok(self.records.values().cloned().collect())
+    }
+
+    fn update(
+        &mut self,
+        sha256: &str,
+        apply: &mut dyn FnMut(Option<TrustRecord>) -> TrustUpdate,
+    ) -> Result<TrustRecord> {
+        let update = apply(self.records.get(sha256).cloned());
+        self.history.extend(update.history);
+        self.records
+            .insert(sha256.to_string(), update.record.clone());
+        Ok(update.record)
+    }
+
+    fn history(&self, sha256: &str) -> Result<Vec<TrustHistoryEntry>> {
+        Ok(self
+            .history
+            .iter()
+            .filter(|entry| entry.sha256 == sha256)
+            .cloned()
+            .collect())
+    }
+
+    fn import(&mut self, records: Vec<TrustRecord>) -> Result<usize> {
+        let mut added = 0;
+        for record in records {
+            if !self.records.contains_key(&record.identity.sha256) {
+                self.records.insert(record.identity.sha256.clone(), record);
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::trust::TrustTier;
+
+mod baseline;
+mod events;
+mod hash_cache;
+mod history;
+mod memory;
+mod sqlite;
+
+pub use baseline::{BehaviorBaseline, RunBehavior, RunSummary};
+pub use events::{EventStore, MAX_QUERY_LIMIT};
+pub use hash_cache::{sha256_file, HashCache};
+pub use history::{TierChange, TrustChangeSource, TrustHistoryEntry};
+pub use memory::MemoryTrustBackend;
+pub use sqlite::SqliteTrustBackend;
+
+/// The pre-SQLite trust store, imported once and then renamed.
+pub const LEGACY_TRUST_FILE: &str = "trust.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutableIdentity {
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustRecord {
+    pub identity: ExecutableIdentity,
+    pub tier: TrustTier,
+    pub runs: u32,
+    /// Consecutive clean runs since the last tier change; drives auto-promotion.
+    #[serde(default)]
+    pub clean_runs: u32,
+    pub last_seen: OffsetDateTime,
+    #[serde(default)]
+    pub baseline: BehaviorBaseline,
+}
+
+/// A record write plus the history entry to append alongside it, if any.
+#[derive(Debug, Clone)]
+pub struct TrustUpdate {
+    pub record: TrustRecord,
+    pub history: Option<TrustHistoryEntry>,
+}
+
+/// Storage behind `TrustStore`. `update` must apply its read-modify-write,
+/// including the history append, atomically with respect to other writers.
+/// History is append-only.
+pub trait TrustBackend: Send {
+    fn get(&self, sha256: &str) -> Result<Option<TrustRecord>>;
+    fn list(&self) -> Result<Vec<TrustRecord>>;
+    fn update(
+        &mut self,
+        sha256: &str,
+        apply: &mut dyn FnMut(Option<TrustRecord>) -> TrustUpdate,
+    ) -> Result<TrustRecord>;
+    /// Oldest entry first.
+    fn history(&self, sha256: &str) -> Result<Vec<TrustHistoryEntry>>;
+    /// Inserts records that are not already present; returns how many were added.
+    fn import(&mut self, records: Vec<TrustRecord>) -> Result<usize>;
+}
+
+pub struct TrustStore {
+    backend: Box<dyn TrustBackend>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct LegacyTrustFile {
+    records: HashMap<String, TrustRecord>,
+}
+
+impl ExecutableIdentity {
+    /// Hashes the executable without consulting the cache.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            sha256: sha256_file(path)?,
+        })
+    }
+
+    /// Hashes the executable through the `HashCache` at `cache_path`. If the
+    /// cache cannot be opened the file is hashed directly.
+    pub fn from_path_cached(path: &Path, cache_path: &Path) -> Result<Self> {
+        match HashCache::open(cache_path) {
+            Ok(cache) => Ok(Self {
+                path: path.to_path_buf(),
+                sha256: cache.sha256(path)?,
+            }),
+            Err(_) => Self::from_path(path),
+        }
+    }
+}
+
+impl TrustStore {
+    /// Opens the SQLite store at `path`, importing a legacy `trust.json`
+    /// from the same directory on first use.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut store = Self::with_backend(Box::new(SqliteTrustBackend::open(path)?));
+        let legacy = path.with_file_name(LEGACY_TRUST_FILE);
+        if legacy.exists() {
+            store.import_legacy(&legacy)?;
+        }
+        Ok(store)
+    }
+
+    pub fn in_memory() -> Self {
+        Self::with_backend(Box::new(MemoryTrustBackend::default()))
+    }
+
+    pub fn with_backend(backend: Box<dyn TrustBackend>) -> Self {
+        Self { backend }
+    }
+
+    pub fn get(&self, identity: &ExecutableIdentity) -> Result<Option<TrustRecord>> {
+        self.backend.get(&identity.sha256)
+    }
+
+    pub fn get_tier(&self, identity: &ExecutableIdentity) -> Result<Option<TrustTier>> {
+        Ok(self.get(identity)?.map(|record| record.tier))
+    }
+
+    pub fn contains(&self, identity: &ExecutableIdentity) -> Result<bool> {
+        Ok(self.get(identity)?.is_some())
+    }
+
+    pub fn records(&self) -> Result<Vec<TrustRecord>> {
+        self.backend.list()
+    }
+
+    pub fn history(&self, identity: &ExecutableIdentity) -> Result<Vec<TrustHistoryEntry>> {
+        self.backend.history(&identity.sha256)
+    }
+
+    /// Counts a run and stores the tier and clean-run streak it ended with,
+    /// learning the run into the baseline if it was clean.
+    pub fn record_run(
+        &mut self,
+        identity: ExecutableIdentity,
+        tier: TrustTier,
+        clean_runs: u32,
+        change: TierChange,
+        run: &RunSummary,
+    ) -> Result<TrustRecord> {
+        self.write(identity, tier, Some((clean_runs, run)), change)
+    }
+
+    pub fn set_tier(
+        &mut self,
+        identity: ExecutableIdentity,
+        tier: TrustTier,
+        change: TierChange,
+    ) -> Result<TrustRecord> {
+        self.write(identity, tier, None, change)
+    }
+
+    fn write(
+        &mut self,
+        identity: ExecutableIdentity,
+        tier: TrustTier,
+        run: Option<(u32, &RunSummary)>,
+        change: TierChange,
+    ) -> Result<TrustRecord> {
+        let now = OffsetDateTime::now_utc();
+        self.backend
+            .update(&identity.sha256.clone(), &mut |existing| {
+                let old_tier = existing.as_ref().map(|record| record.tier);
+                let mut record = existing.unwrap_or_else(|| new_record(&identity, tier, now));
+                match run {
+                    Some((clean_runs, summary)) => {
+                        record.runs = record.runs.saturating_add(1);
+                        record.clean_runs = clean_runs;
+                        record.baseline.record(summary);
+                    }
+                    // A manual tier restarts the streak.
+                    None if old_tier != Some(tier) => record.clean_runs = 0,
+                    None => {}
+                }
+                record.tier = tier;
+                record.identity.path = identity.path.clone();
+                record.last_seen = now;
+                let history = (old_tier != Some(tier))
+                    .then(|| change.entry(&identity.sha256, old_tier, tier, now));
+                TrustUpdate { record, history }
+            })
+    }
+
+    fn import_legacy(&mut self, legacy: &Path) -> Result<()> {
+        let contents = match fs::read_to_string(legacy) {
+            Ok(contents) => contents,
+            // Another process finished the import first.
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read trust store {}", legacy.display()))
+            }
+        };
+        let file: LegacyTrustFile =
+            serde_json::from_str(&contents).context("parse trust store JSON")?;
+        self.backend.import(file.records.into_values().collect())?;
+
+        let migrated = legacy.with_extension("json.migrated");
+        match fs::rename(legacy, &migrated) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                Err(err).with_context(|| format!("rename trust store to {}", migrated.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn new_record(identity: &ExecutableIdentity, tier: TrustTier, now: OffsetDateTime) -> TrustRecord {
+    TrustRecord {
+        identity: identity.clone(),
+        tier,
+        runs: 0,
+        clean_runs: 0,
+        last_seen: now,
+        baseline: BehaviorBaseline::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(name: &str) -> ExecutableIdentity {
+        ExecutableIdentity {
+            path: PathBuf::from(format!("/games/{name}.exe")),
+            sha256: format!("{name}-hash"),
+        }
+    }
+
+    fn clean_run() -> RunSummary {
+        RunSummary {
+            score: 90,
+            clean: true,
+            anomalous: false,
+            behavior: RunBehavior {
+                destinations: ["steampowered.com".to_string()].into(),
+                ..RunBehavior::default()
+            },
+        }
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("winewarden-store-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn exercise(store: &mut TrustStore) {
+        let game = identity("game");
+        assert!(!store.contains(&game).unwrap());
+
+        let session = uuid::Uuid::new_v4();
+        let first = TierChange::for_run(TrustTier::Yellow, TrustTier::Yellow, false);
+        store
+            .record_run(
+                game.clone(),
+                TrustTier::Yellow,
+                1,
+                first.with_session(session),
+                &clean_run(),
+            )
+            .unwrap();
+        let again = TierChange::for_run(TrustTier::Yellow, TrustTier::Yellow, false);
+        let record = store
+            .record_run(game.clone(), TrustTier::Yellow, 2, again, &clean_run())
+            .unwrap();
+        assert_eq!(record.runs, 2);
+        assert_eq!(record.clean_runs, 2);
+        assert_eq!(record.baseline.runs, 2);
+        assert!(record.baseline.destinations.contains("steampowered.com"));
+
+        let manual = TierChange::new(TrustChangeSource::Manual, "known good");
+        store
+            .set_tier(game.clone(), TrustTier::Green, manual)
+            .unwrap();
+        assert_eq!(store.get_tier(&game).unwrap(), Some(TrustTier::Green));
+        let record = store.get(&game).unwrap().unwrap();
+        assert_eq!((record.runs, record.clean_runs), (2, 0));
+        assert_eq!(store.records().unwrap().len(), 1);
+
+        // Only tier changes are recorded: the repeat run at Yellow is not.
+        let history = store.history(&game).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].old_tier, None);
+        assert_eq!(history[0].source, TrustChangeSource::Initial);
+        assert_eq!(history[0].session_id, Some(session));
+        assert_eq!(history[1].old_tier, Some(TrustTier::Yellow));
+        assert_eq!(history[1].new_tier, TrustTier::Green);
+        assert_eq!(history[1].source, TrustChangeSource::Manual);
+        assert_eq!(history[1].reason, "known good");
+    }
+
+    #[test]
+    fn test_memory_backend() {
+        exercise(&mut TrustStore::in_memory());
+    }
+
+    #[test]
+    fn test_sqlite_backend_persists() {
+        let dir = temp_dir();
+        let path = dir.join("trust.db");
+        exercise(&mut TrustStore::open(&path).unwrap());
+
+        let reopened = TrustStore::open(&path).unwrap();
+        let record = reopened.get(&identity("game")).unwrap().unwrap();
+        assert_eq!(record.tier, TrustTier::Green);
+        assert_eq!(record.identity.path, PathBuf::from("/games/game.exe"));
+        assert_eq!(record.baseline.scores, vec![90, 90]);
+        assert_eq!(reopened.history(&identity("game")).unwrap().len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_legacy_json_is_imported_once() {
+        let dir = temp_dir();
+        let game = identity("old");
+        let legacy = serde_json::json!({
+            "records": {
+                game.sha256.clone(): new_record(&game, TrustTier::Green, OffsetDateTime::now_utc())
+            }
+        });
+        fs::write(dir.join(LEGACY_TRUST_FILE), legacy.to_string()).unwrap();
+
+        let store = TrustStore::open(&dir.join("trust.db")).unwrap();
+        assert_eq!(store.get_tier(&game).unwrap(), Some(TrustTier::Green));
+        assert!(!dir.join(LEGACY_TRUST_FILE).exists());
+        assert!(dir.join("trust.json.migrated").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parallel_writers_do_not_lose_runs() {
+        let dir = temp_dir();
+        let path = dir.join("trust.db");
+        TrustStore::open(&path).unwrap();
+
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let mut store = TrustStore::open(&path).unwrap();
+                    for _ in 0..25 {
+                        let change =
+                            TierChange::for_run(TrustTier::Yellow, TrustTier::Yellow, false);
+                        store
+                            .record_run(
+                                identity("shared"),
+                                TrustTier::Yellow,
+                                0,
+                                change,
+                                &clean_run(),
+                            )
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let store = TrustStore::open(&path).unwrap();
+        let record = store.get(&identity("shared")).unwrap().unwrap();
+        assert_eq!(record.runs, 100);
+        assert_eq!(record.baseline.runs, 100);
+        assert_eq!(store.history(&identity("shared")).unwrap().len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
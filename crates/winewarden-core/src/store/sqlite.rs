@@ -0,0 +1,272 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row, TransactionBehavior};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use super::{ExecutableIdentity, TrustBackend, TrustHistoryEntry, TrustRecord, TrustUpdate};
+
+/// Stored in `PRAGMA user_version`.
+const SCHEMA_VERSION: i32 = 4;
+/// How long a writer waits for another process to release the database.
+pub(super) const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// SQLite backend in WAL mode. Every write runs in a `BEGIN IMMEDIATE`
+/// transaction, so concurrent `winewarden run` processes and the daemon
+/// serialize their updates instead of overwriting each other.
+pub struct SqliteTrustBackend {
+    conn: Connection,
+}
+
+impl SqliteTrustBackend {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("create trust dir {}", parent.display()))?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("open trust database {}", path.display()))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+            .context("enable WAL on trust database")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+
+        let mut backend = Self { conn };
+        backend.migrate()?;
+        Ok(backend)
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let version: i32 = tx.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(anyhow::anyhow!(
+                "trust database schema {version} is newer than this build supports ({SCHEMA_VERSION})"
+            ));
+        }
+        if version < 1 {
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS trust_records (
+                    sha256 TEXT PRIMARY KEY,
+                    path BLOB NOT NULL,
+                    tier TEXT NOT NULL,
+                    runs INTEGER NOT NULL,
+                    last_seen TEXT NOT NULL
+                );",
+            )?;
+        }
+        if version < 2 {
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS trust_history (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    sha256 TEXT NOT NULL,
+                    old_tier TEXT,
+                    new_tier TEXT NOT NULL,
+                    timestamp TEXT NOT NULL,
+                    source TEXT NOT NULL,
+                    session_id TEXT,
+                    reason TEXT NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS trust_history_sha256 ON trust_history (sha256, id);
+                CREATE TRIGGER IF NOT EXISTS trust_history_no_update BEFORE UPDATE ON trust_history
+                BEGIN SELECT RAISE(ABORT, 'trust history is append-only'); END;
+                CREATE TRIGGER IF NOT EXISTS trust_history_no_delete BEFORE DELETE ON trust_history
+                BEGIN SELECT RAISE(ABORT, 'trust history is append-only'); END;",
+            )?;
+        }
+        if version < 3 {
+            tx.execute_batch(
+                "ALTER TABLE trust_records ADD COLUMN clean_runs INTEGER NOT NULL DEFAULT 0;",
+            )?;
+        }
+        if version < 4 {
+            // The behavior baseline, as JSON.
+            tx.execute_batch(
+                "ALTER TABLE trust_records ADD COLUMN baseline TEXT NOT NULL DEFAULT '{}';",
+            )?;
+        }
+        tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        tx.commit().context("migrate trust database")?;
+        Ok(())
+    }
+}
+
+impl TrustBackend for SqliteTrustBackend {
+    fn get(&self, sha256: &str) -> Result<Option<TrustRecord>> {
+        select_one(&self.conn, sha256)
+    }
+
+    fn list(&self) -> Result<Vec<TrustRecord>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT sha256, path, tier, runs, last_seen, clean_runs, baseline FROM trust_records ORDER BY sha256")?;
+        let rows = statement.query_map([], read_row)?;
+        rows.map(|row| row?).collect()
+    }
+
+    fn update(
+        &mut self,
+        sha256: &str,
+        apply: &mut dyn FnMut(Option<TrustRecord>) -> TrustUpdate,
+    ) -> Result<TrustRecord> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .context("lock trust database")?;
+        let update = apply(select_one(&tx, sha256)?);
+        upsert(&tx, &update.record)?;
+        if let Some(entry) = &update.history {
+            append_history(&tx, entry)?;
+        }
+        tx.commit().context("commit trust update")?;
+        Ok(update.record)
+    }
+
+    fn history(&self, sha256: &str) -> Result<Vec<TrustHistoryEntry>> {
+        let mut statement = self.conn.prepare(
+            "SELECT sha256, old_tier, new_tier, timestamp, source, session_id, reason
+             FROM trust_history WHERE sha256 = ?1 ORDER BY id",
+        )?;
+        let rows = statement.query_map([sha256], read_history_row)?;
+        rows.map(|row| row?).collect()
+    }
+
+    fn import(&mut self, records: Vec<TrustRecord>) -> Result<usize> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .context("lock trust database")?;
+        let mut added = 0;
+        for record in &records {
+            added += tx.execute(
+                "INSERT OR IGNORE INTO trust_records (sha256, path, tier, runs, last_seen, clean_runs, baseline)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    record.identity.sha256,
+                    record.identity.path.as_os_str().as_bytes(),
+                    record.tier.to_string(),
+                    record.runs,
+                    format_time(record.last_seen)?,
+                    record.clean_runs,
+                    serde_json::to_string(&record.baseline)?,
+                ],
+            )?;
+        }
+        tx.commit().context("commit trust import")?;
+        Ok(added)
+    }
+}
+
+fn select_one(conn: &Connection, sha256: &str) -> Result<Option<TrustRecord>> {
+    let row = conn
+        .query_row(
+            "SELECT sha256, path, tier, runs, last_seen, clean_runs, baseline FROM trust_records WHERE sha256 = ?1",
+            [sha256],
+            read_row,
+        )
+        .optional()?;
+    row.transpose()
+}
+
+fn upsert(conn: &Connection, record: &TrustRecord) -> Result<()> {
+    conn.execute(
+        "INSERT INTO trust_records (sha256, path, tier, runs, last_seen, clean_runs, baseline)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(sha256) DO UPDATE SET
+            path = excluded.path,
+            tier = excluded.tier,
+            runs = excluded.runs,
+            last_seen = excluded.last_seen,
+            clean_runs = excluded.clean_runs,
+            baseline = excluded.baseline",
+        params![
+            record.identity.sha256,
+            record.identity.path.as_os_str().as_bytes(),
+            record.tier.to_string(),
+            record.runs,
+            format_time(record.last_seen)?,
+            record.clean_runs,
+            serde_json::to_string(&record.baseline)?,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Column decoding that can fail outside SQLite's own types is surfaced
+/// through the inner `Result`.
+fn read_row(row: &Row<'_>) -> rusqlite::Result<Result<TrustRecord>> {
+    let sha256: String = row.get(0)?;
+    let path: Vec<u8> = row.get(1)?;
+    let tier: String = row.get(2)?;
+    let runs: u32 = row.get(3)?;
+    let last_seen: String = row.get(4)?;
+    let clean_runs: u32 = row.get(5)?;
+    let baseline: String = row.get(6)?;
+    Ok((|| {
+        Ok(TrustRecord {
+            identity: ExecutableIdentity {
+                path: PathBuf::from(OsStr::from_bytes(&path)),
+                sha256,
+            },
+            tier: tier.parse().map_err(anyhow::Error::msg)?,
+            runs,
+            clean_runs,
+            last_seen: OffsetDateTime::parse(&last_seen, &Rfc3339)
+                .with_context(|| format!("parse last_seen {last_seen}"))?,
+            baseline: serde_json::from_str(&baseline).context("parse behavior baseline")?,
+        })
+    })())
+}
+
+fn append_history(conn: &Connection, entry: &TrustHistoryEntry) -> Result<()> {
+    conn.execute(
+        "INSERT INTO trust_history (sha256, old_tier, new_tier, timestamp, source, session_id, reason)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            entry.sha256,
+            entry.old_tier.map(|tier| tier.to_string()),
+            entry.new_tier.to_string(),
+            format_time(entry.timestamp)?,
+            entry.source.as_str(),
+            entry.session_id.map(|id| id.to_string()),
+            entry.reason,
+        ],
+    )?;
+    Ok(())
+}
+
+fn read_history_row(row: &Row<'_>) -> rusqlite::Result<Result<TrustHistoryEntry>> {
+    let sha256: String = row.get(0)?;
+    let old_tier: Option<String> = row.get(1)?;
+    let new_tier: String = row.get(2)?;
+    let timestamp: String = row.get(3)?;
+    let source: String = row.get(4)?;
+    let session_id: Option<String> = row.get(5)?;
+    let reason: String = row.get(6)?;
+    Ok((|| {
+        Ok(TrustHistoryEntry {
+            sha256,
+            old_tier: old_tier
+                .map(|tier| tier.parse())
+                .transpose()
+                .map_err(anyhow::Error::msg)?,
+            new_tier: new_tier.parse().map_err(anyhow::Error::msg)?,
+            timestamp: OffsetDateTime::parse(&timestamp, &Rfc3339)
+                .with_context(|| format!("parse history timestamp {timestamp}"))?,
+            source: source.parse().map_err(anyhow::Error::msg)?,
+            session_id: session_id.map(|id| id.parse()).transpose()?,
+            reason,
+        })
+    })())
+}
+
+fn format_time(value: OffsetDateTime) -> Result<String> {
+    value.format(&Rfc3339).context("format trust timestamp")
+}
@@ -24,6 +24,27 @@ impl TrustTier {
             TrustTier::Red => "restricted",
         }
     }
+
+    /// One step towards Green; Green stays Green.
+    pub fn promoted(self) -> Self {
+        match self {
+            TrustTier::Red => TrustTier::Yellow,
+            TrustTier::Yellow | TrustTier::Green => TrustTier::Green,
+        }
+    }
+
+    /// True when `self` grants less trust than `other`.
+    pub fn is_below(self, other: TrustTier) -> bool {
+        self.rank() < other.rank()
+    }
+
+    fn rank(self) -> u8 {
+        match self {
+            TrustTier::Red => 0,
+            TrustTier::Yellow => 1,
+            TrustTier::Green => 2,
+        }
+    }
 }
 
 impl TrustSignal {
@@ -1,4 +1,6 @@
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
@@ -24,16 +26,51 @@ pub struct AccessAttempt {
     pub note: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AccessKind {
     Read,
     Write,
     Execute,
+    Delete,
+    Rename,
     Network,
     Device,
     SystemSocket,
 }
 
+impl AccessKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AccessKind::Read => "read",
+            AccessKind::Write => "write",
+            AccessKind::Execute => "execute",
+            AccessKind::Delete => "delete",
+            AccessKind::Rename => "rename",
+            AccessKind::Network => "network",
+            AccessKind::Device => "device",
+            AccessKind::SystemSocket => "system_socket",
+        }
+    }
+}
+
+impl FromStr for AccessKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().replace('-', "_").as_str() {
+            "read" => Ok(AccessKind::Read),
+            "write" => Ok(AccessKind::Write),
+            "execute" => Ok(AccessKind::Execute),
+            "delete" => Ok(AccessKind::Delete),
+            "rename" => Ok(AccessKind::Rename),
+            "network" => Ok(AccessKind::Network),
+            "device" => Ok(AccessKind::Device),
+            "system_socket" => Ok(AccessKind::SystemSocket),
+            _ => Err(format!("unknown access kind: {value}")),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AccessTarget {
     Path(PathBuf),
@@ -47,6 +84,20 @@ pub struct NetworkTarget {
     pub host: String,
     pub port: u16,
     pub protocol: String,
+    /// Bind targets are local addresses.
+    #[serde(default)]
+    pub direction: NetworkDirection,
+    /// Names the game looked up over DNS that answered with `host`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub names: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkDirection {
+    #[default]
+    Connect,
+    Bind,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -73,3 +124,56 @@ impl Default for LiveMonitorConfig {
         }
     }
 }
+
+/// Something a user asked the daemon to do to a running session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionAction {
+    Pause,
+    Resume,
+    Kill,
+}
+
+/// A session control action and what came of it, kept in the session report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlRecord {
+    pub timestamp: OffsetDateTime,
+    pub action: SessionAction,
+    /// Processes in the session's tree when the action was applied.
+    pub processes: usize,
+    pub result: ControlResult,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "message", rename_all = "snake_case")]
+pub enum ControlResult {
+    Applied,
+    /// The game exited after wineserver asked it to.
+    ExitedGracefully,
+    /// Processes outlived the grace period and were sent SIGKILL.
+    ForceKilled,
+    Failed(String),
+}
+
+impl fmt::Display for SessionAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            SessionAction::Pause => "pause",
+            SessionAction::Resume => "resume",
+            SessionAction::Kill => "kill",
+        };
+        f.write_str(label)
+    }
+}
+
+impl ControlRecord {
+    pub fn summary(&self) -> String {
+        let outcome = match &self.result {
+            ControlResult::Applied => "done".to_string(),
+            ControlResult::ExitedGracefully => "the game shut down cleanly".to_string(),
+            ControlResult::ForceKilled => "the game was force-killed".to_string(),
+            ControlResult::Failed(message) => format!("failed: {message}"),
+        };
+        format!("{} ({} processes): {outcome}", self.action, self.processes)
+    }
+}
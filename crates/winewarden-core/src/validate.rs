@@ -0,0 +1,821 @@
+//! Semantic config validation.
+//!
+//! Parsing only proves a config is well-formed TOML with the right shape.
+//! The checks here catch configs that parse but cannot work, and point each
+//! problem back at the line and column it came from.
+
+use std::fmt;
+use std::ops::Range;
+
+use thiserror::Error;
+use toml_edit::{ImDocument, Item, TableLike, Value};
+
+use crate::config::{Config, PolicyRule, RuleOutcome};
+use crate::devices::{DeviceClass, DeviceId};
+use crate::error::WineWardenError;
+use crate::paths::{PathAction, PREFIX_PLACEHOLDER};
+
+/// Placeholders understood by `expand_path_template`.
+pub const KNOWN_PLACEHOLDERS: &[&str] = &["HOME", "DATA_DIR", "CONFIG_DIR"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ConfigIssueKind {
+    #[error("{0}")]
+    Parse(String),
+    #[error("zone \"{zone}\" is set to {action:?} but has no redirect_to")]
+    MissingRedirectTarget { zone: String, action: PathAction },
+    #[error("unknown placeholder ${{{placeholder}}} (expected one of HOME, DATA_DIR, CONFIG_DIR)")]
+    UnknownPlaceholder { placeholder: String },
+    #[error("empty process pattern")]
+    EmptyPattern,
+    #[error("pattern \"{0}\" is both allowed and blocked")]
+    ConflictingPattern(String),
+    #[error("max_child_processes must be at least 1")]
+    ZeroChildProcesses,
+    #[error("{setting} is {score} but scores run from 0 to 100")]
+    ScoreOutOfRange { setting: String, score: u32 },
+    #[error("volume_factor must be greater than 0")]
+    ZeroVolumeFactor,
+    #[error("window_secs must be at least 1")]
+    ZeroWindow,
+    #[error("entropy_threshold must be above 0 and at most 8 bits per byte")]
+    EntropyOutOfRange,
+    #[error("empty domain")]
+    EmptyDomain,
+    #[error("\"{0}\" is an address; list it under cidrs")]
+    AddressAsDomain(String),
+    #[error("rule \"{rule}\" is set to {outcome:?} but has no redirect_to")]
+    MissingRuleRedirect { rule: String, outcome: RuleOutcome },
+    #[error("${{PREFIX}} can only start a path pattern")]
+    MisplacedPrefix,
+    #[error("invalid path pattern: {0}")]
+    InvalidPattern(String),
+    #[error("\"{0}\" is not a SHA-256 hex digest")]
+    InvalidSha256(String),
+    #[error("\"{0}\" is not a vendor:product USB id in hex")]
+    InvalidDeviceId(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyPart {
+    Key(String),
+    Index(usize),
+}
+
+/// Dotted path to the offending value, e.g. `sacred_zones[1].redirect_to`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigKey(pub Vec<KeyPart>);
+
+/// 1-based line and column in the config source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    pub kind: ConfigIssueKind,
+    pub key: ConfigKey,
+    pub location: Option<SourceLocation>,
+}
+
+impl ConfigKey {
+    fn key(mut self, key: &str) -> Self {
+        self.0.push(KeyPart::Key(key.to_string()));
+        self
+    }
+
+    fn index(mut self, index: usize) -> Self {
+        self.0.push(KeyPart::Index(index));
+        self
+    }
+}
+
+impl fmt::Display for ConfigKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (position, part) in self.0.iter().enumerate() {
+            match part {
+                KeyPart::Key(key) if position == 0 => write!(f, "{key}")?,
+                KeyPart::Key(key) => write!(f, ".{key}")?,
+                KeyPart::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(location) = self.location {
+            write!(f, "{}:{}: ", location.line, location.column)?;
+        }
+        if self.key.0.is_empty() {
+            write!(f, "{}", self.kind)
+        } else {
+            write!(f, "{}: {}", self.key, self.kind)
+        }
+    }
+}
+
+impl ConfigIssue {
+    /// Formats the issue as `origin:line:column: message`.
+    pub fn display_in(&self, origin: &str) -> String {
+        match self.location {
+            Some(_) => format!("{origin}:{self}"),
+            None => format!("{origin}: {self}"),
+        }
+    }
+
+    fn new(kind: ConfigIssueKind, key: ConfigKey) -> Self {
+        Self {
+            kind,
+            key,
+            location: None,
+        }
+    }
+}
+
+/// Checks an already-parsed config. Issues carry no source location.
+pub fn validate_config(config: &Config) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+
+    for (index, zone) in config.sacred_zones.iter().enumerate() {
+        let zone_key = ConfigKey::default().key("sacred_zones").index(index);
+        let per_kind = zone
+            .actions
+            .iter()
+            .map(|(kind, action)| (zone_key.clone().key("actions").key(kind), action));
+        let actions =
+            std::iter::once((zone_key.clone().key("redirect_to"), zone.action)).chain(per_kind);
+        for (key, action) in actions {
+            if matches!(action, PathAction::Redirect | PathAction::Virtualize)
+                && zone.redirect_to.is_none()
+            {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::MissingRedirectTarget {
+                        zone: zone.label.clone(),
+                        action,
+                    },
+                    key,
+                ));
+            }
+        }
+        if unknown_placeholders(&zone.path).is_empty() {
+            if let Some(error) = pattern_error(&zone.path, zone.regex) {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::InvalidPattern(error),
+                    zone_key.clone().key("path"),
+                ));
+            }
+        }
+        let templates = [
+            ("path", Some(&zone.path)),
+            ("redirect_to", zone.redirect_to.as_ref()),
+        ];
+        for (field, template) in templates {
+            let Some(template) = template else { continue };
+            for placeholder in unknown_placeholders(template) {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::UnknownPlaceholder { placeholder },
+                    zone_key.clone().key(field),
+                ));
+            }
+        }
+    }
+
+    for (index, rule) in config.rules.iter().enumerate() {
+        check_rule(
+            rule,
+            ConfigKey::default().key("rules").index(index),
+            &mut issues,
+        );
+    }
+
+    let process = &config.process;
+    let lists = [
+        ("allowed_patterns", &process.allowed_patterns),
+        ("blocked_patterns", &process.blocked_patterns),
+    ];
+    for (field, patterns) in lists {
+        for (index, pattern) in patterns.iter().enumerate() {
+            if pattern.trim().is_empty() {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::EmptyPattern,
+                    ConfigKey::default().key("process").key(field).index(index),
+                ));
+            }
+        }
+    }
+    for (index, pattern) in process.blocked_patterns.iter().enumerate() {
+        if !pattern.trim().is_empty() && process.allowed_patterns.contains(pattern) {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::ConflictingPattern(pattern.clone()),
+                ConfigKey::default()
+                    .key("process")
+                    .key("blocked_patterns")
+                    .index(index),
+            ));
+        }
+    }
+    if process.max_child_processes == 0 {
+        issues.push(ConfigIssue::new(
+            ConfigIssueKind::ZeroChildProcesses,
+            ConfigKey::default()
+                .key("process")
+                .key("max_child_processes"),
+        ));
+    }
+    for (index, rule) in config.network.rules.iter().enumerate() {
+        for (position, domain) in rule.domains.iter().enumerate() {
+            let kind = if domain.trim().trim_matches('.').is_empty() {
+                ConfigIssueKind::EmptyDomain
+            } else if domain.trim().parse::<std::net::IpAddr>().is_ok() {
+                ConfigIssueKind::AddressAsDomain(domain.clone())
+            } else {
+                continue;
+            };
+            issues.push(ConfigIssue::new(
+                kind,
+                ConfigKey::default()
+                    .key("network")
+                    .key("rules")
+                    .index(index)
+                    .key("domains")
+                    .index(position),
+            ));
+        }
+    }
+    if config.trust.promotion_min_score > 100 {
+        issues.push(ConfigIssue::new(
+            ConfigIssueKind::ScoreOutOfRange {
+                setting: "promotion_min_score".to_string(),
+                score: config.trust.promotion_min_score,
+            },
+            ConfigKey::default().key("trust").key("promotion_min_score"),
+        ));
+    }
+    if config.trust.scoring.suspicion_threshold > 100 {
+        issues.push(ConfigIssue::new(
+            ConfigIssueKind::ScoreOutOfRange {
+                setting: "suspicion_threshold".to_string(),
+                score: config.trust.scoring.suspicion_threshold,
+            },
+            ConfigKey::default()
+                .key("trust")
+                .key("scoring")
+                .key("suspicion_threshold"),
+        ));
+    }
+    let heuristics = &config.write_heuristics;
+    let heuristics_key = ConfigKey::default().key("write_heuristics");
+    if heuristics.window_secs == 0 {
+        issues.push(ConfigIssue::new(
+            ConfigIssueKind::ZeroWindow,
+            heuristics_key.clone().key("window_secs"),
+        ));
+    }
+    let entropy = heuristics.entropy_threshold;
+    if entropy.is_nan() || entropy <= 0.0 || entropy > 8.0 {
+        issues.push(ConfigIssue::new(
+            ConfigIssueKind::EntropyOutOfRange,
+            heuristics_key.clone().key("entropy_threshold"),
+        ));
+    }
+    for (index, pattern) in heuristics.protected_paths.iter().enumerate() {
+        check_path_pattern(
+            pattern,
+            heuristics_key.clone().key("protected_paths").index(index),
+            &mut issues,
+        );
+    }
+    for class in DeviceClass::ALL {
+        let devices = config.devices.class(class);
+        let class_key = ConfigKey::default().key("devices").key(class.as_str());
+        for (index, pattern) in devices.paths.iter().enumerate() {
+            if let Some(error) = pattern_error(pattern, false) {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::InvalidPattern(error),
+                    class_key.clone().key("paths").index(index),
+                ));
+            }
+        }
+        for (index, id) in devices.ids.iter().enumerate() {
+            if id.parse::<DeviceId>().is_err() {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueKind::InvalidDeviceId(id.clone()),
+                    class_key.clone().key("ids").index(index),
+                ));
+            }
+        }
+    }
+    let volume_factor = config.trust.baseline.volume_factor;
+    if volume_factor.is_nan() || volume_factor <= 0.0 {
+        issues.push(ConfigIssue::new(
+            ConfigIssueKind::ZeroVolumeFactor,
+            ConfigKey::default()
+                .key("trust")
+                .key("baseline")
+                .key("volume_factor"),
+        ));
+    }
+
+    issues
+}
+
+/// Parses and checks config source, attaching line and column to each issue.
+pub fn validate_toml_str(contents: &str) -> Vec<ConfigIssue> {
+    let document = match ImDocument::parse(contents) {
+        Ok(document) => document,
+        Err(err) => {
+            return vec![ConfigIssue {
+                kind: ConfigIssueKind::Parse(err.message().to_string()),
+                key: ConfigKey::default(),
+                location: err.span().map(|span| location_of(contents, span.start)),
+            }];
+        }
+    };
+    let config: Config = match toml::from_str(contents) {
+        Ok(config) => config,
+        Err(err) => {
+            return vec![ConfigIssue {
+                kind: ConfigIssueKind::Parse(err.message().to_string()),
+                key: ConfigKey::default(),
+                location: err.span().map(|span| location_of(contents, span.start)),
+            }];
+        }
+    };
+
+    let mut issues = validate_config(&config);
+    for issue in &mut issues {
+        issue.location =
+            span_of(document.as_item(), &issue.key).map(|span| location_of(contents, span.start));
+    }
+    issues.sort_by_key(|issue| {
+        issue
+            .location
+            .map(|location| (location.line, location.column))
+    });
+    issues
+}
+
+/// Fails with `InvalidConfig` listing every issue, one per line.
+pub fn ensure_valid(issues: &[ConfigIssue], origin: &str) -> Result<(), WineWardenError> {
+    if issues.is_empty() {
+        return Ok(());
+    }
+    let lines: Vec<String> = issues
+        .iter()
+        .map(|issue| issue.display_in(origin))
+        .collect();
+    Err(WineWardenError::InvalidConfig(lines.join("\n")))
+}
+
+fn check_rule(rule: &PolicyRule, rule_key: ConfigKey, issues: &mut Vec<ConfigIssue>) {
+    if matches!(rule.then, RuleOutcome::Redirect | RuleOutcome::Virtualize)
+        && rule.redirect_to.is_none()
+    {
+        issues.push(ConfigIssue::new(
+            ConfigIssueKind::MissingRuleRedirect {
+                rule: rule.label.clone(),
+                outcome: rule.then,
+            },
+            rule_key.clone().key("redirect_to"),
+        ));
+    }
+    if let Some(template) = &rule.redirect_to {
+        for placeholder in unknown_placeholders(template) {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::UnknownPlaceholder { placeholder },
+                rule_key.clone().key("redirect_to"),
+            ));
+        }
+    }
+
+    let when_key = rule_key.key("when");
+    for (index, pattern) in rule.when.path.iter().enumerate() {
+        check_path_pattern(pattern, when_key.clone().key("path").index(index), issues);
+    }
+    for (index, digest) in rule.when.sha256.iter().enumerate() {
+        if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::InvalidSha256(digest.clone()),
+                when_key.clone().key("sha256").index(index),
+            ));
+        }
+    }
+    for (index, host) in rule.when.host.iter().enumerate() {
+        if host.trim().trim_matches('.').is_empty() {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::EmptyDomain,
+                when_key.clone().key("host").index(index),
+            ));
+        }
+    }
+}
+
+/// Checks a path glob that may start with `${PREFIX}`.
+fn check_path_pattern(pattern: &str, key: ConfigKey, issues: &mut Vec<ConfigIssue>) {
+    let rest = pattern.strip_prefix(PREFIX_PLACEHOLDER).unwrap_or(pattern);
+    if rest.contains(PREFIX_PLACEHOLDER) {
+        issues.push(ConfigIssue::new(ConfigIssueKind::MisplacedPrefix, key));
+        return;
+    }
+    let unknown = unknown_placeholders(rest);
+    if !unknown.is_empty() {
+        for placeholder in unknown {
+            issues.push(ConfigIssue::new(
+                ConfigIssueKind::UnknownPlaceholder { placeholder },
+                key.clone(),
+            ));
+        }
+        return;
+    }
+    if let Some(error) = pattern_error(rest, false) {
+        issues.push(ConfigIssue::new(
+            ConfigIssueKind::InvalidPattern(error),
+            key,
+        ));
+    }
+}
+
+/// Syntax errors in a glob or regex path. Known placeholders stand in for
+/// plain directories; unknown ones are reported separately.
+fn pattern_error(template: &str, regex: bool) -> Option<String> {
+    let mut pattern = template.to_string();
+    for placeholder in KNOWN_PLACEHOLDERS {
+        pattern = pattern.replace(&format!("${{{placeholder}}}"), "/placeholder");
+    }
+    if regex {
+        regex::Regex::new(&pattern).err().map(|err| match err {
+            regex::Error::Syntax(message) => message.lines().last().unwrap_or_default().to_string(),
+            other => other.to_string(),
+        })
+    } else {
+        globset::GlobBuilder::new(&pattern)
+            .literal_separator(true)
+            .build()
+            .err()
+            .map(|err| err.kind().to_string())
+    }
+}
+
+fn unknown_placeholders(template: &str) -> Vec<String> {
+    let mut unknown = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        let after = &rest[start + 2..];
+        match after.find('}') {
+            Some(end) => {
+                let name = &after[..end];
+                if !KNOWN_PLACEHOLDERS.contains(&name) {
+                    unknown.push(name.to_string());
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                unknown.push(after.to_string());
+                break;
+            }
+        }
+    }
+    unknown
+}
+
+/// Walks the document along `key`, returning the deepest span found. Missing
+/// keys therefore point at their parent table.
+fn span_of(root: &Item, key: &ConfigKey) -> Option<Range<usize>> {
+    let mut span = None;
+    let mut node = Node::Item(root);
+
+    for part in &key.0 {
+        let (next, next_span) = match part {
+            KeyPart::Key(name) => {
+                let Some((key, child)) = node
+                    .table_like()
+                    .and_then(|table| table.get_key_value(name))
+                else {
+                    break;
+                };
+                (Node::Item(child), key.span().or_else(|| child.span()))
+            }
+            KeyPart::Index(index) => match node {
+                Node::Item(Item::ArrayOfTables(tables)) => match tables.get(*index) {
+                    Some(table) => (Node::Table(table), table.span()),
+                    None => break,
+                },
+                Node::Item(Item::Value(Value::Array(array))) | Node::Value(Value::Array(array)) => {
+                    match array.get(*index) {
+                        Some(element) => (Node::Value(element), element.span()),
+                        None => break,
+                    }
+                }
+                _ => break,
+            },
+        };
+        node = next;
+        span = next_span.or(span);
+    }
+    span
+}
+
+#[derive(Clone, Copy)]
+enum Node<'a> {
+    Item(&'a Item),
+    Table(&'a toml_edit::Table),
+    Value(&'a Value),
+}
+
+impl<'a> Node<'a> {
+    fn table_like(self) -> Option<&'a dyn TableLike> {
+        match self {
+            Node::Item(item) => item.as_table_like(),
+            Node::Table(table) => Some(table),
+            Node::Value(value) => value.as_inline_table().map(|table| table as &dyn TableLike),
+        }
+    }
+}
+
+fn location_of(contents: &str, offset: usize) -> SourceLocation {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+    SourceLocation {
+        line,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AccessKind;
+
+    fn issues_for(contents: &str) -> Vec<ConfigIssue> {
+        validate_toml_str(contents)
+    }
+
+    fn default_toml() -> String {
+        Config::default_config().to_toml_string().unwrap()
+    }
+
+    #[test]
+    fn test_default_config_is_valid() {
+        assert!(validate_config(&Config::default_config()).is_empty());
+        assert!(issues_for(&default_toml()).is_empty());
+    }
+
+    #[test]
+    fn test_missing_redirect_target_points_at_zone() {
+        let contents = default_toml().replace("redirect_to = \"${DATA_DIR}/virtual/config\"\n", "");
+        let issues = issues_for(&contents);
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(
+            issues[0].kind,
+            ConfigIssueKind::MissingRedirectTarget {
+                action: PathAction::Redirect,
+                ..
+            }
+        ));
+        assert_eq!(issues[0].key.to_string(), "sacred_zones[3].redirect_to");
+
+        let location = issues[0].location.expect("location");
+        let line = contents.lines().nth(location.line - 1).unwrap();
+        assert_eq!(line.trim(), "[[sacred_zones]]");
+    }
+
+    #[test]
+    fn test_unknown_placeholder() {
+        let contents = default_toml().replace("${HOME}/.ssh", "${HOEM}/.ssh");
+        let issues = issues_for(&contents);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            issues[0].kind,
+            ConfigIssueKind::UnknownPlaceholder {
+                placeholder: "HOEM".to_string()
+            }
+        );
+        let location = issues[0].location.expect("location");
+        let line = contents.lines().nth(location.line - 1).unwrap();
+        assert!(line.contains("${HOEM}"));
+        assert_eq!(location.column, 1);
+    }
+
+    #[test]
+    fn test_process_checks() {
+        let mut config = Config::default_config();
+        config.process.allowed_patterns.push(" ".to_string());
+        config
+            .process
+            .blocked_patterns
+            .push("wineserver".to_string());
+        config.process.max_child_processes = 0;
+
+        let kinds: Vec<ConfigIssueKind> = validate_config(&config)
+            .into_iter()
+            .map(|issue| issue.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ConfigIssueKind::EmptyPattern,
+                ConfigIssueKind::ConflictingPattern("wineserver".to_string()),
+                ConfigIssueKind::ZeroChildProcesses,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_network_rule_checks() {
+        let contents = format!(
+            "{}\n[[network.rules]]\nlabel = \"LAN\"\naction = \"deny\"\ndomains = [\"\", \"10.0.0.1\"]\n",
+            default_toml().replace("rules = []\n", "")
+        );
+        let issues = issues_for(&contents);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].kind, ConfigIssueKind::EmptyDomain);
+        assert_eq!(issues[0].key.to_string(), "network.rules[0].domains[0]");
+        assert_eq!(
+            issues[1].kind,
+            ConfigIssueKind::AddressAsDomain("10.0.0.1".to_string())
+        );
+
+        let bad_cidr = format!(
+            "{}\n[[network.rules]]\nlabel = \"LAN\"\naction = \"deny\"\ncidrs = [\"10.0.0.0/40\"]\n",
+            default_toml().replace("rules = []\n", "")
+        );
+        let issues = issues_for(&bad_cidr);
+        assert!(matches!(issues[0].kind, ConfigIssueKind::Parse(_)));
+        assert!(issues[0].location.is_some());
+
+        let enforced = format!(
+            "{}\n[[network.rules]]\nlabel = \"Steam\"\naction = \"allow\"\ndomains = [\"steampowered.com\"]\n",
+            default_toml()
+                .replace("rules = []\n", "")
+                .replace("mode = \"observe\"", "mode = \"enforce\"")
+        );
+        assert!(issues_for(&enforced).is_empty());
+    }
+
+    #[test]
+    fn test_per_kind_zone_actions() {
+        let zone = "\n[[sacred_zones]]\nlabel = \"My Games\"\npath = \"${HOME}/Documents/My Games\"\naction = \"allow\"\nactions = { write = \"virtualize\", delete = \"deny\" }\n";
+        let contents = format!("{}{zone}", default_toml());
+        let issues = issues_for(&contents);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            issues[0].kind,
+            ConfigIssueKind::MissingRedirectTarget {
+                zone: "My Games".to_string(),
+                action: PathAction::Virtualize
+            }
+        );
+        assert_eq!(issues[0].key.to_string(), "sacred_zones[4].actions.write");
+        let location = issues[0].location.expect("location");
+        assert!(contents
+            .lines()
+            .nth(location.line - 1)
+            .unwrap()
+            .starts_with("actions = "));
+
+        let fixed = contents.replace(
+            "action = \"allow\"\n",
+            "action = \"allow\"\nredirect_to = \"${DATA_DIR}/virtual/games\"\n",
+        );
+        assert!(issues_for(&fixed).is_empty());
+        let config = Config::from_toml_str(&fixed).unwrap();
+        let actions = config.sacred_zones[4].actions;
+        assert_eq!(
+            actions.for_kind(AccessKind::Read, PathAction::Allow),
+            PathAction::Allow
+        );
+        assert_eq!(
+            actions.for_kind(AccessKind::Delete, PathAction::Allow),
+            PathAction::Deny
+        );
+        assert_eq!(
+            actions.for_kind(AccessKind::Rename, PathAction::Allow),
+            PathAction::Virtualize
+        );
+    }
+
+    #[test]
+    fn test_zone_pattern_checks() {
+        let contents = format!(
+            "{}\n[[sacred_zones]]\nlabel = \"Wallets\"\npath = \"${{HOME}}/(wallet\"\naction = \"deny\"\nregex = true\n\n[[sacred_zones]]\nlabel = \"Keys\"\npath = \"${{HOME}}/**/*.[kdbx\"\naction = \"deny\"\n",
+            default_toml()
+        );
+        let issues = issues_for(&contents);
+        assert_eq!(issues.len(), 2);
+        assert!(matches!(issues[0].kind, ConfigIssueKind::InvalidPattern(_)));
+        assert_eq!(issues[0].key.to_string(), "sacred_zones[4].path");
+        assert_eq!(issues[1].key.to_string(), "sacred_zones[5].path");
+
+        let valid = contents
+            .replace("/(wallet", "/(wallet|keys)")
+            .replace(".[kdbx", ".kdbx");
+        assert!(issues_for(&valid).is_empty());
+    }
+
+    #[test]
+    fn test_policy_rule_checks() {
+        let contents = format!(
+            "{}\n[[rules]]\nlabel = \"Games\"\nwhen = {{ path = [\"${{HOME}}/${{PREFIX}}\", \"/games/[a\"], sha256 = [\"abc\"] }}\nthen = \"redirect\"\n",
+            default_toml()
+        );
+        let issues = issues_for(&contents);
+        let kinds: Vec<&ConfigIssueKind> = issues.iter().map(|issue| &issue.kind).collect();
+        assert_eq!(issues.len(), 4, "{kinds:?}");
+        assert_eq!(issues[0].key.to_string(), "rules[7].redirect_to");
+        assert!(matches!(
+            issues[0].kind,
+            ConfigIssueKind::MissingRuleRedirect {
+                outcome: RuleOutcome::Redirect,
+                ..
+            }
+        ));
+        assert_eq!(issues[1].kind, ConfigIssueKind::MisplacedPrefix);
+        assert_eq!(issues[1].key.to_string(), "rules[7].when.path[0]");
+        assert!(matches!(issues[2].kind, ConfigIssueKind::InvalidPattern(_)));
+        assert_eq!(
+            issues[3].kind,
+            ConfigIssueKind::InvalidSha256("abc".to_string())
+        );
+        let location = issues[3].location.expect("location");
+        let line = contents.lines().nth(location.line - 1).unwrap();
+        assert!(line.starts_with("when = "));
+    }
+
+    #[test]
+    fn test_syntax_error_has_location() {
+        let issues = issues_for("[trust]\ndefault_tier = \n");
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0].kind, ConfigIssueKind::Parse(_)));
+        assert_eq!(issues[0].location.map(|location| location.line), Some(2));
+    }
+
+    #[test]
+    fn test_ensure_valid_uses_invalid_config() {
+        let mut config = Config::default_config();
+        config.process.max_child_processes = 0;
+        let err = ensure_valid(&validate_config(&config), "config.toml").unwrap_err();
+        assert!(matches!(err, WineWardenError::InvalidConfig(_)));
+        assert!(err.to_string().contains("process.max_child_processes"));
+    }
+
+    #[test]
+    fn test_promotion_score_range() {
+        let contents =
+            default_toml().replace("promotion_min_score = 60", "promotion_min_score = 120");
+        let issues = issues_for(&contents);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            issues[0].kind.to_string(),
+            "promotion_min_score is 120 but scores run from 0 to 100"
+        );
+        assert_eq!(issues[0].key.to_string(), "trust.promotion_min_score");
+    }
+
+    #[test]
+    fn test_write_heuristics_settings() {
+        let contents = default_toml()
+            .replace("window_secs = 10", "window_secs = 0")
+            .replace("\"${HOME}/Pictures/**\"", "\"${HOME}/${PREFIX}/Pictures\"");
+        let issues = issues_for(&contents);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].kind, ConfigIssueKind::ZeroWindow);
+        assert_eq!(issues[1].kind, ConfigIssueKind::MisplacedPrefix);
+        assert_eq!(
+            issues[1].key.to_string(),
+            "write_heuristics.protected_paths[1]"
+        );
+        assert!(issues[1].location.is_some());
+    }
+
+    #[test]
+    fn test_device_class_settings() {
+        let contents = default_toml()
+            .replace("\"/dev/snd/*\"", "\"/dev/snd/[pcm\"")
+            .replace("\"057e:*\"", "\"nintendo\"");
+        let issues = issues_for(&contents);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(
+            issues[0].kind,
+            ConfigIssueKind::InvalidDeviceId("nintendo".to_string())
+        );
+        assert_eq!(issues[0].key.to_string(), "devices.gamepad.ids[5]");
+        assert!(matches!(issues[1].kind, ConfigIssueKind::InvalidPattern(_)));
+        assert_eq!(issues[1].key.to_string(), "devices.audio.paths[0]");
+        assert!(issues[1].location.is_some());
+    }
+
+    #[test]
+    fn test_baseline_volume_factor() {
+        let contents = default_toml().replace("volume_factor = 3.0", "volume_factor = 0.0");
+        let issues = issues_for(&contents);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, ConfigIssueKind::ZeroVolumeFactor);
+        assert_eq!(issues[0].key.to_string(), "trust.baseline.volume_factor");
+        assert!(issues[0].location.is_some());
+    }
+}
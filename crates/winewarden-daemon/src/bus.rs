@@ -0,0 +1,79 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use monitor::EventSink;
+use policy_engine::TrustScore;
+use reporting::ReportEvent;
+use uuid::Uuid;
+use winewarden_core::ipc::{SessionEvent, SessionEventPayload, SubscribeRequest};
+use winewarden_core::types::RunMetadata;
+
+struct Subscriber {
+    request: SubscribeRequest,
+    sender: Sender<SessionEvent>,
+}
+
+/// Fans session events out to every subscriber whose filters match.
+/// Subscribers are dropped the first time a send fails.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&self, request: SubscribeRequest) -> Receiver<SessionEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.lock().push(Subscriber { request, sender });
+        receiver
+    }
+
+    pub fn publish(&self, event: SessionEvent) {
+        self.lock().retain(|subscriber| {
+            !subscriber.request.matches(&event) || subscriber.sender.send(event.clone()).is_ok()
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Subscriber>> {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Publishes a monitored session onto the bus.
+pub struct BusSink {
+    pub bus: EventBus,
+}
+
+impl EventSink for BusSink {
+    fn session_started(&self, metadata: &RunMetadata) {
+        self.bus.publish(SessionEvent::now(
+            metadata.session_id,
+            SessionEventPayload::Started {
+                executable: metadata.executable.clone(),
+                trust_tier: metadata.trust_tier,
+            },
+        ));
+    }
+
+    fn event(&self, session_id: Uuid, event: &ReportEvent) {
+        self.bus.publish(SessionEvent::now(
+            session_id,
+            SessionEventPayload::Access {
+                attempt: Box::new(event.attempt.clone()),
+                decision: event.decision.clone(),
+            },
+        ));
+    }
+
+    fn trust_score(&self, session_id: Uuid, score: &TrustScore) {
+        self.bus.publish(SessionEvent::now(
+            session_id,
+            SessionEventPayload::TrustScore {
+                score: score.score,
+                recommended_tier: score.recommended_tier,
+                assessment: score.assessment.clone(),
+            },
+        ));
+    }
+}
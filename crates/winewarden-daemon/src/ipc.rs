@@ -1,32 +1,71 @@
 use std::fs;
-use std::io::{BufRead, BufReader, BufWriter, Write};
-use std::os::unix::net::{UnixListener, UnixStream};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
 use std::os::unix::fs::PermissionsExt;
 use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use time::OffsetDateTime;
 
-use winewarden_core::ipc::{WineWardenRequest, WineWardenResponse, RunResult, StatusPayload};
+use winewarden_core::ipc::{
+    read_frame, write_frame, ClientFrame, ErrorPayload, Hello, RunResult, ServerFrame,
+    SessionEvent, WineWardenRequest, WineWardenResponse, LEGACY_FIRST_BYTE,
+};
+use winewarden_core::store::EventStore;
 
-use crate::store::EventStore;
+use crate::bus::EventBus;
+use crate::reload::LiveConfig;
+use crate::scheduler::Scheduler;
+use crate::sessions::SessionRegistry;
+
+/// Answers one request; shared by every connection thread.
+pub trait Handler:
+    Fn(WineWardenRequest, &Arc<Mutex<DaemonState>>) -> Result<WineWardenResponse>
+    + Send
+    + Sync
+    + 'static
+{
+}
+
+impl<F> Handler for F where
+    F: Fn(WineWardenRequest, &Arc<Mutex<DaemonState>>) -> Result<WineWardenResponse>
+        + Send
+        + Sync
+        + 'static
+{
+}
+
+/// How often an idle subscription checks whether its client went away.
+const SUBSCRIBER_POLL: Duration = Duration::from_secs(1);
+/// How long a client has to finish the handshake, or to send its request
+/// on a legacy connection, before the connection is dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Connections served at once, subscriptions included. Further clients are
+/// refused until one closes.
+const MAX_CONNECTIONS: usize = 64;
 
 pub struct DaemonState {
     pub started_at: OffsetDateTime,
-    pub active_sessions: u32,
+    pub sessions: SessionRegistry,
     pub last_session_id: Option<uuid::Uuid>,
     pub last_summary: Option<String>,
-    #[allow(dead_code)]
     pub store: EventStore,
+    pub bus: EventBus,
+    pub scheduler: Scheduler,
+    pub config: LiveConfig,
+    /// Set once SIGTERM arrives; new runs are refused from then on.
+    pub shutting_down: bool,
 }
 
-pub fn serve(
-    socket_path: &Path,
-    state: Arc<Mutex<DaemonState>>,
-    handler: impl Fn(WineWardenRequest, &Arc<Mutex<DaemonState>>) -> Result<WineWardenResponse> + Send + Sync + 'static,
-) -> Result<()> {
+/// Binds the daemon socket, replacing a stale one. Only used when systemd
+/// did not pass in a listening socket.
+pub fn bind(socket_path: &Path) -> Result<UnixListener> {
     if let Some(parent) = socket_path.parent() {
         fs::create_dir_all(parent)
             .with_context(|| format!("create socket dir {}", parent.display()))?;
@@ -40,44 +79,268 @@ pub fn serve(
         .with_context(|| format!("bind socket {}", socket_path.display()))?;
     fs::set_permissions(socket_path, fs::Permissions::from_mode(0o600))
         .with_context(|| format!("set socket permissions {}", socket_path.display()))?;
+    Ok(listener)
+}
 
-    for stream in listener.incoming() {
-        let stream = stream?;
-        check_peer_uid(&stream)?;
-        let response = handle_connection(stream, &handler, &state)?;
-        if let Some(response) = response {
-            update_state(&state, &response);
+/// Accepts connections for the life of the daemon. With a `heartbeat`, the
+/// loop wakes at least that often to call `tick`, so the ticks stop when
+/// the loop does.
+pub fn serve(
+    listener: UnixListener,
+    state: Arc<Mutex<DaemonState>>,
+    handler: impl Handler,
+    heartbeat: Option<Duration>,
+    mut tick: impl FnMut(),
+) -> Result<()> {
+    // Each connection gets its own thread, so a long run does not block
+    // status queries and a misbehaving client cannot stop the accept loop.
+    let handler = Arc::new(handler);
+    let open = Arc::new(AtomicUsize::new(0));
+    let mut last_tick = Instant::now();
+    loop {
+        if let Some(period) = heartbeat {
+            if last_tick.elapsed() >= period {
+                tick();
+                last_tick = Instant::now();
+            }
+            if !wait_readable(&listener, period.saturating_sub(last_tick.elapsed()))? {
+                continue;
+            }
         }
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                eprintln!("accept failed: {err}");
+                continue;
+            }
+        };
+        if let Err(err) = check_peer_uid(&stream) {
+            eprintln!("refused connection: {err:#}");
+            continue;
+        }
+        let Some(slot) = ConnectionSlot::claim(&open) else {
+            eprintln!("refused connection: {MAX_CONNECTIONS} connections already open");
+            continue;
+        };
+        let handler = Arc::clone(&handler);
+        let state = Arc::clone(&state);
+        thread::spawn(move || {
+            let _slot = slot;
+            if let Err(err) = handle_connection(stream, handler.as_ref(), &state) {
+                eprintln!("connection failed: {err:#}");
+            }
+        });
     }
-    Ok(())
 }
 
+/// One of the `MAX_CONNECTIONS`, held by a connection's thread until it
+/// ends.
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    fn claim(open: &Arc<AtomicUsize>) -> Option<Self> {
+        open.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+            (count < MAX_CONNECTIONS).then_some(count + 1)
+        })
+        .ok()?;
+        Some(Self(Arc::clone(open)))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Waits up to `timeout` for a connection to accept.
+fn wait_readable(listener: &UnixListener, timeout: Duration) -> Result<bool> {
+    let mut poll_fd = libc::pollfd {
+        fd: listener.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let millis = libc::c_int::try_from(timeout.as_millis()).unwrap_or(libc::c_int::MAX);
+    match unsafe { libc::poll(&mut poll_fd, 1, millis) } {
+        -1 => match io::Error::last_os_error() {
+            err if err.kind() == io::ErrorKind::Interrupted => Ok(false),
+            err => Err(err).context("poll daemon socket"),
+        },
+        ready => Ok(ready > 0),
+    }
+}
+
+/// Picks the protocol from the first byte: `{` starts a legacy JSON line,
+/// anything else is a length-prefixed frame. Reads time out until the
+/// handshake is done, so a silent client cannot hold the thread.
 fn handle_connection(
     stream: UnixStream,
-    handler: &impl Fn(WineWardenRequest, &Arc<Mutex<DaemonState>>) -> Result<WineWardenResponse>,
+    handler: &impl Handler,
     state: &Arc<Mutex<DaemonState>>,
-) -> Result<Option<WineWardenResponse>> {
+) -> Result<()> {
+    stream
+        .set_read_timeout(Some(HANDSHAKE_TIMEOUT))
+        .context("set handshake timeout")?;
     let mut reader = BufReader::new(stream.try_clone()?);
-    let mut line = String::new();
-    let bytes = reader.read_line(&mut line)?;
-    if bytes == 0 {
-        return Ok(None);
+    let writer = BufWriter::new(stream);
+    let first = match reader
+        .fill_buf()
+        .context("wait for the client's first message")?
+        .first()
+    {
+        Some(byte) => *byte,
+        None => return Ok(()),
+    };
+    if first == LEGACY_FIRST_BYTE {
+        handle_legacy(reader, writer, handler, state)
+    } else {
+        handle_framed(reader, writer, handler, state)
     }
-    let request: WineWardenRequest = serde_json::from_str(&line).context("parse request")?;
-    let response = handler(request, state)?;
+}
+
+/// Compatibility shim for clients that send one newline-terminated JSON
+/// request and read one JSON line back.
+fn handle_legacy(
+    mut reader: BufReader<UnixStream>,
+    mut writer: BufWriter<UnixStream>,
+    handler: &impl Handler,
+    state: &Arc<Mutex<DaemonState>>,
+) -> Result<()> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let response = match serde_json::from_str(&line).context("parse request") {
+        Ok(request) => respond(handler, request, state),
+        Err(err) => error_response(err),
+    };
 
-    let mut writer = BufWriter::new(stream);
     let payload = serde_json::to_string(&response).context("serialize response")?;
     writer.write_all(payload.as_bytes())?;
     writer.write_all(b"\n")?;
     writer.flush()?;
+    Ok(())
+}
+
+/// Handshake, then answer requests until the client disconnects. Each
+/// response carries the id of the request it answers.
+fn handle_framed(
+    mut reader: BufReader<UnixStream>,
+    mut writer: BufWriter<UnixStream>,
+    handler: &impl Handler,
+    state: &Arc<Mutex<DaemonState>>,
+) -> Result<()> {
+    let hello = match read_frame(&mut reader).context("read the client's hello")? {
+        Some(ClientFrame::Hello(hello)) => hello,
+        Some(ClientFrame::Request { .. }) => {
+            return reject(
+                &mut writer,
+                "expected a hello before the first request".to_string(),
+            )
+        }
+        None => return Ok(()),
+    };
+    if let Err(mismatch) = hello.check_compatible() {
+        return reject(&mut writer, mismatch.to_string());
+    }
+    write_frame(&mut writer, &ServerFrame::Welcome(Hello::current()))?;
+    // Clients may idle between requests once connected
+    reader.get_ref().set_read_timeout(None)?;
+
+    loop {
+        let frame = match read_frame(&mut reader) {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return Ok(()),
+            // The stream cannot be resynchronised after a bad frame.
+            Err(err) => return reject(&mut writer, format!("{err:#}")),
+        };
+        let ClientFrame::Request { id, request } = frame else {
+            return reject(&mut writer, "unexpected hello after handshake".to_string());
+        };
+        if let WineWardenRequest::Subscribe(subscribe) = request {
+            let events = lock(state).bus.subscribe(subscribe);
+            let response = WineWardenResponse::Subscribed;
+            write_frame(&mut writer, &ServerFrame::Response { id, response })?;
+            stream_events(id, events, reader, writer);
+            return Ok(());
+        }
+        let response = respond(handler, request, state);
+        write_frame(&mut writer, &ServerFrame::Response { id, response })?;
+    }
+}
+
+/// Runs the handler, turning a failed request into an error response so the
+/// client hears about it and the connection stays usable.
+fn respond(
+    handler: &impl Handler,
+    request: WineWardenRequest,
+    state: &Arc<Mutex<DaemonState>>,
+) -> WineWardenResponse {
+    match handler(request, state) {
+        Ok(response) => {
+            update_state(state, &response);
+            response
+        }
+        Err(err) => error_response(err),
+    }
+}
+
+fn error_response(err: anyhow::Error) -> WineWardenResponse {
+    WineWardenResponse::Error(ErrorPayload {
+        message: format!("{err:#}"),
+    })
+}
 
-    Ok(Some(response))
+/// Forwards bus events to a subscriber until it disconnects. The client
+/// sends nothing after subscribing, so a readable socket means EOF.
+fn stream_events(
+    id: u64,
+    events: Receiver<SessionEvent>,
+    mut reader: BufReader<UnixStream>,
+    mut writer: BufWriter<UnixStream>,
+) {
+    loop {
+        match events.recv_timeout(SUBSCRIBER_POLL) {
+            Ok(event) => {
+                let response = WineWardenResponse::Event(event);
+                if write_frame(&mut writer, &ServerFrame::Response { id, response }).is_err() {
+                    return;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) if !peer_closed(&mut reader) => {}
+            Err(_) => return,
+        }
+    }
+}
+
+fn peer_closed(reader: &mut BufReader<UnixStream>) -> bool {
+    if reader.get_ref().set_nonblocking(true).is_err() {
+        return true;
+    }
+    let mut byte = [0u8; 1];
+    let closed = match reader.get_mut().read(&mut byte) {
+        Ok(0) => true,
+        Ok(_) => false,
+        Err(err) => err.kind() != io::ErrorKind::WouldBlock,
+    };
+    closed || reader.get_ref().set_nonblocking(false).is_err()
+}
+
+fn reject(writer: &mut BufWriter<UnixStream>, message: String) -> Result<()> {
+    write_frame(
+        writer,
+        &ServerFrame::Rejected {
+            server: Hello::current(),
+            error: ErrorPayload { message },
+        },
+    )
 }
 
 fn check_peer_uid(stream: &UnixStream) -> Result<()> {
     let fd = stream.as_raw_fd();
-    let mut cred: libc::ucred = libc::ucred { pid: 0, uid: 0, gid: 0 };
+    let mut cred: libc::ucred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
     let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
     let rc = unsafe {
         libc::getsockopt(
@@ -98,21 +361,20 @@ fn check_peer_uid(stream: &UnixStream) -> Result<()> {
     Ok(())
 }
 
+fn lock(state: &Arc<Mutex<DaemonState>>) -> std::sync::MutexGuard<'_, DaemonState> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
 fn update_state(state: &Arc<Mutex<DaemonState>>, response: &WineWardenResponse) {
-    let mut state = match state.lock() {
-        Ok(guard) => guard,
-        Err(poisoned) => poisoned.into_inner(),
-    };
-    match response {
-        WineWardenResponse::RunResult(RunResult { session_id, summary }) => {
-            state.last_session_id = Some(*session_id);
-            state.last_summary = Some(summary.clone());
-        }
-        WineWardenResponse::Status(StatusPayload { active_sessions, last_session_id, last_summary, .. }) => {
-            state.active_sessions = *active_sessions;
-            state.last_session_id = *last_session_id;
-            state.last_summary = last_summary.clone();
-        }
-        _ => {}
+    let mut state = lock(state);
+    if let WineWardenResponse::RunResult(RunResult {
+        session_id,
+        summary,
+    }) = response
+    {
+        state.last_session_id = Some(*session_id);
+        state.last_summary = Some(summary.clone());
     }
 }
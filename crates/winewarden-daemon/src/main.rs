@@ -1,89 +1,282 @@
+use std::os::unix::fs::PermissionsExt;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use std::os::unix::fs::PermissionsExt;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use time::OffsetDateTime;
+use uuid::Uuid;
 
 use winewarden_core::config::{Config, ConfigPaths};
-use winewarden_core::ipc::{WineWardenRequest, WineWardenResponse, RunRequestPayload, RunResult, StatusPayload, resolve_pid_path, resolve_socket_path};
-use winewarden_core::store::{ExecutableIdentity, TrustStore};
+use winewarden_core::ipc::{
+    resolve_pid_path, resolve_socket_path, ErrorPayload, RunRequestPayload, RunResult,
+    SessionEvent, SessionEventPayload, StatusPayload, WineWardenRequest, WineWardenResponse,
+};
+use winewarden_core::overlay::{self, OverlayTarget};
+use winewarden_core::store::{EventStore, ExecutableIdentity, TierChange, TrustStore};
 use winewarden_core::trust::TrustTier;
 
-use monitor::{Monitor, RunRequest};
+use monitor::{Monitor, RunRequest, SessionControl, StoreSink};
 use policy_engine::PolicyEngine;
 use prefix_manager::PrefixManager;
 use reporting::SessionReport;
 use runner::{Runner, RunnerRequest};
 
+mod bus;
 mod ipc;
+mod maintenance;
+mod reload;
 mod scheduler;
-mod store;
+mod sessions;
+mod shutdown;
+mod signals;
+mod systemd;
 
+use bus::{BusSink, EventBus};
 use ipc::DaemonState;
-use store::EventStore;
+use maintenance::Maintenance;
+use reload::LiveConfig;
+use scheduler::Scheduler;
+use sessions::{ConfigSource, SessionInfo, SessionRegistry};
+use shutdown::Cleanup;
+use systemd::Notifier;
 
 fn main() -> Result<()> {
     let socket_path = resolve_socket_path();
     let pid_path = resolve_pid_path();
+    let notifier = Notifier::from_env()?;
+    let inherited = systemd::listen_socket()?;
+    let paths = ConfigPaths::resolve()?;
+    let live_config = LiveConfig::load(&paths.config_path)?;
+    let (config, _) = live_config.snapshot();
+    let events = EventStore::open(&paths.events_db_path)?;
+    let scheduler = Scheduler::load(
+        &config.maintenance,
+        &paths.scheduler_state_path,
+        OffsetDateTime::now_utc(),
+    )?;
+    let maintenance = Maintenance {
+        paths: paths.clone(),
+        config: config.maintenance.clone(),
+        events: events.clone(),
+    };
+    scheduler::spawn(scheduler.clone(), move |kind| maintenance.run(kind));
+
     let state = Arc::new(Mutex::new(DaemonState {
         started_at: OffsetDateTime::now_utc(),
-        active_sessions: 0,
+        sessions: SessionRegistry::default(),
         last_session_id: None,
         last_summary: None,
-        store: EventStore { location: socket_path.display().to_string() },
+        store: events,
+        scheduler,
+        config: live_config,
+        bus: EventBus::default(),
+        shutting_down: false,
     }));
 
+    // A socket passed in by systemd stays where the socket unit put it and
+    // is not ours to remove.
+    let (listener, owned_socket) = match inherited {
+        Some(listener) => (listener, None),
+        None => (ipc::bind(&socket_path)?, Some(socket_path.clone())),
+    };
     write_pid_file(&pid_path)?;
-    println!("WineWarden daemon listening on {}", socket_path.display());
-    ipc::serve(&socket_path, state, handle_request)?;
+    let cleanup = Cleanup {
+        pid_path,
+        socket_path: owned_socket,
+    };
+    {
+        let state = Arc::clone(&state);
+        let notifier = notifier.clone();
+        let paths = paths.clone();
+        signals::spawn(move |signal| {
+            if signal == libc::SIGHUP {
+                reload_config(&state, &paths, "SIGHUP");
+            } else {
+                shutdown::start(&state, &notifier, &cleanup);
+            }
+        })?;
+    }
+    if config.daemon.watch_config {
+        let state = Arc::clone(&state);
+        let watched = paths.config_path.clone();
+        reload::watch(&watched, move || {
+            reload_config(&state, &paths, "file change")
+        })?;
+    }
+    match listener
+        .local_addr()
+        .ok()
+        .and_then(|addr| addr.as_pathname().map(PathBuf::from))
+    {
+        Some(path) => println!("WineWarden daemon listening on {}", path.display()),
+        None => println!("WineWarden daemon listening on an inherited socket"),
+    }
+    notifier.ready("accepting requests")?;
+    let heartbeat = notifier.watchdog_period();
+    let watchdog = {
+        let state = Arc::clone(&state);
+        move || {
+            // Every request needs the state lock; stop pinging if it wedges
+            drop(
+                state
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            );
+            if let Err(err) = notifier.watchdog() {
+                eprintln!("watchdog notification failed: {err:#}");
+            }
+        }
+    };
+    ipc::serve(listener, state, handle_request, heartbeat, watchdog)?;
     Ok(())
 }
 
-fn handle_request(request: WineWardenRequest, state: &Arc<Mutex<DaemonState>>) -> Result<WineWardenResponse> {
+fn handle_request(
+    request: WineWardenRequest,
+    state: &Arc<Mutex<DaemonState>>,
+) -> Result<WineWardenResponse> {
     match request {
         WineWardenRequest::Ping => Ok(WineWardenResponse::Pong),
         WineWardenRequest::Status => Ok(WineWardenResponse::Status(build_status(state))),
         WineWardenRequest::Run(payload) => handle_run(payload, state),
+        WineWardenRequest::Subscribe(_) => Ok(WineWardenResponse::Error(ErrorPayload {
+            message: "subscriptions need a framed connection".to_string(),
+        })),
+        WineWardenRequest::ListSessions => Ok(WineWardenResponse::Sessions(sessions(state).list())),
+        WineWardenRequest::ControlSession(request) => {
+            let control = sessions(state)
+                .control(request.session_id)
+                .with_context(|| format!("no running session {}", request.session_id))?;
+            Ok(WineWardenResponse::Controlled(
+                control.apply(request.action)?,
+            ))
+        }
+        WineWardenRequest::Query(query) => {
+            let store = state
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .store
+                .clone();
+            Ok(WineWardenResponse::Events(store.query(&query)?))
+        }
+    }
+}
+
+/// Swaps in the config on disk for new runs and, if the new config asks
+/// for it, for the sessions already running.
+fn reload_config(state: &Arc<Mutex<DaemonState>>, paths: &ConfigPaths, trigger: &str) {
+    let (live, sessions) = {
+        let guard = state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        (guard.config.clone(), guard.sessions.clone())
+    };
+    if let Err(err) = live.reload() {
+        eprintln!("Config reload after {trigger} rejected, keeping the previous config: {err:#}");
+        return;
+    }
+    let (config, _) = live.snapshot();
+    if config.daemon.update_running_sessions {
+        let updated = live.push_to_sessions(&sessions, paths);
+        println!("Config reloaded after {trigger}; rules updated in {updated} running sessions");
+    } else {
+        println!("Config reloaded after {trigger}; applies to new sessions");
     }
 }
 
+fn sessions(state: &Arc<Mutex<DaemonState>>) -> SessionRegistry {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .sessions
+        .clone()
+}
+
 fn build_status(state: &Arc<Mutex<DaemonState>>) -> StatusPayload {
-    let guard = state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let guard = state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
     let uptime = OffsetDateTime::now_utc() - guard.started_at;
     StatusPayload {
         started_at: guard.started_at,
         uptime_seconds: uptime.whole_seconds().max(0) as u64,
-        active_sessions: guard.active_sessions,
+        active_sessions: guard.sessions.active_count(),
         last_session_id: guard.last_session_id,
         last_summary: guard.last_summary.clone(),
+        jobs: guard.scheduler.statuses(),
+        config: Some(guard.config.status()),
     }
 }
 
-fn handle_run(payload: RunRequestPayload, state: &Arc<Mutex<DaemonState>>) -> Result<WineWardenResponse> {
-    let report = execute_run(payload)?;
+fn handle_run(
+    payload: RunRequestPayload,
+    state: &Arc<Mutex<DaemonState>>,
+) -> Result<WineWardenResponse> {
+    let (bus, sessions, store, live) = {
+        let guard = state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if guard.shutting_down {
+            return Err(anyhow!("daemon is shutting down"));
+        }
+        (
+            guard.bus.clone(),
+            guard.sessions.clone(),
+            guard.store.clone(),
+            guard.config.clone(),
+        )
+    };
+    let report = execute_run(payload, &bus, &sessions, &store, &live)?;
     let summary = report.human_summary();
     let result = RunResult {
         session_id: report.session_id,
         summary,
     };
 
-    let mut guard = state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
-    guard.active_sessions = guard.active_sessions.saturating_add(1);
+    let mut guard = state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
     guard.last_session_id = Some(result.session_id);
     guard.last_summary = Some(result.summary.clone());
 
     Ok(WineWardenResponse::RunResult(result))
 }
 
-fn execute_run(payload: RunRequestPayload) -> Result<SessionReport> {
+fn execute_run(
+    payload: RunRequestPayload,
+    bus: &EventBus,
+    sessions: &SessionRegistry,
+    store: &EventStore,
+    live: &LiveConfig,
+) -> Result<SessionReport> {
     let paths = ConfigPaths::resolve()?;
-    let config = load_config(payload.config_path.as_ref(), &paths)?;
+    // Runs without their own config use the daemon's, which only changes
+    // through a validated reload.
+    let (base_config, config_path, overlays) = match &payload.config_path {
+        Some(path) => {
+            let overlays = overlay::load_overlays(&overlay::overlay_dir(path))?;
+            (Config::load_validated(path)?, path.clone(), overlays)
+        }
+        None => {
+            let (config, overlays) = live.snapshot();
+            (config, live.path().to_path_buf(), overlays)
+        }
+    };
 
-    let mut trust_store = TrustStore::load(&paths.trust_db_path)?;
-    let identity = ExecutableIdentity::from_path(&payload.executable)?;
-    let base_tier = payload.trust_override
-        .or_else(|| trust_store.get_tier(&identity))
+    let mut trust_store = TrustStore::open(&paths.trust_db_path)?;
+    let identity =
+        ExecutableIdentity::from_path_cached(&payload.executable, &paths.hash_cache_path)?;
+    let target = OverlayTarget {
+        identity: &identity,
+        steam_app_id: payload.steam_app_id,
+    };
+    let effective = overlay::merge_overlays(base_config, Some(&config_path), &overlays, &target)?;
+    effective.validate()?;
+    let config = effective.config;
+    let existing = trust_store.get(&identity)?;
+    let base_tier = payload
+        .trust_override
+        .or(existing.as_ref().map(|record| record.tier))
         .unwrap_or(config.trust.default_tier);
 
     let mut trust_tier = base_tier;
@@ -91,9 +284,11 @@ fn execute_run(payload: RunRequestPayload) -> Result<SessionReport> {
         trust_tier = downgrade_tier(trust_tier);
     }
 
-    let prefix_root = payload.prefix_root.unwrap_or_else(|| default_prefix_path(&paths, trust_tier));
+    let prefix_root = payload
+        .prefix_root
+        .unwrap_or_else(|| default_prefix_path(&paths, trust_tier));
 
-    if config.prefix.snapshot_before_first_run && !trust_store.records.contains_key(&identity.sha256) {
+    if config.prefix.snapshot_before_first_run && existing.is_none() {
         let manager = PrefixManager::new(prefix_root.clone(), &paths);
         let _snapshot = manager.create_snapshot()?;
     }
@@ -115,8 +310,16 @@ fn execute_run(payload: RunRequestPayload) -> Result<SessionReport> {
     };
     let _command = runner.dry_run(&runner_request)?;
 
-    let policy = PolicyEngine::from_config(config.clone(), &paths)?;
-    let monitor = Monitor::new(policy);
+    let baseline = existing
+        .as_ref()
+        .map(|record| record.baseline.clone())
+        .unwrap_or_default();
+    let policy = PolicyEngine::from_config(config.clone(), &paths)?.with_baseline(baseline);
+    let control = SessionControl::default();
+    let monitor = Monitor::new(policy)
+        .with_sink(Box::new(BusSink { bus: bus.clone() }))
+        .with_sink(Box::new(StoreSink::new(store.clone(), identity.clone())))
+        .with_control(control.clone());
 
     let live = if payload.live_monitor.enabled() {
         Some(payload.live_monitor)
@@ -124,7 +327,9 @@ fn execute_run(payload: RunRequestPayload) -> Result<SessionReport> {
         None
     };
 
+    let session_id = Uuid::new_v4();
     let request = RunRequest {
+        session_id: Some(session_id),
         executable: payload.executable.clone(),
         args: payload.args.clone(),
         prefix_root: prefix_root.clone(),
@@ -134,10 +339,45 @@ fn execute_run(payload: RunRequestPayload) -> Result<SessionReport> {
         live_monitor: live,
     };
 
-    let report = monitor.run(request)?;
+    let _session = sessions.register(SessionInfo {
+        session_id,
+        executable: payload.executable.clone(),
+        trust_tier,
+        started_at: OffsetDateTime::now_utc(),
+        control,
+        config_source: payload.config_path.is_none().then(|| ConfigSource {
+            identity: identity.clone(),
+            steam_app_id: payload.steam_app_id,
+        }),
+    });
+    let mut report = monitor.run(request)?;
 
-    trust_store.record_run(identity, trust_tier);
-    trust_store.save(&paths.trust_db_path)?;
+    let prior_clean_runs = existing.map(|record| record.clean_runs).unwrap_or(0);
+    let transition =
+        monitor
+            .policy()
+            .assess_run(trust_tier, prior_clean_runs, report.stats.systemic_risks);
+    let change = transition
+        .tier_change()
+        .unwrap_or_else(|| {
+            TierChange::for_run(base_tier, trust_tier, payload.trust_override.is_some())
+        })
+        .with_session(report.session_id);
+    let run = monitor.policy().run_summary(&transition);
+    trust_store.record_run(
+        identity,
+        transition.new_tier,
+        transition.clean_runs,
+        change,
+        &run,
+    )?;
+    report.trust_transition = Some(transition);
+    bus.publish(SessionEvent::now(
+        report.session_id,
+        SessionEventPayload::Finished {
+            summary: report.human_summary(),
+        },
+    ));
 
     store_report(&paths, &config, &report)?;
     Ok(report)
@@ -157,13 +397,6 @@ fn store_report(paths: &ConfigPaths, config: &Config, report: &SessionReport) ->
     Ok(())
 }
 
-fn load_config(path: Option<&PathBuf>, paths: &ConfigPaths) -> Result<Config> {
-    match path {
-        Some(path) => Config::load(path),
-        None => Config::load(&paths.config_path).or_else(|_| Ok(Config::default_config())),
-    }
-}
-
 fn downgrade_tier(tier: TrustTier) -> TrustTier {
     match tier {
         TrustTier::Green => TrustTier::Yellow,
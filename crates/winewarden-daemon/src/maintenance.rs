@@ -0,0 +1,131 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use time::{Duration, OffsetDateTime};
+
+use prefix_manager::snapshots::{list_snapshots, prune_snapshots};
+use prefix_manager::PrefixManager;
+use winewarden_core::config::{ConfigPaths, MaintenanceConfig};
+use winewarden_core::store::{EventStore, HashCache, TierChange, TrustChangeSource, TrustStore};
+use winewarden_core::trust::TrustTier;
+
+use crate::scheduler::JobKind;
+
+/// What the scheduled jobs act on.
+pub struct Maintenance {
+    pub paths: ConfigPaths,
+    pub config: MaintenanceConfig,
+    pub events: EventStore,
+}
+
+impl Maintenance {
+    /// Runs one job and describes what it did.
+    pub fn run(&self, kind: JobKind) -> Result<String> {
+        match kind {
+            JobKind::HygieneScan => self.scan_prefixes(),
+            JobKind::ReportPruning => self.prune_reports(),
+            JobKind::SnapshotPruning => {
+                let keep = self.config.snapshots_per_prefix.max(1) as usize;
+                let removed = prune_snapshots(&self.paths.snapshot_dir, keep)?;
+                Ok(format!(
+                    "removed {removed} snapshots, keeping {keep} per prefix"
+                ))
+            }
+            JobKind::TrustReview => self.review_trust(),
+            JobKind::HashCacheCleanup => {
+                let removed = HashCache::open(&self.paths.hash_cache_path)?.prune()?;
+                Ok(format!("removed {removed} stale hash cache entries"))
+            }
+        }
+    }
+
+    fn scan_prefixes(&self) -> Result<String> {
+        let prefixes = self.known_prefixes()?;
+        let mut findings = 0;
+        let mut failed = 0;
+        for prefix in &prefixes {
+            match PrefixManager::new(prefix.clone(), &self.paths).scan_hygiene() {
+                Ok(found) => findings += found.len(),
+                Err(err) => {
+                    eprintln!("hygiene scan of {} failed: {err:#}", prefix.display());
+                    failed += 1;
+                }
+            }
+        }
+        let mut summary = format!("scanned {} prefixes, {findings} findings", prefixes.len());
+        if failed > 0 {
+            summary.push_str(&format!(", {failed} could not be read"));
+        }
+        Ok(summary)
+    }
+
+    /// The per-tier prefixes under the data directory plus any prefix that
+    /// still exists and has a snapshot.
+    fn known_prefixes(&self) -> Result<BTreeSet<PathBuf>> {
+        let mut prefixes = BTreeSet::new();
+        if let Ok(entries) = fs::read_dir(self.paths.data_dir.join("prefixes")) {
+            for entry in entries {
+                let path = entry?.path();
+                if path.is_dir() {
+                    prefixes.insert(path);
+                }
+            }
+        }
+        for (_, snapshot) in list_snapshots(&self.paths.snapshot_dir)? {
+            if snapshot.prefix_root.is_dir() {
+                prefixes.insert(snapshot.prefix_root);
+            }
+        }
+        Ok(prefixes)
+    }
+
+    fn prune_reports(&self) -> Result<String> {
+        let days = self.config.report_retention_days;
+        let cutoff = OffsetDateTime::now_utc() - Duration::days(i64::from(days));
+        let mut reports = 0;
+        if let Ok(entries) = fs::read_dir(&self.paths.report_dir) {
+            for entry in entries {
+                let entry = entry?;
+                let path = entry.path();
+                let modified = entry.metadata()?.modified().map(OffsetDateTime::from);
+                if path.extension().is_some_and(|ext| ext == "json")
+                    && modified.is_ok_and(|modified| modified < cutoff)
+                {
+                    fs::remove_file(&path)
+                        .with_context(|| format!("remove report {}", path.display()))?;
+                    reports += 1;
+                }
+            }
+        }
+        let events = self.events.prune_before(cutoff)?;
+        Ok(format!(
+            "removed {reports} reports and {events} stored events older than {days} days"
+        ))
+    }
+
+    /// Green executables that have not run for a while drop to yellow and
+    /// earn their way back through clean runs.
+    fn review_trust(&self) -> Result<String> {
+        let days = self.config.trust_stale_after_days;
+        let cutoff = OffsetDateTime::now_utc() - Duration::days(i64::from(days));
+        let mut store = TrustStore::open(&self.paths.trust_db_path)?;
+        let stale: Vec<_> = store
+            .records()?
+            .into_iter()
+            .filter(|record| record.tier == TrustTier::Green && record.last_seen < cutoff)
+            .collect();
+        for record in &stale {
+            let change = TierChange::new(
+                TrustChangeSource::StaleReview,
+                format!("not run for more than {days} days"),
+            );
+            store.set_tier(record.identity.clone(), TrustTier::Yellow, change)?;
+        }
+        Ok(format!(
+            "lowered {} stale green records to yellow",
+            stale.len()
+        ))
+    }
+}
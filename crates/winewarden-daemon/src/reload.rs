@@ -0,0 +1,321 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use time::OffsetDateTime;
+
+use policy_engine::LiveRules;
+use winewarden_core::config::{Config, ConfigPaths};
+use winewarden_core::ipc::{ConfigStatus, ReloadError};
+use winewarden_core::migrate;
+use winewarden_core::overlay::{self, ConfigOverlay, EffectiveConfig, OverlayTarget};
+
+use crate::sessions::SessionRegistry;
+
+/// Editors write a file in several steps; changes this close together
+/// cause one reload.
+const SETTLE: Duration = Duration::from_millis(500);
+
+/// The validated base config and overlays new runs start from. A reload
+/// swaps both at once; a rejected reload keeps the previous ones.
+#[derive(Clone)]
+pub struct LiveConfig {
+    inner: Arc<Mutex<Loaded>>,
+    path: PathBuf,
+}
+
+struct Loaded {
+    config: Config,
+    overlays: Vec<ConfigOverlay>,
+    loaded_at: Option<OffsetDateTime>,
+    last_error: Option<ReloadError>,
+}
+
+impl LiveConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let path = path.to_path_buf();
+        let (config, overlays) = read(&path, load_config)?;
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Loaded {
+                config,
+                overlays,
+                loaded_at: path.exists().then(OffsetDateTime::now_utc),
+                last_error: None,
+            })),
+            path,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn snapshot(&self) -> (Config, Vec<ConfigOverlay>) {
+        let loaded = self.lock();
+        (loaded.config.clone(), loaded.overlays.clone())
+    }
+
+    /// Re-reads the config and overlays. An older schema is migrated in
+    /// memory only; the file is left for `config migrate`. On failure the
+    /// error is kept for `Status` and the previous config stays in use.
+    pub fn reload(&self) -> Result<()> {
+        match read(&self.path, Config::load_validated) {
+            Ok((config, overlays)) => {
+                let mut loaded = self.lock();
+                loaded.config = config;
+                loaded.overlays = overlays;
+                loaded.loaded_at = self.path.exists().then(OffsetDateTime::now_utc);
+                loaded.last_error = None;
+                Ok(())
+            }
+            Err(err) => {
+                self.lock().last_error = Some(ReloadError {
+                    at: OffsetDateTime::now_utc(),
+                    message: format!("{err:#}"),
+                });
+                Err(err)
+            }
+        }
+    }
+
+    pub fn status(&self) -> ConfigStatus {
+        let loaded = self.lock();
+        ConfigStatus {
+            path: self.path.clone(),
+            loaded_at: loaded.loaded_at,
+            last_error: loaded.last_error.clone(),
+        }
+    }
+
+    /// Resolves each running session's rules against the current config
+    /// and hands them over. Sessions started with their own config file
+    /// are left alone. Returns how many sessions were updated.
+    pub fn push_to_sessions(&self, sessions: &SessionRegistry, paths: &ConfigPaths) -> usize {
+        let (config, overlays) = self.snapshot();
+        let mut updated = 0;
+        for (source, control) in sessions.reloadable() {
+            let target = OverlayTarget {
+                identity: &source.identity,
+                steam_app_id: source.steam_app_id,
+            };
+            let rules =
+                overlay::merge_overlays(config.clone(), Some(&self.path), &overlays, &target)
+                    .and_then(|effective| {
+                        effective.validate()?;
+                        LiveRules::from_config(&effective.config, paths)
+                    });
+            match rules {
+                Ok(rules) => {
+                    control.update_rules(rules);
+                    updated += 1;
+                }
+                Err(err) => eprintln!(
+                    "keeping old rules for {}: {err:#}",
+                    source.identity.path.display()
+                ),
+            }
+        }
+        updated
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Loaded> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Loads and validates `path`, upgrading an older schema on disk first.
+/// Only done at startup.
+pub fn load_config(path: &Path) -> Result<Config> {
+    if let Some(backup) = migrate::upgrade_in_place(path)? {
+        println!(
+            "Config {} upgraded (backup at {})",
+            path.display(),
+            backup.display()
+        );
+    }
+    Config::load_validated(path)
+}
+
+/// The daemon's config falls back to the built-in defaults while the file
+/// does not exist. Every overlay must validate over it.
+fn read(
+    path: &Path,
+    load: impl FnOnce(&Path) -> Result<Config>,
+) -> Result<(Config, Vec<ConfigOverlay>)> {
+    let config = if path.exists() {
+        load(path)?
+    } else {
+        Config::default_config()
+    };
+    EffectiveConfig::from_base(config.clone(), Some(path))?.validate()?;
+    let overlays = overlay::load_overlays(&overlay::overlay_dir(path))?;
+    overlay::validate_overlays(&config, Some(path), &overlays)?;
+    Ok((config, overlays))
+}
+
+/// Calls `on_change` after the config file or an overlay changes on disk.
+/// Watches the config directory, so a config created later is seen too.
+pub fn watch(config_path: &Path, on_change: impl Fn() + Send + 'static) -> Result<()> {
+    let Some(dir) = config_path.parent().filter(|dir| dir.is_dir()) else {
+        println!(
+            "Not watching config: {} does not exist",
+            config_path.display()
+        );
+        return Ok(());
+    };
+    let overlays = overlay::overlay_dir(config_path);
+    let config_path = config_path.to_path_buf();
+    let relevant = move |event: &Event| {
+        !matches!(event.kind, EventKind::Access(_))
+            && event.paths.iter().any(|path| {
+                path == &config_path
+                    || path == &overlays
+                    || (path.parent() == Some(overlays.as_path())
+                        && path.extension().is_some_and(|ext| ext == "toml"))
+            })
+    };
+
+    let (sender, receiver) = channel();
+    let mut watcher = RecommendedWatcher::new(sender, notify::Config::default())
+        .context("create config watcher")?;
+    watcher
+        .watch(dir, RecursiveMode::Recursive)
+        .with_context(|| format!("watch {}", dir.display()))?;
+    thread::spawn(move || {
+        let _watcher = watcher;
+        while let Ok(event) = receiver.recv() {
+            if !event.is_ok_and(|event| relevant(&event)) {
+                continue;
+            }
+            thread::sleep(SETTLE);
+            while receiver.try_recv().is_ok() {}
+            on_change();
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use monitor::SessionControl;
+    use uuid::Uuid;
+    use winewarden_core::store::ExecutableIdentity;
+    use winewarden_core::trust::TrustTier;
+
+    use crate::sessions::{ConfigSource, SessionInfo};
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("winewarden-reload-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn session(control: &SessionControl, config_source: Option<ConfigSource>) -> SessionInfo {
+        SessionInfo {
+            session_id: Uuid::new_v4(),
+            executable: PathBuf::from("/games/foo.exe"),
+            trust_tier: TrustTier::Yellow,
+            started_at: OffsetDateTime::now_utc(),
+            control: control.clone(),
+            config_source,
+        }
+    }
+
+    #[test]
+    fn test_rejected_reload_keeps_previous_config() {
+        let dir = temp_dir();
+        let path = dir.join("config.toml");
+        let mut config = Config::default_config();
+        config.process.max_child_processes = 7;
+        config.save(&path).unwrap();
+
+        let live = LiveConfig::load(&path).unwrap();
+        assert!(live.status().loaded_at.is_some());
+
+        std::fs::write(&path, "[process\nbroken").unwrap();
+        assert!(live.reload().is_err());
+        assert!(live.status().last_error.is_some());
+        assert_eq!(live.snapshot().0.process.max_child_processes, 7);
+
+        config.process.max_child_processes = 9;
+        config.save(&path).unwrap();
+        live.reload().unwrap();
+        assert!(live.status().last_error.is_none());
+        assert_eq!(live.snapshot().0.process.max_child_processes, 9);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reload_leaves_old_schema_on_disk() {
+        let dir = temp_dir();
+        let path = dir.join("config.toml");
+        Config::default_config().save(&path).unwrap();
+        let live = LiveConfig::load(&path).unwrap();
+
+        fs::write(&path, "schema_version = 1\n").unwrap();
+        live.reload().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "schema_version = 1\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reload_rejects_invalid_overlay() {
+        let dir = temp_dir();
+        let path = dir.join("config.toml");
+        Config::default_config().save(&path).unwrap();
+        let live = LiveConfig::load(&path).unwrap();
+
+        let games = overlay::overlay_dir(&path);
+        fs::create_dir_all(&games).unwrap();
+        fs::write(
+            games.join("other.toml"),
+            "[match]\nsteam_app_id = 7\n[process]\nmax_child_processes = 0\n",
+        )
+        .unwrap();
+        assert!(live.reload().is_err());
+        assert!(live.snapshot().1.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reload_pushes_rules_to_running_sessions() {
+        let dir = temp_dir();
+        let paths = ConfigPaths::under(&dir);
+        let mut config = Config::default_config();
+        config.save(&paths.config_path).unwrap();
+        let live = LiveConfig::load(&paths.config_path).unwrap();
+
+        let sessions = SessionRegistry::default();
+        let following = SessionControl::default();
+        let own_config = SessionControl::default();
+        let source = ConfigSource {
+            identity: ExecutableIdentity {
+                path: PathBuf::from("/games/foo.exe"),
+                sha256: "abc".to_string(),
+            },
+            steam_app_id: None,
+        };
+        let _following = sessions.register(session(&following, Some(source)));
+        let _own_config = sessions.register(session(&own_config, None));
+
+        config.process.max_child_processes = 9;
+        config.save(&paths.config_path).unwrap();
+        live.reload().unwrap();
+        assert_eq!(live.push_to_sessions(&sessions, &paths), 1);
+
+        let rules = following.take_rules().expect("rules pushed");
+        assert_eq!(rules.process.max_child_processes, 9);
+        assert!(own_config.take_rules().is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
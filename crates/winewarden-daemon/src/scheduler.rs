@@ -1,8 +1,281 @@
-use time::OffsetDateTime;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+
+use winewarden_core::config::MaintenanceConfig;
+use winewarden_core::ipc::{JobOutcome, JobStatus};
+
+/// Jobs due at startup wait this long, so the daemon answers requests first.
+const STARTUP_DELAY: Duration = Duration::minutes(1);
+/// Longest the scheduler thread sleeps between checks.
+const MAX_SLEEP: Duration = Duration::minutes(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    HygieneScan,
+    ReportPruning,
+    SnapshotPruning,
+    TrustReview,
+    HashCacheCleanup,
+}
 
-#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct ScheduledJob {
-    pub name: String,
+    pub kind: JobKind,
+    pub interval: Duration,
     pub next_run: OffsetDateTime,
+    pub last_run: Option<JobRecord>,
+}
+
+/// The last run of a job, kept across daemon restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub finished_at: OffsetDateTime,
+    pub outcome: JobOutcome,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct SchedulerState {
+    #[serde(default)]
+    jobs: BTreeMap<JobKind, JobRecord>,
+}
+
+/// Enabled maintenance jobs and when each runs next. Shared between the
+/// scheduler thread and status requests.
+#[derive(Clone)]
+pub struct Scheduler {
+    inner: Arc<Mutex<Jobs>>,
+}
+
+struct Jobs {
+    jobs: Vec<ScheduledJob>,
+    state_path: PathBuf,
+}
+
+impl JobKind {
+    const ALL: [JobKind; 5] = [
+        JobKind::HygieneScan,
+        JobKind::ReportPruning,
+        JobKind::SnapshotPruning,
+        JobKind::TrustReview,
+        JobKind::HashCacheCleanup,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            JobKind::HygieneScan => "hygiene_scan",
+            JobKind::ReportPruning => "report_pruning",
+            JobKind::SnapshotPruning => "snapshot_pruning",
+            JobKind::TrustReview => "trust_review",
+            JobKind::HashCacheCleanup => "hash_cache_cleanup",
+        }
+    }
+
+    fn interval_hours(self, config: &MaintenanceConfig) -> u64 {
+        match self {
+            JobKind::HygieneScan => config.hygiene_scan_interval_hours,
+            JobKind::ReportPruning => config.report_prune_interval_hours,
+            JobKind::SnapshotPruning => config.snapshot_prune_interval_hours,
+            JobKind::TrustReview => config.trust_review_interval_hours,
+            JobKind::HashCacheCleanup => config.hash_cache_cleanup_interval_hours,
+        }
+    }
+}
+
+impl Scheduler {
+    /// Schedules every enabled job from its last recorded run. Jobs that
+    /// never ran, or are overdue, run shortly after `now`.
+    pub fn load(
+        config: &MaintenanceConfig,
+        state_path: &Path,
+        now: OffsetDateTime,
+    ) -> Result<Self> {
+        let mut state = read_state(state_path)?;
+        let earliest = now + STARTUP_DELAY;
+        let jobs = JobKind::ALL
+            .into_iter()
+            .filter(|_| config.enabled)
+            .filter_map(|kind| {
+                let hours = kind.interval_hours(config);
+                if hours == 0 {
+                    return None;
+                }
+                let interval = Duration::hours(hours as i64);
+                let last_run = state.jobs.remove(&kind);
+                let next_run = last_run
+                    .as_ref()
+                    .map(|record| record.finished_at + interval)
+                    .map_or(earliest, |next| next.max(earliest));
+                Some(ScheduledJob {
+                    kind,
+                    interval,
+                    next_run,
+                    last_run,
+                })
+            })
+            .collect();
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Jobs {
+                jobs,
+                state_path: state_path.to_path_buf(),
+            })),
+        })
+    }
+
+    pub fn due(&self, now: OffsetDateTime) -> Vec<JobKind> {
+        self.lock()
+            .jobs
+            .iter()
+            .filter(|job| job.next_run <= now)
+            .map(|job| job.kind)
+            .collect()
+    }
+
+    /// Records a finished run, schedules the next one and saves the state.
+    pub fn complete(
+        &self,
+        kind: JobKind,
+        finished_at: OffsetDateTime,
+        outcome: JobOutcome,
+    ) -> Result<()> {
+        let mut jobs = self.lock();
+        if let Some(job) = jobs.jobs.iter_mut().find(|job| job.kind == kind) {
+            job.next_run = finished_at + job.interval;
+            job.last_run = Some(JobRecord {
+                finished_at,
+                outcome,
+            });
+        }
+        jobs.save()
+    }
+
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        self.lock()
+            .jobs
+            .iter()
+            .map(|job| JobStatus {
+                name: job.kind.name().to_string(),
+                interval_seconds: job.interval.whole_seconds().max(0) as u64,
+                next_run: job.next_run,
+                last_run: job.last_run.as_ref().map(|record| record.finished_at),
+                last_outcome: job.last_run.as_ref().map(|record| record.outcome.clone()),
+            })
+            .collect()
+    }
+
+    fn until_next(&self, now: OffsetDateTime) -> Duration {
+        self.lock()
+            .jobs
+            .iter()
+            .map(|job| job.next_run - now)
+            .min()
+            .unwrap_or(MAX_SLEEP)
+            .clamp(Duration::ZERO, MAX_SLEEP)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Jobs> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Jobs {
+    fn save(&self) -> Result<()> {
+        let state = SchedulerState {
+            jobs: self
+                .jobs
+                .iter()
+                .filter_map(|job| Some((job.kind, job.last_run.clone()?)))
+                .collect(),
+        };
+        if let Some(parent) = self.state_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("create scheduler dir {}", parent.display()))?;
+        }
+        let contents = serde_json::to_string_pretty(&state).context("render scheduler state")?;
+        let temp = self.state_path.with_extension("json.tmp");
+        fs::write(&temp, contents)
+            .with_context(|| format!("write scheduler state {}", temp.display()))?;
+        fs::rename(&temp, &self.state_path)
+            .with_context(|| format!("replace scheduler state {}", self.state_path.display()))
+    }
+}
+
+/// Runs due jobs on a background thread for the life of the daemon.
+pub fn spawn(scheduler: Scheduler, run: impl Fn(JobKind) -> Result<String> + Send + 'static) {
+    thread::spawn(move || loop {
+        for kind in scheduler.due(OffsetDateTime::now_utc()) {
+            let outcome = match run(kind) {
+                Ok(summary) => JobOutcome::Succeeded(summary),
+                Err(err) => JobOutcome::Failed(format!("{err:#}")),
+            };
+            if let Err(err) = scheduler.complete(kind, OffsetDateTime::now_utc(), outcome) {
+                eprintln!("failed to save {} state: {err:#}", kind.name());
+            }
+        }
+        let wait = scheduler.until_next(OffsetDateTime::now_utc());
+        thread::sleep(wait.try_into().unwrap_or(std::time::Duration::from_secs(1)));
+    });
+}
+
+fn read_state(path: &Path) -> Result<SchedulerState> {
+    match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .with_context(|| format!("parse scheduler state {}", path.display())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(SchedulerState::default()),
+        Err(err) => Err(err).with_context(|| format!("read scheduler state {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_runs_survive_restart() {
+        let dir =
+            std::env::temp_dir().join(format!("winewarden-scheduler-{}", uuid::Uuid::new_v4()));
+        let state_path = dir.join("scheduler.json");
+        let config = MaintenanceConfig {
+            hash_cache_cleanup_interval_hours: 0,
+            ..MaintenanceConfig::default()
+        };
+        let start = OffsetDateTime::now_utc();
+
+        let scheduler = Scheduler::load(&config, &state_path, start).unwrap();
+        assert_eq!(scheduler.statuses().len(), 4);
+        assert!(scheduler.due(start).is_empty());
+        let later = start + STARTUP_DELAY;
+        assert_eq!(scheduler.due(later).len(), 4);
+
+        scheduler
+            .complete(
+                JobKind::HygieneScan,
+                later,
+                JobOutcome::Succeeded("ok".to_string()),
+            )
+            .unwrap();
+        assert_eq!(scheduler.due(later).len(), 3);
+
+        let restarted = Scheduler::load(&config, &state_path, later).unwrap();
+        let hygiene = restarted
+            .statuses()
+            .into_iter()
+            .find(|job| job.name == "hygiene_scan")
+            .unwrap();
+        assert_eq!(
+            hygiene.last_outcome,
+            Some(JobOutcome::Succeeded("ok".to_string()))
+        );
+        assert_eq!(hygiene.next_run, later + Duration::hours(24));
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
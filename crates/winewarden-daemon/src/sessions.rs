@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use monitor::SessionControl;
+use winewarden_core::ipc::SessionSummary;
+use winewarden_core::store::ExecutableIdentity;
+use winewarden_core::trust::TrustTier;
+
+#[derive(Clone)]
+pub struct SessionInfo {
+    pub session_id: Uuid,
+    pub executable: PathBuf,
+    pub trust_tier: TrustTier,
+    pub started_at: OffsetDateTime,
+    pub control: SessionControl,
+    /// `None` when the run named its own config file, which reloads of
+    /// the daemon's config do not touch.
+    pub config_source: Option<ConfigSource>,
+}
+
+/// What a session's overlays were matched against, so a reload can
+/// resolve its rules again.
+#[derive(Clone)]
+pub struct ConfigSource {
+    pub identity: ExecutableIdentity,
+    pub steam_app_id: Option<u32>,
+}
+
+/// Sessions the daemon is monitoring right now, shared by every
+/// connection thread.
+#[derive(Clone, Default)]
+pub struct SessionRegistry {
+    sessions: Arc<Mutex<HashMap<Uuid, SessionInfo>>>,
+}
+
+/// Removes its session from the registry when dropped, so a run that
+/// fails or panics part-way is not counted as active forever.
+pub struct SessionGuard {
+    registry: SessionRegistry,
+    session_id: Uuid,
+}
+
+impl SessionRegistry {
+    pub fn register(&self, info: SessionInfo) -> SessionGuard {
+        let session_id = info.session_id;
+        self.lock().insert(session_id, info);
+        SessionGuard {
+            registry: self.clone(),
+            session_id,
+        }
+    }
+
+    pub fn active_count(&self) -> u32 {
+        self.lock().len() as u32
+    }
+
+    /// Running sessions, oldest first.
+    pub fn list(&self) -> Vec<SessionSummary> {
+        let mut sessions: Vec<SessionSummary> = self
+            .lock()
+            .values()
+            .map(|info| SessionSummary {
+                session_id: info.session_id,
+                executable: info.executable.clone(),
+                trust_tier: info.trust_tier,
+                started_at: info.started_at,
+                paused: info.control.is_paused(),
+            })
+            .collect();
+        sessions.sort_by_key(|session| session.started_at);
+        sessions
+    }
+
+    pub fn control(&self, session_id: Uuid) -> Option<SessionControl> {
+        self.lock()
+            .get(&session_id)
+            .map(|info| info.control.clone())
+    }
+
+    pub fn controls(&self) -> Vec<SessionControl> {
+        self.lock()
+            .values()
+            .map(|info| info.control.clone())
+            .collect()
+    }
+
+    /// Sessions that follow the daemon's config, with their controls.
+    pub fn reloadable(&self) -> Vec<(ConfigSource, SessionControl)> {
+        self.lock()
+            .values()
+            .filter_map(|info| Some((info.config_source.clone()?, info.control.clone())))
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Uuid, SessionInfo>> {
+        self.sessions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.registry.lock().remove(&self.session_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info() -> SessionInfo {
+        SessionInfo {
+            session_id: Uuid::new_v4(),
+            executable: PathBuf::from("/games/game.exe"),
+            trust_tier: TrustTier::Yellow,
+            started_at: OffsetDateTime::now_utc(),
+            control: SessionControl::default(),
+            config_source: None,
+        }
+    }
+
+    #[test]
+    fn test_guard_tracks_active_sessions() {
+        let registry = SessionRegistry::default();
+        let first = registry.register(info());
+        let second = registry.register(info());
+        assert_eq!(registry.active_count(), 2);
+        assert_eq!(registry.list().len(), 2);
+
+        drop(first);
+        assert_eq!(registry.active_count(), 1);
+        drop(second);
+        assert_eq!(registry.active_count(), 0);
+    }
+}
@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use monitor::KILL_GRACE;
+use winewarden_core::types::SessionAction;
+
+use crate::ipc::DaemonState;
+use crate::sessions::SessionRegistry;
+use crate::systemd::Notifier;
+
+/// How long running sessions get to finish on their own after SIGTERM.
+const SESSION_GRACE: Duration = Duration::from_secs(30);
+/// How long killed sessions get to write their reports.
+const REPORT_GRACE: Duration = Duration::from_secs(5);
+const DRAIN_POLL: Duration = Duration::from_millis(200);
+
+/// Files the daemon created and removes on the way out.
+#[derive(Clone)]
+pub struct Cleanup {
+    pub pid_path: PathBuf,
+    /// `None` when the socket belongs to systemd.
+    pub socket_path: Option<PathBuf>,
+}
+
+/// Starts the shutdown on its own thread, so the signal thread can still
+/// answer SIGHUP while sessions drain. Later calls do nothing.
+pub fn start(state: &Arc<Mutex<DaemonState>>, notifier: &Notifier, cleanup: &Cleanup) {
+    let sessions = {
+        let mut guard = state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if guard.shutting_down {
+            return;
+        }
+        guard.shutting_down = true;
+        guard.sessions.clone()
+    };
+    let notifier = notifier.clone();
+    let cleanup = cleanup.clone();
+    thread::spawn(move || run(&sessions, &notifier, &cleanup));
+}
+
+/// Lets running sessions finish, kills any that outlast the grace period,
+/// removes the daemon's files and exits.
+fn run(sessions: &SessionRegistry, notifier: &Notifier, cleanup: &Cleanup) -> ! {
+    let active = sessions.active_count();
+    println!("WineWarden daemon stopping, {active} active sessions");
+    if let Err(err) = notifier.stopping(&format!("waiting for {active} sessions")) {
+        eprintln!("stop notification failed: {err:#}");
+    }
+
+    if !drain(sessions, SESSION_GRACE) {
+        for control in sessions.controls() {
+            match control.apply(SessionAction::Kill) {
+                Ok(record) => println!("Ended session at shutdown: {}", record.summary()),
+                Err(err) => eprintln!("could not end session: {err:#}"),
+            }
+        }
+        drain(sessions, KILL_GRACE + REPORT_GRACE);
+    }
+
+    let _ = fs::remove_file(&cleanup.pid_path);
+    if let Some(socket_path) = &cleanup.socket_path {
+        let _ = fs::remove_file(socket_path);
+    }
+    std::process::exit(0);
+}
+
+/// Waits up to `timeout` for every session to end.
+fn drain(sessions: &SessionRegistry, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while sessions.active_count() > 0 {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(DRAIN_POLL);
+    }
+    true
+}
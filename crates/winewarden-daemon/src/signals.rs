@@ -0,0 +1,48 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::fd::FromRawFd;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::thread;
+
+use anyhow::{Context, Result};
+
+/// Write end of the pipe the signal handler wakes the signal thread with.
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// Calls `handle` on a dedicated thread for every SIGTERM, SIGINT and
+/// SIGHUP the daemon receives.
+///
+/// The handler only writes the signal number to a pipe; handlers reset on
+/// exec, so games launched by the daemon keep the default behaviour.
+pub fn spawn(handle: impl Fn(libc::c_int) + Send + 'static) -> Result<()> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(io::Error::last_os_error()).context("create signal pipe");
+    }
+    let mut reader = unsafe { File::from_raw_fd(fds[0]) };
+    SIGNAL_PIPE.store(fds[1], Ordering::SeqCst);
+    for signal in [libc::SIGTERM, libc::SIGINT, libc::SIGHUP] {
+        let handler = on_signal as extern "C" fn(libc::c_int);
+        if unsafe { libc::signal(signal, handler as libc::sighandler_t) } == libc::SIG_ERR {
+            return Err(io::Error::last_os_error()).context("install signal handler");
+        }
+    }
+
+    thread::spawn(move || {
+        let mut signal = [0u8; 1];
+        loop {
+            if let Err(err) = reader.read_exact(&mut signal) {
+                eprintln!("waiting for signals failed: {err}");
+                return;
+            }
+            handle(libc::c_int::from(signal[0]));
+        }
+    });
+    Ok(())
+}
+
+extern "C" fn on_signal(signal: libc::c_int) {
+    let fd = SIGNAL_PIPE.load(Ordering::SeqCst);
+    let byte = signal as u8;
+    unsafe { libc::write(fd, (&byte as *const u8).cast(), 1) };
+}
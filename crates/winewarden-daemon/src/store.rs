@@ -1,5 +0,0 @@
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
-pub struct EventStore {
-    pub location: String,
-}
@@ -0,0 +1,198 @@
+//! Socket activation and readiness notification, following the protocols
+//! in sd_listen_fds(3) and sd_notify(3) without linking libsystemd.
+
+use std::env;
+use std::os::fd::FromRawFd;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram, UnixListener};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+
+/// First inherited descriptor; 0-2 are stdio.
+const LISTEN_FDS_START: i32 = 3;
+
+/// Takes the listening socket systemd passed in, if this process was
+/// socket-activated. The variables are cleared so children do not see them.
+pub fn listen_socket() -> Result<Option<UnixListener>> {
+    let count = listen_fd_count(
+        env::var("LISTEN_PID").ok().as_deref(),
+        env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    )?;
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+    if count == 0 {
+        return Ok(None);
+    }
+    if count > 1 {
+        return Err(anyhow!("expected one socket from systemd, got {count}"));
+    }
+
+    let fd = LISTEN_FDS_START;
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(fd, &mut stat) } != 0 || stat.st_mode & libc::S_IFMT != libc::S_IFSOCK {
+        return Err(anyhow!("inherited fd {fd} is not a socket"));
+    }
+    unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+    Ok(Some(unsafe { UnixListener::from_raw_fd(fd) }))
+}
+
+/// Number of sockets passed to this process. Variables meant for another
+/// process (a different `LISTEN_PID`) are ignored.
+fn listen_fd_count(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> Result<usize> {
+    let (Some(listen_pid), Some(listen_fds)) = (listen_pid, listen_fds) else {
+        return Ok(0);
+    };
+    let listen_pid: u32 = listen_pid.parse().context("parse LISTEN_PID")?;
+    if listen_pid != pid {
+        return Ok(0);
+    }
+    listen_fds.parse().context("parse LISTEN_FDS")
+}
+
+/// Sends state changes to the service manager. Every message is dropped
+/// when the daemon was not started with `NOTIFY_SOCKET`.
+#[derive(Clone)]
+pub struct Notifier {
+    target: Option<SocketAddr>,
+    /// The watchdog interval systemd asked for, if any.
+    watchdog: Option<Duration>,
+}
+
+impl Notifier {
+    /// Reads and clears the notification variables. Call it before any
+    /// thread starts, as changing the environment races with readers.
+    pub fn from_env() -> Result<Self> {
+        let target = match env::var("NOTIFY_SOCKET") {
+            Ok(path) => Some(notify_addr(&path)?),
+            Err(_) => None,
+        };
+        let watchdog = watchdog_interval(
+            env::var("WATCHDOG_PID").ok().as_deref(),
+            env::var("WATCHDOG_USEC").ok().as_deref(),
+            std::process::id(),
+        )?;
+        env::remove_var("NOTIFY_SOCKET");
+        env::remove_var("WATCHDOG_PID");
+        env::remove_var("WATCHDOG_USEC");
+        Ok(Self { target, watchdog })
+    }
+
+    pub fn ready(&self, status: &str) -> Result<()> {
+        self.send(&format!("READY=1\nSTATUS={status}"))
+    }
+
+    pub fn stopping(&self, status: &str) -> Result<()> {
+        self.send(&format!("STOPPING=1\nSTATUS={status}"))
+    }
+
+    pub fn watchdog(&self) -> Result<()> {
+        self.send("WATCHDOG=1")
+    }
+
+    /// How often the main loop should ping the watchdog: half the interval
+    /// systemd asked for, if it asked for one.
+    pub fn watchdog_period(&self) -> Option<Duration> {
+        self.watchdog
+            .filter(|_| self.target.is_some())
+            .map(|interval| interval / 2)
+    }
+
+    fn send(&self, message: &str) -> Result<()> {
+        let Some(target) = &self.target else {
+            return Ok(());
+        };
+        let socket = UnixDatagram::unbound().context("create notify socket")?;
+        socket
+            .send_to_addr(message.as_bytes(), target)
+            .context("send to NOTIFY_SOCKET")?;
+        Ok(())
+    }
+}
+
+/// `@name` is an abstract socket; anything else is a filesystem path.
+fn notify_addr(value: &str) -> Result<SocketAddr> {
+    let addr = match value.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name.as_bytes()),
+        None => SocketAddr::from_pathname(PathBuf::from(value)),
+    };
+    addr.with_context(|| format!("invalid NOTIFY_SOCKET {value}"))
+}
+
+fn watchdog_interval(
+    watchdog_pid: Option<&str>,
+    watchdog_usec: Option<&str>,
+    pid: u32,
+) -> Result<Option<Duration>> {
+    let Some(usec) = watchdog_usec else {
+        return Ok(None);
+    };
+    if let Some(watchdog_pid) = watchdog_pid {
+        if watchdog_pid.parse::<u32>().context("parse WATCHDOG_PID")? != pid {
+            return Ok(None);
+        }
+    }
+    let usec: u64 = usec.parse().context("parse WATCHDOG_USEC")?;
+    Ok((usec > 0).then(|| Duration::from_micros(usec)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listen_fds_only_for_this_pid() {
+        assert_eq!(listen_fd_count(None, None, 42).unwrap(), 0);
+        assert_eq!(listen_fd_count(Some("41"), Some("1"), 42).unwrap(), 0);
+        assert_eq!(listen_fd_count(Some("42"), Some("1"), 42).unwrap(), 1);
+        assert!(listen_fd_count(Some("42"), Some("x"), 42).is_err());
+
+        assert_eq!(watchdog_interval(None, None, 42).unwrap(), None);
+        assert_eq!(
+            watchdog_interval(Some("41"), Some("1000"), 42).unwrap(),
+            None
+        );
+        assert_eq!(
+            watchdog_interval(Some("42"), Some("2000000"), 42).unwrap(),
+            Some(Duration::from_secs(2))
+        );
+    }
+
+    #[test]
+    fn test_notifications_reach_a_fake_notify_socket() {
+        let dir = std::env::temp_dir().join(format!("winewarden-notify-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notify.sock");
+        let manager = UnixDatagram::bind(&path).unwrap();
+        let notifier = Notifier {
+            target: Some(notify_addr(path.to_str().unwrap()).unwrap()),
+            watchdog: Some(Duration::from_secs(10)),
+        };
+        assert_eq!(notifier.watchdog_period(), Some(Duration::from_secs(5)));
+
+        let mut buffer = [0u8; 256];
+        notifier.ready("listening").unwrap();
+        let len = manager.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"READY=1\nSTATUS=listening");
+
+        notifier.watchdog().unwrap();
+        let len = manager.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"WATCHDOG=1");
+
+        notifier.stopping("waiting for 1 session").unwrap();
+        let len = manager.recv(&mut buffer).unwrap();
+        assert!(buffer[..len].starts_with(b"STOPPING=1\n"));
+
+        // Without NOTIFY_SOCKET every message is a no-op.
+        let silent = Notifier {
+            target: None,
+            watchdog: Some(Duration::from_secs(10)),
+        };
+        assert!(silent.ready("listening").is_ok());
+        assert_eq!(silent.watchdog_period(), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}